@@ -2,8 +2,11 @@ use crate::interval::Interval;
 use crate::ray::Ray;
 use crate::vec::Point3;
 
+/// Minimum size of any axis of a padded bounding box
+const MIN_THICKNESS: f64 = 0.0001;
+
 /// Axis-Aligned Bounding Box
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct AABB {
     x: Interval,
     y: Interval,
@@ -28,7 +31,7 @@ impl AABB {
             } else {
                 Interval::new(p2.y, p1.y)
             },
-            z: if p1.z < p1.z {
+            z: if p1.z < p2.z {
                 Interval::new(p1.z, p2.z)
             } else {
                 Interval::new(p2.z, p1.z)
@@ -52,6 +55,42 @@ impl AABB {
         }
     }
 
+    /// Corner with the smallest coordinates
+    pub fn min(&self) -> Point3 {
+        Point3::new(self.x.min, self.y.min, self.z.min)
+    }
+
+    /// Corner with the largest coordinates
+    pub fn max(&self) -> Point3 {
+        Point3::new(self.x.max, self.y.max, self.z.max)
+    }
+
+    /// Returns a copy of the box where every axis is at least `MIN_THICKNESS` thick, so
+    /// flat objects don't end up with zero-thickness slabs that `hit` would reject
+    pub fn pad_to_minimum(&self) -> Self {
+        let pad = |interval: &Interval| {
+            if interval.size() < MIN_THICKNESS {
+                interval.expand(MIN_THICKNESS)
+            } else {
+                interval.clone()
+            }
+        };
+        Self {
+            x: pad(&self.x),
+            y: pad(&self.y),
+            z: pad(&self.z),
+        }
+    }
+
+    /// A box is empty if any of its axes is empty
+    pub fn is_empty(&self) -> bool {
+        self.x.is_empty() || self.y.is_empty() || self.z.is_empty()
+    }
+
+    pub fn is_finite(&self) -> bool {
+        self.x.is_finite() && self.y.is_finite() && self.z.is_finite()
+    }
+
     pub fn axis_interval(&self, n: usize) -> &Interval {
         match n {
             0 => &self.x,
@@ -97,16 +136,100 @@ impl AABB {
         true
     }
 
+    /// Sum of the areas of the six faces
+    pub fn surface_area(&self) -> f64 {
+        let (dx, dy, dz) = (self.x.size(), self.y.size(), self.z.size());
+        2.0 * (dx * dy + dy * dz + dz * dx)
+    }
+
     pub fn longest_axis(&self) -> usize {
         if self.x.size() > self.y.size() {
-            if self.x.size() > self.z.size() {
-                return 0;
-            }
-            return 2;
+            if self.x.size() > self.z.size() { 0 } else { 2 }
         } else if self.y.size() > self.z.size() {
-            return 1;
+            1
         } else {
-            return 2;
+            2
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec::Vec3;
+
+    #[test]
+    fn from_points_accepts_any_pair_of_opposite_corners() {
+        let (min, max) = (Point3::new(-1.0, 2.0, -3.0), Point3::new(4.0, 5.0, 6.0));
+        let coords = |p: Point3| (p.x, p.y, p.z);
+        // Each bit picks which end of one axis the first corner is on
+        for corner in 0..8 {
+            let pick = |bit: usize, lo: f64, hi: f64| if corner & bit == 0 { lo } else { hi };
+            let p1 = Point3::new(
+                pick(1, min.x, max.x),
+                pick(2, min.y, max.y),
+                pick(4, min.z, max.z),
+            );
+            let p2 = min + max - p1;
+
+            let bbox = AABB::from_points(p1, p2);
+            assert_eq!(coords(bbox.min()), coords(min), "corner {corner}");
+            assert_eq!(coords(bbox.max()), coords(max), "corner {corner}");
+        }
+    }
+
+    #[test]
+    fn empty_infinite_and_normal_boxes() {
+        let empty = AABB::empty();
+        assert!(empty.is_empty());
+        assert!(!empty.is_finite());
+
+        let unit = AABB::from_points(Point3::zero(), Point3::new(1.0, 1.0, 1.0));
+        assert!(!unit.is_empty());
+        assert!(unit.is_finite());
+
+        // A flat box still holds points
+        let flat = AABB::from_points(Point3::zero(), Point3::new(1.0, 0.0, 1.0));
+        assert!(!flat.is_empty());
+
+        // A single empty axis empties the whole box
+        let one_empty_axis = AABB::new(
+            Interval::new(0.0, 1.0),
+            Interval::empty(),
+            Interval::new(0.0, 1.0),
+        );
+        assert!(one_empty_axis.is_empty());
+
+        let infinite = AABB::new(
+            Interval::new(f64::NEG_INFINITY, f64::INFINITY),
+            Interval::new(0.0, 1.0),
+            Interval::new(0.0, 1.0),
+        );
+        assert!(!infinite.is_empty());
+        assert!(!infinite.is_finite());
+
+        // Merging with the empty box leaves the other box as it was
+        let merged = AABB::from_boxes(&empty, &unit);
+        assert!(merged.is_finite());
+        let coords = |p: Point3| (p.x, p.y, p.z);
+        assert_eq!(coords(merged.min()), (0.0, 0.0, 0.0));
+        assert_eq!(coords(merged.max()), (1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn padding_only_thickens_thin_axes() {
+        let flat = AABB::from_points(Point3::new(0.0, 1.0, 0.0), Point3::new(2.0, 1.0, 3.0));
+        let padded = flat.pad_to_minimum();
+        for axis in [0, 2] {
+            let (before, after) = (flat.axis_interval(axis), padded.axis_interval(axis));
+            assert_eq!((before.min, before.max), (after.min, after.max));
+        }
+        let y = padded.axis_interval(1);
+        assert!(y.size() >= MIN_THICKNESS * 0.999);
+        assert!(y.min < 1.0 && y.max > 1.0);
+
+        // A ray grazing the flat box's plane is no longer rejected by a zero-width slab
+        let ray = Ray::new(Point3::new(1.0, 3.0, 1.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!(padded.hit(&ray, &Interval::new(0.0, f64::INFINITY)));
+    }
+}
@@ -1,4 +1,6 @@
+use std::cmp::Ordering;
 use std::sync::Arc;
+use std::thread;
 
 use crate::aabb::AABB;
 use crate::hittable::HitRecord;
@@ -7,73 +9,155 @@ use crate::hittable::HittableList;
 use crate::interval::Interval;
 use crate::ray::Ray;
 
-type BoxCompareFn = fn(&dyn Hittable, &dyn Hittable) -> bool;
+/// Spans shorter than this are built and sorted on the current thread
+const PARALLEL_MIN_SPAN: usize = 4096;
 
-fn box_compare(left: &dyn Hittable, right: &dyn Hittable, axis: usize) -> bool {
+fn box_compare(left: &dyn Hittable, right: &dyn Hittable, axis: usize) -> Ordering {
     let left_axis_interval = left.boundnig_box().axis_interval(axis);
     let right_axis_interval = right.boundnig_box().axis_interval(axis);
-    return left_axis_interval.min < right_axis_interval.min;
+    left_axis_interval.min.total_cmp(&right_axis_interval.min)
 }
 
-fn box_compare_x(left: &dyn Hittable, right: &dyn Hittable) -> bool {
-    box_compare(left, right, 0)
-}
+/// Stable merge sort on the boxes' minimum along `axis`, the halves are sorted on separate
+/// threads so the result is the same as a serial `sort_by`
+fn par_sort(objects: &mut [Arc<dyn Hittable>], axis: usize, threads: usize) {
+    if threads <= 1 || objects.len() < PARALLEL_MIN_SPAN {
+        objects.sort_by(|left, right| box_compare(left.as_ref(), right.as_ref(), axis));
+        return;
+    }
 
-fn box_compare_y(left: &dyn Hittable, right: &dyn Hittable) -> bool {
-    box_compare(left, right, 1)
+    let mid = objects.len() / 2;
+    let (left, right) = objects.split_at_mut(mid);
+    thread::scope(|scope| {
+        scope.spawn(|| par_sort(left, axis, threads / 2));
+        par_sort(right, axis, threads - threads / 2);
+    });
+
+    let (left, right) = objects.split_at(mid);
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+    let (mut i, mut j) = (0, 0);
+    while i < left.len() && j < right.len() {
+        // Ties take from the left half to keep the sort stable
+        if box_compare(right[j].as_ref(), left[i].as_ref(), axis) == Ordering::Less {
+            merged.push(Arc::clone(&right[j]));
+            j += 1;
+        } else {
+            merged.push(Arc::clone(&left[i]));
+            i += 1;
+        }
+    }
+    merged.extend_from_slice(&left[i..]);
+    merged.extend_from_slice(&right[j..]);
+    objects.clone_from_slice(&merged);
 }
 
-fn box_compare_z(left: &dyn Hittable, right: &dyn Hittable) -> bool {
-    box_compare(left, right, 2)
+/// Sorts `objects` along the axis with the cheapest surface area heuristic split and returns
+/// the index the span should be split at
+///
+/// ## Math
+/// ### Variables
+/// `S(B)` is the surface area of box `B`, `L` and `R` the boxes of the two halves, holding
+/// `N_L` and `N_R` objects
+/// ### Calculation
+/// The chance of a ray hitting a child is proportional to its surface area, so the expected
+/// cost of a split is `S(L)*N_L + S(R)*N_R`. Every split position along every axis is
+/// evaluated with prefix and suffix boxes over the sorted objects.
+/// ### Outcomes
+/// - The cheapest split wins, ties keeping the earliest axis and position
+/// - Spans with no finite cost (e.g. unbounded objects) fall back to the median on the z axis
+fn sah_split(objects: &mut [Arc<dyn Hittable>], threads: usize) -> usize {
+    let object_span = objects.len();
+    let mut best_cost = f64::INFINITY;
+    let mut best_axis = 2;
+    let mut best_index = object_span / 2;
+
+    let mut right_areas = vec![0.0; object_span];
+    for axis in 0..3 {
+        par_sort(objects, axis, threads);
+
+        let mut right_box = AABB::empty();
+        for i in (1..object_span).rev() {
+            right_box = AABB::from_boxes(&right_box, objects[i].boundnig_box());
+            right_areas[i] = right_box.surface_area();
+        }
+
+        let mut left_box = AABB::empty();
+        for i in 1..object_span {
+            left_box = AABB::from_boxes(&left_box, objects[i - 1].boundnig_box());
+            let cost =
+                left_box.surface_area() * i as f64 + right_areas[i] * (object_span - i) as f64;
+            if cost < best_cost {
+                best_cost = cost;
+                best_axis = axis;
+                best_index = i;
+            }
+        }
+    }
+
+    // The objects are left sorted on the last axis
+    if best_axis != 2 {
+        par_sort(objects, best_axis, threads);
+    }
+    best_index
 }
 
 /// Bounding Volume Hierarchy Node
 pub struct BVHNode {
     bbox: AABB,
     left: Arc<dyn Hittable>,
-    right: Arc<dyn Hittable>,
+    right: Option<Arc<dyn Hittable>>, // `None` for a leaf holding a single object
 }
 
 impl BVHNode {
-    pub fn new(hittable_list: &mut HittableList) -> BVHNode {
-        let end = hittable_list.get_objects().len();
-        BVHNode::new_span(hittable_list.get_objects(), 0, end)
-    }
-
-    fn new_span(objects: &mut Vec<Arc<dyn Hittable>>, start: usize, end: usize) -> BVHNode {
-        let mut bbox = AABB::empty();
-        objects
-            .iter()
-            .for_each(|object| bbox = AABB::from_boxes(&bbox, object.boundnig_box()));
-
-        let comparator: BoxCompareFn = match bbox.longest_axis() {
-            0 => box_compare_x,
-            1 => box_compare_y,
-            2 => box_compare_z,
-            _ => unreachable!(),
-        };
-        let object_span = end - start;
+    /// Builds the tree over its own copy of the list's objects, leaving the list's order as is
+    pub fn new(hittable_list: &HittableList) -> BVHNode {
+        BVHNode::from_objects(hittable_list.objects().to_vec())
+    }
+
+    /// Same tree as `new`, with the sorting and the subtrees spread over every core
+    pub fn par_new(hittable_list: &HittableList) -> BVHNode {
+        let mut objects = hittable_list.objects().to_vec();
+        BVHNode::new_span(&mut objects, num_cpus::get())
+    }
+
+    /// Builds the tree over `objects`, which is only used as scratch space for sorting
+    pub fn from_objects(mut objects: Vec<Arc<dyn Hittable>>) -> BVHNode {
+        BVHNode::new_span(&mut objects, 1)
+    }
+
+    fn new_span(objects: &mut [Arc<dyn Hittable>], threads: usize) -> BVHNode {
+        let bbox = objects.iter().fold(AABB::empty(), |bbox, object| {
+            AABB::from_boxes(&bbox, object.boundnig_box())
+        });
 
-        let left;
-        let right;
-        if object_span == 1 {
-            left = Arc::clone(&objects[start]);
-            right = Arc::clone(&objects[start]);
+        let left: Arc<dyn Hittable>;
+        let right: Option<Arc<dyn Hittable>>;
+        let object_span = objects.len();
+        if object_span == 0 {
+            // Nothing to hit, the empty box rejects every ray
+            left = Arc::new(HittableList::new());
+            right = None;
+        } else if object_span == 1 {
+            left = Arc::clone(&objects[0]);
+            right = None;
         } else if object_span == 2 {
-            left = Arc::clone(&objects[start]);
-            right = Arc::clone(&objects[start + 1]);
+            left = Arc::clone(&objects[0]);
+            right = Some(Arc::clone(&objects[1]));
         } else {
-            objects[start..end].sort_by(|left, right| {
-                if comparator(left.as_ref(), right.as_ref()) {
-                    std::cmp::Ordering::Less
-                } else {
-                    std::cmp::Ordering::Greater
-                }
-            });
-
-            let mid = (start + end) / 2;
-            left = Arc::new(BVHNode::new_span(objects, start, mid));
-            right = Arc::new(BVHNode::new_span(objects, mid, end));
+            let split = sah_split(objects, threads);
+            let (left_objects, right_objects) = objects.split_at_mut(split);
+            if threads > 1 && object_span >= PARALLEL_MIN_SPAN {
+                let (left_node, right_node) = thread::scope(|scope| {
+                    let left_handle = scope.spawn(|| BVHNode::new_span(left_objects, threads / 2));
+                    let right_node = BVHNode::new_span(right_objects, threads - threads / 2);
+                    (left_handle.join().unwrap(), right_node)
+                });
+                left = Arc::new(left_node);
+                right = Some(Arc::new(right_node));
+            } else {
+                left = Arc::new(BVHNode::new_span(left_objects, 1));
+                right = Some(Arc::new(BVHNode::new_span(right_objects, 1)));
+            }
         }
 
         BVHNode { bbox, left, right }
@@ -87,17 +171,144 @@ impl Hittable for BVHNode {
         }
 
         let left_hit_record = self.left.hit(ray, ray_t.clone());
+        let Some(right) = &self.right else {
+            return left_hit_record;
+        };
         let interval = if let Some(HitRecord { t, .. }) = left_hit_record {
             Interval::new(ray_t.min, t)
         } else {
             Interval::new(ray_t.min, ray_t.max)
         };
-        let right_hit_record = self.right.hit(ray, interval);
+        let right_hit_record = right.hit(ray, interval);
 
-        right_hit_record.or_else(|| left_hit_record)
+        right_hit_record.or(left_hit_record)
     }
 
     fn boundnig_box(&self) -> &AABB {
         &self.bbox
     }
+
+    fn occluded(&self, ray: &Ray, ray_t: Interval) -> bool {
+        self.bbox.hit(ray, &ray_t)
+            && (self.left.occluded(ray, ray_t.clone())
+                || self
+                    .right
+                    .as_ref()
+                    .is_some_and(|right| right.occluded(ray, ray_t)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering as AtomicOrdering;
+
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::sphere::Sphere;
+    use crate::vec::Color3;
+    use crate::vec::Point3;
+    use crate::vec::Vec3;
+
+    #[test]
+    fn empty_list_builds_a_tree_that_hits_nothing() {
+        let ray = Ray::new(Point3::zero(), Vec3::new(0.0, 0.0, -1.0));
+        for bvh in [
+            BVHNode::new(&HittableList::new()),
+            BVHNode::par_new(&HittableList::new()),
+        ] {
+            assert!(bvh.boundnig_box().is_empty());
+            assert!(bvh.hit(&ray, Interval::new(0.001, f64::INFINITY)).is_none());
+            assert!(!bvh.occluded(&ray, Interval::new(0.001, f64::INFINITY)));
+        }
+    }
+
+    fn spheres(count: usize) -> HittableList {
+        let material = Arc::new(Lambertian::new(Color3::new(0.5, 0.5, 0.5)));
+        let mut list = HittableList::new();
+        // Spread along X in an order the tree has to sort
+        for k in 0..count {
+            let x = ((k * 7) % count) as f64 * 3.0;
+            list.add(Sphere::new(Point3::new(x, 0.0, 0.0), 1.0, material.clone()));
+        }
+        list
+    }
+
+    #[test]
+    fn building_leaves_the_list_order_alone() {
+        let list = spheres(9);
+        let before = list.objects().to_vec();
+        let bvh = BVHNode::new(&list);
+        let par_bvh = BVHNode::par_new(&list);
+        assert!(
+            before
+                .iter()
+                .zip(list.objects())
+                .all(|(a, b)| Arc::ptr_eq(a, b))
+        );
+
+        // Along +X from the left, the nearest sphere is the one at x = 0
+        let ray = Ray::new(Point3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        for tree in [&bvh, &par_bvh] {
+            let hit_record = tree.hit(&ray, Interval::new(0.001, f64::INFINITY)).unwrap();
+            assert_eq!(hit_record.t, 4.0);
+        }
+    }
+
+    /// Counts the calls to the wrapped object's `hit`
+    struct CountingHits {
+        object: Sphere,
+        hits: AtomicUsize,
+    }
+
+    impl Hittable for CountingHits {
+        fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord> {
+            self.hits.fetch_add(1, AtomicOrdering::Relaxed);
+            self.object.hit(ray, ray_t)
+        }
+
+        fn boundnig_box(&self) -> &AABB {
+            self.object.boundnig_box()
+        }
+    }
+
+    #[test]
+    fn leaves_test_their_object_once() {
+        let material = Arc::new(Lambertian::new(Color3::new(0.5, 0.5, 0.5)));
+        let counters: Vec<Arc<CountingHits>> = (0..5)
+            .map(|k| {
+                let center = Point3::new(k as f64 * 3.0, 0.0, 0.0);
+                Arc::new(CountingHits {
+                    object: Sphere::new(center, 1.0, material.clone()),
+                    hits: AtomicUsize::new(0),
+                })
+            })
+            .collect();
+        let mut list = HittableList::new();
+        for counter in &counters {
+            list.add_shared(counter.clone());
+        }
+        let bvh = BVHNode::new(&list);
+
+        let hits = |counter: &CountingHits| counter.hits.load(AtomicOrdering::Relaxed);
+        // Straight down onto each isolated sphere in turn
+        for (k, counter) in counters.iter().enumerate() {
+            let before = hits(counter);
+            let origin = Point3::new(k as f64 * 3.0, 5.0, 0.0);
+            let ray = Ray::new(origin, Vec3::new(0.0, -1.0, 0.0));
+            assert!(bvh.hit(&ray, Interval::new(0.001, f64::INFINITY)).is_some());
+            assert_eq!(hits(counter) - before, 1);
+        }
+        // Siblings sharing a node may be tested as well, but never the whole scene
+        let total: usize = counters.iter().map(|counter| hits(counter)).sum();
+        assert!(total < 2 * counters.len());
+
+        let single = BVHNode::new(&spheres(1));
+        let ray = Ray::new(Point3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!(
+            single
+                .hit(&ray, Interval::new(0.001, f64::INFINITY))
+                .is_some()
+        );
+    }
 }
@@ -1,17 +1,201 @@
-use std::fs::File;
+use std::f64::consts::PI;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::io::Read;
 use std::io::Write;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
 use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
 
+use crate::denoise::bilateral;
+use crate::hittable::HitRecord;
 use crate::hittable::Hittable;
-use crate::hittable::HittableList;
+use crate::image::RgbImage;
 use crate::interval::Interval;
+use crate::light::Light;
+use crate::pdf::HittablePdf;
+use crate::pdf::MixturePdf;
+use crate::pdf::Pdf;
+use crate::png::ColorProfile;
 use crate::ray::Ray;
+use crate::sphere::Sphere;
+use crate::sun::SunLight;
+use crate::texture::ImageTexture;
+use crate::texture::Texture;
+use crate::utils::format_utc;
+use crate::utils::hash_u64;
 use crate::utils::random_percentage;
+use crate::utils::rng_seed;
+use crate::utils::seed_thread_rng;
 use crate::vec::Color3;
 use crate::vec::Point3;
+use crate::vec::ToneMap;
 use crate::vec::Vec3;
 
+pub use crate::image::Output;
+pub use crate::image::OutputFormat;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    Perspective,
+    /// Parallel rays along the view direction, `scale` being the height of the view in world
+    /// units. Depth of field is ignored.
+    Orthographic {
+        scale: f64,
+    },
+}
+
+/// What `render` computes for every pixel
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum RenderMode {
+    /// Full path tracing
+    #[default]
+    Shaded,
+    /// Outward surface normal at the first hit mapped to RGB with `0.5 * (normal + 1)`
+    Normals,
+    /// Distance to the first hit as grayscale, white at `near` fading to black at `far`
+    Depth { near: f64, far: f64 },
+    /// Only the emission of lights over the background, every other surface is see-through.
+    /// Meant for placing lights before a full render.
+    LightsOnly,
+}
+
+/// Reconstruction filter shaping where samples land around the pixel center, in pixels
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PixelFilter {
+    /// Uniform over the pixel's square, `[-0.5, 0.5)` on each axis
+    #[default]
+    Box,
+    /// Triangle peaking at the pixel center and falling to zero at the pixel's edges,
+    /// `[-0.5, 0.5]` on each axis
+    Tent,
+    /// Normal distribution with standard deviation `sigma`, cut off at `3 * sigma`
+    Gaussian { sigma: f64 },
+}
+
+impl PixelFilter {
+    /// Warps `u`, `v` uniform within `[0, 1)` into an offset from the pixel center distributed
+    /// following the filter. Stratified inputs stay stratified since every warp is monotonic
+    /// or, for `Gaussian`, continuous.
+    ///
+    /// ## Math
+    /// - Tent: inverting the CDF of the triangle `1 - |x|` over `[-1, 1]` gives `sqrt(2u) - 1`
+    ///   for `u < 0.5` and `1 - sqrt(2 - 2u)` otherwise, halved to fit the pixel
+    /// - Gaussian: Box–Muller, `r = sigma * sqrt(-2 ln(1 - u))`, `φ = 2π*v`, offset
+    ///   `(r cos φ, r sin φ)`
+    fn offset(&self, u: f64, v: f64) -> (f64, f64) {
+        match *self {
+            PixelFilter::Box => (u - 0.5, v - 0.5),
+            PixelFilter::Tent => {
+                let tent = |u: f64| {
+                    let x = if u < 0.5 {
+                        (2.0 * u).sqrt() - 1.0
+                    } else {
+                        1.0 - (2.0 - 2.0 * u).sqrt()
+                    };
+                    0.5 * x
+                };
+                (tent(u), tent(v))
+            }
+            PixelFilter::Gaussian { sigma } => {
+                let r = sigma * (-2.0 * (1.0 - u).ln()).sqrt();
+                let phi = 2.0 * std::f64::consts::PI * v;
+                let limit = 3.0 * sigma;
+                (
+                    (r * phi.cos()).clamp(-limit, limit),
+                    (r * phi.sin()).clamp(-limit, limit),
+                )
+            }
+        }
+    }
+}
+
+/// Computes the color seen by a ray that misses every object
+pub type BackgroundFn = Box<dyn Fn(&Ray) -> Color3 + Send + Sync>;
+
+/// Computes the value of pixel i, j, see `Camera::render_tiles`
+type PixelShader<T> = fn(&Camera, usize, usize, &dyn Hittable) -> T;
+
+/// Index of a tile, tiles being numbered row by row from the top left
+pub type TileId = usize;
+
+/// Tile id, linear pixels row by row and the samples per pixel they average, see
+/// `Camera::export_tiles`
+pub type RenderedTile = (TileId, Vec<Color3>, usize);
+
+/// What rays missing every object see
+#[derive(Default)]
+pub enum Background {
+    /// White to light blue gradient, see `Camera::sky_gradient`
+    #[default]
+    Gradient,
+    Solid(Color3),
+    /// Two flat colors split at the horizon, `sky` for rays going up and `ground` for rays
+    /// going down. Handy to tell shading artifacts from sky lighting.
+    Studio {
+        sky: Color3,
+        ground: Color3,
+    },
+    /// Equirectangular image wrapped around the scene, e.g. an HDRI loaded from a `.hdr` file.
+    /// Directions map to the image like points on a `Sphere` map to its texture.
+    EnvironmentMap(Arc<ImageTexture>),
+    Function(BackgroundFn),
+}
+
+impl Background {
+    /// Radiance carried by `ray` after it missed every object
+    pub fn radiance(&self, ray: &Ray) -> Color3 {
+        match self {
+            Background::Gradient => Camera::sky_gradient(ray),
+            Background::Solid(color) => *color,
+            Background::Studio { sky, ground } => {
+                if ray.dir.y >= 0.0 {
+                    *sky
+                } else {
+                    *ground
+                }
+            }
+            Background::EnvironmentMap(image) => {
+                let dir = ray.dir.unit();
+                let (u, v) = Sphere::get_uv(&dir);
+                image.value(u, v, &dir)
+            }
+            Background::Function(background_fn) => background_fn(ray),
+        }
+    }
+}
+
+impl From<Color3> for Background {
+    fn from(color: Color3) -> Self {
+        Background::Solid(color)
+    }
+}
+
+impl fmt::Debug for Background {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Background::Gradient => write!(f, "Gradient"),
+            Background::Solid(color) => f.debug_tuple("Solid").field(color).finish(),
+            Background::Studio { sky, ground } => f
+                .debug_struct("Studio")
+                .field("sky", sky)
+                .field("ground", ground)
+                .finish(),
+            Background::EnvironmentMap(image) => {
+                f.debug_tuple("EnvironmentMap").field(image).finish()
+            }
+            Background::Function(_) => write!(f, "Function(..)"),
+        }
+    }
+}
+
 pub struct Camera {
     image_width: usize,
     image_height: usize,
@@ -19,38 +203,216 @@ pub struct Camera {
     pixel00_loc: Point3,
     pixel_delta_u: Vec3,
     pixel_delta_v: Vec3,
-    samples_per_pixel: usize,
+    sqrt_spp: usize, // Pixels are sampled on a `sqrt_spp × sqrt_spp` grid of sub-cells
+    recip_sqrt_spp: f64,
     pixel_sample_scale: f64,
     max_depth: usize,
     defocus_angle: f64,   // Varaition angle of rays through each pixel
     defocus_disk_u: Vec3, // Defocus disk horizontal radius
     defocus_disk_v: Vec3, // Defocus disk vertical radius
     enable_motion_blur: bool,
+    shutter_open: f64, // Ray times are sampled within `[shutter_open, shutter_close)`
+    shutter_close: f64,
+    background: Background,
+    sun: Option<SunLight>,
+    lights: Vec<Light>, // Sampled with shadow rays at every diffuse hit
+    light_shapes: Option<Arc<dyn Hittable>>, // Emissive objects scattered rays are aimed at
+    report_overexposure: bool,
+    show_progress: bool,
+    exposure: f64,                       // Linear brightness multiplier
+    exposure_ev: f64, // Exposure compensation in stops, applied on top of `exposure`
+    denoise: Option<(f64, f64)>, // Bilateral filter sigmas, spatial then color
+    aov_output: Option<PathBuf>, // Directory the albedo and normal images are written to
+    light_group_output: Option<PathBuf>, // Directory the per-light images are written to
+    embed_metadata: bool,
+    embed_color_profile: bool,
+    output: Output,
+    output_format: OutputFormat,
+    branch_factor: usize, // Number of rays scattered at the branching bounce
+    branch_depth: usize,  // Bounce at which paths branch, 0 being the primary hit
+    focus_dist: f64,
+    vfov: f64,
+    projection: Projection,
+    forward: Vec3, // View direction scaled to reach the focal plane, `focus_dist * -w`
+    focus_peaking: Option<f64>, // Depth tolerance around the focal plane to highlight
+    russian_roulette: bool,
+    russian_roulette_min_bounces: usize, // Bounces always followed before a path may be ended
+    tone_map: ToneMap,
+    gamma: f64,             // Output encoding gamma, 0 or less for linear output
+    threads: Option<usize>, // Render worker count, `None` for one per core
+    tile_size: usize,       // Side of the square tiles workers render at a time
+    render_mode: RenderMode,
+    pixel_filter: PixelFilter,
 }
 
-impl Camera {
-    pub fn new(
-        aspect_ratio: f64,
-        image_width: usize,
-        samples_per_pixel: usize,
-        max_depth: usize,
-        vfov: f64,
-        look_from: Point3, // Point camera is looking from
-        look_at: Point3,   // Point camera is looking at
-        v_up: Vec3,        // Camera relative "up" direction
-        defocus_angle: f64,
-        focus_dist: f64, // Distance from camera lookfrom point to plane of perfect focus
-        enable_motion_blur: bool,
-    ) -> Camera {
+/// Named, chainable alternative to `Camera::new`
+#[derive(Debug, Clone)]
+pub struct CameraBuilder {
+    aspect_ratio: f64,
+    image_width: usize,
+    samples_per_pixel: usize,
+    max_depth: usize,
+    vfov: f64,
+    look_from: Point3,
+    look_at: Point3,
+    v_up: Vec3,
+    defocus_angle: f64,
+    focus_dist: f64,
+    enable_motion_blur: bool,
+    shutter_open: f64,
+    shutter_close: f64,
+    projection: Projection,
+    russian_roulette_min_bounces: usize,
+}
+
+impl Default for CameraBuilder {
+    fn default() -> Self {
+        Self {
+            aspect_ratio: 16.0 / 9.0,
+            image_width: 400,
+            samples_per_pixel: 100,
+            max_depth: 50,
+            vfov: 40.0,
+            look_from: Point3::zero(),
+            look_at: Point3::new(0.0, 0.0, -1.0),
+            v_up: Vec3::new(0.0, 1.0, 0.0),
+            defocus_angle: 0.0,
+            focus_dist: 10.0,
+            enable_motion_blur: false,
+            shutter_open: 0.0,
+            shutter_close: 1.0,
+            projection: Projection::Perspective,
+            russian_roulette_min_bounces: 3,
+        }
+    }
+}
+
+impl CameraBuilder {
+    pub fn aspect_ratio(mut self, aspect_ratio: f64) -> Self {
+        self.aspect_ratio = aspect_ratio;
+        self
+    }
+
+    pub fn image_width(mut self, image_width: usize) -> Self {
+        self.image_width = image_width;
+        self
+    }
+
+    /// Rounded down to the nearest square so samples can be stratified over a grid
+    pub fn samples_per_pixel(mut self, samples_per_pixel: usize) -> Self {
+        self.samples_per_pixel = samples_per_pixel;
+        self
+    }
+
+    /// Maximum number of times a ray will bounce
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Vertical field of view in degrees
+    pub fn vfov(mut self, vfov: f64) -> Self {
+        self.vfov = vfov;
+        self
+    }
+
+    /// Point camera is looking from
+    pub fn look_from(mut self, look_from: Point3) -> Self {
+        self.look_from = look_from;
+        self
+    }
+
+    /// Point camera is looking at
+    pub fn look_at(mut self, look_at: Point3) -> Self {
+        self.look_at = look_at;
+        self
+    }
+
+    /// Camera relative "up" direction
+    pub fn v_up(mut self, v_up: Vec3) -> Self {
+        self.v_up = v_up;
+        self
+    }
+
+    /// Variation angle of rays through each pixel in degrees, 0 disables depth of field
+    pub fn defocus_angle(mut self, defocus_angle: f64) -> Self {
+        self.defocus_angle = defocus_angle;
+        self
+    }
+
+    /// Distance from the look from point to the plane of perfect focus
+    pub fn focus_dist(mut self, focus_dist: f64) -> Self {
+        self.focus_dist = focus_dist;
+        self
+    }
+
+    pub fn enable_motion_blur(mut self, enable_motion_blur: bool) -> Self {
+        self.enable_motion_blur = enable_motion_blur;
+        self
+    }
+
+    /// Time the shutter opens at, 0 by default. Moving objects are at their start position at
+    /// time 0 and at their end position at time 1.
+    pub fn shutter_open(mut self, shutter_open: f64) -> Self {
+        self.shutter_open = shutter_open;
+        self
+    }
+
+    /// Time the shutter closes at, 1 by default
+    pub fn shutter_close(mut self, shutter_close: f64) -> Self {
+        self.shutter_close = shutter_close;
+        self
+    }
+
+    /// `Projection::Perspective` by default, `vfov` and the defocus settings only apply to it
+    pub fn projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self
+    }
+
+    /// Bounces always followed before Russian roulette may end a path, 3 by default. Higher
+    /// values keep the first, most important bounces free of roulette noise at the cost of
+    /// render time.
+    pub fn russian_roulette_min_bounces(mut self, russian_roulette_min_bounces: usize) -> Self {
+        self.russian_roulette_min_bounces = russian_roulette_min_bounces;
+        self
+    }
+
+    pub fn build(self) -> Camera {
+        let CameraBuilder {
+            aspect_ratio,
+            image_width,
+            samples_per_pixel,
+            max_depth,
+            vfov,
+            look_from,
+            look_at,
+            v_up,
+            defocus_angle,
+            focus_dist,
+            enable_motion_blur,
+            shutter_open,
+            shutter_close,
+            projection,
+            russian_roulette_min_bounces,
+        } = self;
+        assert!(shutter_open <= shutter_close);
+
         let image_height = ((image_width as f64 / aspect_ratio) as usize).max(1);
         let aspect_ratio = image_width as f64 / image_height as f64;
 
         let center = look_from;
+        let sqrt_spp = (samples_per_pixel as f64).sqrt().max(1.0) as usize;
 
         // Camera
-        let theta = vfov.to_radians();
-        let h = (theta / 2.0).tan();
-        let viewport_height = 2.0 * h * focus_dist;
+        let viewport_height = match projection {
+            Projection::Perspective => {
+                let theta = vfov.to_radians();
+                let h = (theta / 2.0).tan();
+                2.0 * h * focus_dist
+            }
+            Projection::Orthographic { scale } => scale,
+        };
         let viewport_width = viewport_height * aspect_ratio;
 
         let w = (look_from - look_at).unit(); // Unit vector pointing to the opposite of view direction (since right-hand coordinates are used)
@@ -59,7 +421,7 @@ impl Camera {
 
         // Calculate the vectors accross the horizontal and down the vertical viewport edges
         let viewport_u = viewport_width * u;
-        let viewport_v = viewport_height * v.negate();
+        let viewport_v = viewport_height * -v;
 
         // Calculate the horizontal and vertical delta vectors from pixel to pixel
         let pixel_delta_u = viewport_u / image_width as f64;
@@ -81,111 +443,1824 @@ impl Camera {
             pixel00_loc,
             pixel_delta_u,
             pixel_delta_v,
-            samples_per_pixel,
-            pixel_sample_scale: 1.0 / (samples_per_pixel as f64),
+            sqrt_spp,
+            recip_sqrt_spp: 1.0 / sqrt_spp as f64,
+            pixel_sample_scale: 1.0 / (sqrt_spp * sqrt_spp) as f64,
             max_depth,
             defocus_angle,
             defocus_disk_u,
             defocus_disk_v,
             enable_motion_blur,
+            shutter_open,
+            shutter_close,
+            background: Background::Gradient,
+            sun: None,
+            lights: vec![],
+            light_shapes: None,
+            report_overexposure: false,
+            show_progress: true,
+            exposure: 1.0,
+            exposure_ev: 0.0,
+            denoise: None,
+            aov_output: None,
+            light_group_output: None,
+            embed_metadata: false,
+            embed_color_profile: false,
+            output: Output::from("image.ppm"),
+            output_format: OutputFormat::Ppm,
+            branch_factor: 1,
+            branch_depth: 0,
+            focus_dist,
+            vfov,
+            projection,
+            forward: -(focus_dist * w),
+            focus_peaking: None,
+            russian_roulette: false,
+            russian_roulette_min_bounces,
+            tone_map: ToneMap::Clamp,
+            gamma: 2.0,
+            threads: None,
+            tile_size: 32,
+            render_mode: RenderMode::Shaded,
+            pixel_filter: PixelFilter::Box,
         }
     }
+}
 
-    pub fn render(self: Arc<Self>, objects: Arc<dyn Hittable>) {
-        println!("Writing image to file");
-        let mut image_data = String::new();
-        image_data.push_str(&format!(
-            "P3\n{} {}\n255\n",
-            self.image_width, self.image_height
-        ));
+impl Camera {
+    /// Starts a `CameraBuilder` with every setting at its default
+    pub fn builder() -> CameraBuilder {
+        CameraBuilder::default()
+    }
 
-        let thread_count = num_cpus::get().saturating_sub(4).max(1); // Using only 20 cores out of 24 that I have
-        let batch_size = self.image_height / thread_count;
-        let last_batch_size = self.image_height - batch_size * (thread_count - 1);
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        aspect_ratio: f64,
+        image_width: usize,
+        samples_per_pixel: usize,
+        max_depth: usize,
+        vfov: f64,
+        look_from: Point3, // Point camera is looking from
+        look_at: Point3,   // Point camera is looking at
+        v_up: Vec3,        // Camera relative "up" direction
+        defocus_angle: f64,
+        focus_dist: f64, // Distance from camera lookfrom point to plane of perfect focus
+        enable_motion_blur: bool,
+    ) -> Camera {
+        Camera::builder()
+            .aspect_ratio(aspect_ratio)
+            .image_width(image_width)
+            .samples_per_pixel(samples_per_pixel)
+            .max_depth(max_depth)
+            .vfov(vfov)
+            .look_from(look_from)
+            .look_at(look_at)
+            .v_up(v_up)
+            .defocus_angle(defocus_angle)
+            .focus_dist(focus_dist)
+            .enable_motion_blur(enable_motion_blur)
+            .build()
+    }
 
-        let mut thread_handles = Vec::new();
-        for t in 0..thread_count {
-            let batch_start = t * batch_size;
-            let batch_end = if t == thread_count - 1 {
-                batch_start + last_batch_size
-            } else {
-                batch_start + batch_size
-            };
+    /// What rays missing every object see, the sky gradient by default. A plain `Color3` sets a
+    /// solid color.
+    pub fn set_background(&mut self, background: impl Into<Background>) {
+        self.background = background.into();
+    }
+
+    /// Overrides the sky with a custom function of the missed ray
+    pub fn set_background_fn(
+        &mut self,
+        background_fn: impl Fn(&Ray) -> Color3 + Send + Sync + 'static,
+    ) {
+        self.background = Background::Function(Box::new(background_fn));
+    }
+
+    /// Adds a visible sun disk on top of whichever background is in use. Like `light_shapes`,
+    /// part of the rays scattered off surfaces that provide a `pdf` are aimed at it.
+    pub fn set_sun(&mut self, sun: SunLight) {
+        self.sun = Some(sun);
+    }
+
+    /// Adds a light sampled directly at every diffuse hit, on top of the light paths find by
+    /// bouncing around
+    pub fn add_light(&mut self, light: Light) {
+        self.lights.push(light);
+    }
+
+    /// Aims half of the rays scattered off surfaces that provide a `pdf` (e.g. `Lambertian`) at
+    /// `light_shapes`, which should hold the scene's small emissive quads and spheres. The
+    /// objects must also be part of the scene for their light to be picked up.
+    pub fn set_light_shapes(&mut self, light_shapes: Arc<dyn Hittable>) {
+        self.light_shapes = Some(light_shapes);
+    }
+
+    /// Reports the share of pixels whose luminance exceeds 1.0 before clamping and writes a
+    /// heatmap of them next to the image, `<stem>.overexposure.<ext>`. There's no heatmap when
+    /// the image goes to stdout.
+    pub fn set_report_overexposure(&mut self, report_overexposure: bool) {
+        self.report_overexposure = report_overexposure;
+    }
+
+    /// Prints the remaining tiles and an estimate of the time left while rendering, on by default
+    pub fn set_progress(&mut self, show_progress: bool) {
+        self.show_progress = show_progress;
+    }
+
+    /// Multiplies the linear radiance of every pixel by `exposure` before tone mapping, 1 by
+    /// default. Combines with `set_exposure_ev`.
+    pub fn set_exposure(&mut self, exposure: f64) {
+        assert!(exposure >= 0.0);
+        self.exposure = exposure;
+    }
+
+    /// Runs an edge-aware bilateral filter over the rendered image to hide noise at low sample
+    /// counts, see `denoise::bilateral`. `sigma_spatial` is in pixels and `sigma_color` in
+    /// linear color units, off by default.
+    pub fn set_denoise(&mut self, sigma_spatial: f64, sigma_color: f64) {
+        assert!(sigma_spatial > 0.0 && sigma_color > 0.0);
+        self.denoise = Some((sigma_spatial, sigma_color));
+    }
+
+    /// Writes the render settings (size, samples, depth, seed and date) as `#` comments in the
+    /// header of PPM outputs, off by default. PNG outputs are unaffected.
+    pub fn set_embed_metadata(&mut self, embed_metadata: bool) {
+        self.embed_metadata = embed_metadata;
+    }
+
+    /// Writes `sRGB`/`gAMA` chunks matching the output gamma (see `set_gamma`) into PNG outputs,
+    /// off by default. Color-managed viewers otherwise have to guess the encoding.
+    pub fn set_embed_color_profile(&mut self, embed_color_profile: bool) {
+        self.embed_color_profile = embed_color_profile;
+    }
+
+    /// Also writes first-hit albedo and normal images (AOVs) to `albedo.<ext>` and
+    /// `normal.<ext>` in the directory `dir`, created if missing, when `render` runs
+    ///
+    /// Albedo comes from `Material::albedo` and is gamma encoded like the image, normals are
+    /// the outward normals mapped from `[-1, 1]` to `[0, 1]` and written linearly. Rays that
+    /// miss everything are black in both.
+    pub fn set_aov_output(&mut self, dir: impl Into<PathBuf>) {
+        self.aov_output = Some(dir.into());
+    }
+
+    /// Splits the image by light when `render` runs: `light_<i>.<ext>` holds what the `i`-th
+    /// light added with `add_light` contributes through direct lighting, and `light_other.<ext>`
+    /// everything else (emissive objects, the background and the sun). They are written to the
+    /// directory `dir`, created if missing.
+    ///
+    /// The images are exposed like the image but written linearly without tone mapping, so
+    /// adding them up gives back the image before tone mapping, gamma and denoising. Scaling
+    /// one of them relights the scene with that light's intensity scaled.
+    pub fn set_light_group_output(&mut self, dir: impl Into<PathBuf>) {
+        self.light_group_output = Some(dir.into());
+    }
+
+    /// Scales the linear radiance of every pixel by `2^exposure_ev` before it is written,
+    /// so +1 doubles the brightness and -1 halves it
+    pub fn set_exposure_ev(&mut self, exposure_ev: f64) {
+        self.exposure_ev = exposure_ev;
+    }
+
+    /// Where and in which format `render` writes the image, `image.ppm` as PPM by default.
+    /// Takes a file path or an `Output`.
+    pub fn set_output(&mut self, output: impl Into<Output>, format: OutputFormat) {
+        self.output = output.into();
+        self.output_format = format;
+    }
+
+    /// Scatters `branch_factor` rays instead of one at bounce `branch_depth` (0 being the
+    /// primary hit) and averages them, every other bounce keeps following a single ray
+    pub fn set_branching(&mut self, branch_factor: usize, branch_depth: usize) {
+        assert!(branch_factor >= 1);
+        self.branch_factor = branch_factor;
+        self.branch_depth = branch_depth;
+    }
+
+    /// Debug overlay for depth of field: pixels whose primary hit lies within `tolerance` of
+    /// the focal plane are painted red and the rest of the image is dimmed
+    pub fn set_focus_peaking(&mut self, tolerance: f64) {
+        assert!(tolerance >= 0.0);
+        self.focus_peaking = Some(tolerance);
+    }
+
+    /// Randomly ends dim paths after a few bounces instead of always following them to
+    /// `max_depth`, surviving paths are boosted to keep the image unbiased. Off by default, since
+    /// it trades extra noise for speed.
+    pub fn set_russian_roulette(&mut self, russian_roulette: bool) {
+        self.russian_roulette = russian_roulette;
+    }
+
+    /// How linear radiance is brought into the displayable range, plain clamping by default
+    pub fn set_tone_map(&mut self, tone_map: ToneMap) {
+        self.tone_map = tone_map;
+    }
+
+    /// Gamma the written image is encoded with, 2.0 by default. A value of 0 or less writes
+    /// linear values.
+    pub fn set_gamma(&mut self, gamma: f64) {
+        self.gamma = gamma;
+    }
+
+    /// Replaces path tracing with one of the debug views, see `RenderMode`. Rays missing every
+    /// object still show the background.
+    pub fn set_render_mode(&mut self, render_mode: RenderMode) {
+        if let RenderMode::Depth { near, far } = render_mode {
+            assert!(near < far);
+        }
+        self.render_mode = render_mode;
+    }
+
+    /// Distribution of the samples around each pixel center, `PixelFilter::Box` by default
+    pub fn set_pixel_filter(&mut self, pixel_filter: PixelFilter) {
+        if let PixelFilter::Gaussian { sigma } = pixel_filter {
+            assert!(sigma > 0.0);
+        }
+        self.pixel_filter = pixel_filter;
+    }
+
+    /// Number of threads `render` uses, `None` (the default) for one per available core
+    pub fn set_threads(&mut self, threads: Option<usize>) {
+        assert!(threads != Some(0));
+        self.threads = threads;
+    }
+
+    /// Side in pixels of the square tiles the image is split into, 32 by default. Tiles on the
+    /// right and bottom edges are cut to fit the image.
+    pub fn set_tile_size(&mut self, tile_size: usize) {
+        assert!(tile_size > 0);
+        self.tile_size = tile_size;
+    }
+
+    /// Renders `objects` and writes the image to the output, failing only on I/O errors
+    pub fn render(self: Arc<Self>, objects: Arc<dyn Hittable>) -> io::Result<()> {
+        // Stdout carries the image itself, so status messages move to stderr
+        let status = |message: &str| match self.output {
+            Output::Stdout => eprintln!("{message}"),
+            Output::File(_) => println!("{message}"),
+        };
+
+        status("Writing image to file");
+        let (pixels, light_groups) = match self.light_group_output {
+            Some(_) => {
+                let light_groups = self.render_light_groups(&objects);
+                let pixels = (0..self.image_width * self.image_height)
+                    .map(|k| {
+                        light_groups
+                            .iter()
+                            .fold(Color3::zero(), |sum, group| sum + group[k])
+                    })
+                    .collect();
+                (self.post_process(pixels), light_groups)
+            }
+            None => (self.render_pixels(Arc::clone(&objects)), vec![]),
+        };
+        let mut image = self.to_image(&pixels);
+        if self.embed_metadata {
+            for comment in self.metadata() {
+                image.add_comment(comment);
+            }
+        }
+        image.save_to(&self.output, self.output_format)?;
+
+        if self.report_overexposure {
+            let overexposed = pixels.iter().filter(|p| p.luminance() > 1.0).count();
+            status(&format!(
+                "{:.1}% of pixels overexposed",
+                100.0 * overexposed as f64 / pixels.len() as f64
+            ));
+
+            match self.heatmap_path() {
+                Some(heatmap_path) => {
+                    // Pixels are shaded by how far above 1.0 their luminance goes, saturating
+                    // at 2.0
+                    let heatmap: Vec<Color3> = pixels
+                        .iter()
+                        .map(|p| Color3::new((p.luminance() - 1.0).clamp(0.0, 1.0), 0.0, 0.0))
+                        .collect();
+                    self.to_image(&heatmap)
+                        .save(&heatmap_path, self.output_format)?;
+                }
+                None => status("Skipping the overexposure heatmap, the image went to stdout"),
+            }
+        }
+
+        if let Some(dir) = &self.aov_output {
+            status("Writing albedo and normal AOVs");
+            fs::create_dir_all(dir)?;
+            let extension = self.output_format.extension();
+            let albedo = self.render_tiles(&objects, Camera::albedo_pixel, false);
+            self.encode(&albedo, ToneMap::Clamp, self.gamma)
+                .save(dir.join(format!("albedo.{extension}")), self.output_format)?;
+            let normal = self.render_tiles(&objects, Camera::normal_pixel, false);
+            self.encode(&normal, ToneMap::Clamp, 0.0)
+                .save(dir.join(format!("normal.{extension}")), self.output_format)?;
+        }
+        if let Some(dir) = &self.light_group_output {
+            status("Writing light groups");
+            fs::create_dir_all(dir)?;
+            let extension = self.output_format.extension();
+            let exposure = self.exposure();
+            for (k, group) in light_groups.into_iter().enumerate() {
+                let name = if k < self.lights.len() {
+                    format!("light_{k}.{extension}")
+                } else {
+                    format!("light_other.{extension}")
+                };
+                let group: Vec<Color3> = group.into_iter().map(|color| color * exposure).collect();
+                self.encode(&group, ToneMap::Clamp, 0.0)
+                    .save(dir.join(name), self.output_format)?;
+            }
+        }
+        status("Done");
+        Ok(())
+    }
+
+    /// `key value` lines describing the render settings, see `set_embed_metadata`
+    fn metadata(&self) -> Vec<String> {
+        let seed = rng_seed().map_or_else(|| String::from("none"), |seed| seed.to_string());
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        vec![
+            format!("width {}", self.image_width),
+            format!("height {}", self.image_height),
+            format!("samples_per_pixel {}", self.sqrt_spp * self.sqrt_spp),
+            format!("max_depth {}", self.max_depth),
+            format!("seed {seed}"),
+            format!("date {}", format_utc(now)),
+        ]
+    }
+
+    /// Where the overexposure heatmap goes, next to the output file with `.overexposure` added
+    /// before the extension. `None` for stdout.
+    fn heatmap_path(&self) -> Option<PathBuf> {
+        let Output::File(path) = &self.output else {
+            return None;
+        };
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let extension = self.output_format.extension();
+        Some(path.with_file_name(format!("{stem}.overexposure.{extension}")))
+    }
+
+    /// Renders `objects` into memory instead of a file, tone mapped and gamma encoded the same
+    /// way `render` writes them
+    pub fn render_to_buffer(self: Arc<Self>, objects: Arc<dyn Hittable>) -> RgbImage {
+        let pixels = self.render_pixels(objects);
+        self.to_image(&pixels)
+    }
+
+    /// Linear color of every pixel with the exposure and denoising applied, row by row from the
+    /// top
+    fn render_pixels(self: &Arc<Self>, objects: Arc<dyn Hittable>) -> Vec<Color3> {
+        let pixels = self.render_tiles(&objects, Camera::render_pixel, self.show_progress);
+        self.post_process(pixels)
+    }
+
+    /// Linear images of every light group before exposure, see `set_light_group_output`. The
+    /// last one holds the light not coming from `lights`.
+    fn render_light_groups(self: &Arc<Self>, objects: &Arc<dyn Hittable>) -> Vec<Vec<Color3>> {
+        let pixels = self.render_tiles(objects, Camera::light_groups_pixel, self.show_progress);
+        (0..=self.lights.len())
+            .map(|group| pixels.iter().map(|groups| groups[group]).collect())
+            .collect()
+    }
+
+    /// Linear brightness multiplier from `exposure` and `exposure_ev`
+    fn exposure(&self) -> f64 {
+        self.exposure * 2.0_f64.powf(self.exposure_ev)
+    }
+
+    /// Applies the exposure and denoising to freshly rendered pixels
+    fn post_process(&self, pixels: Vec<Color3>) -> Vec<Color3> {
+        let exposure = self.exposure();
+        let pixels: Vec<Color3> = pixels.into_iter().map(|color| color * exposure).collect();
+
+        match self.denoise {
+            Some((sigma_spatial, sigma_color)) => bilateral(
+                &pixels,
+                self.image_width,
+                self.image_height,
+                sigma_spatial,
+                sigma_color,
+            ),
+            None => pixels,
+        }
+    }
+
+    /// Runs `shade` on every pixel, row by row from the top, rendered tile by tile on `threads`
+    /// workers
+    fn render_tiles<T: Clone + Default + Send + 'static>(
+        self: &Arc<Self>,
+        objects: &Arc<dyn Hittable>,
+        shade: PixelShader<T>,
+        show_progress: bool,
+    ) -> Vec<T> {
+        let tile_ids: Vec<TileId> = (0..self.tile_count()).collect();
+        let tiles = self.render_tile_set(objects, shade, show_progress, tile_ids);
+
+        // Tiles are written back by their absolute pixel coordinates
+        let mut pixels = vec![T::default(); self.image_width * self.image_height];
+        for (tile_id, tile) in tiles {
+            let (x0, y0, x1, _) = self.tile_bounds(tile_id);
+            let tile_width = x1 - x0;
+            for (row, colors) in tile.chunks(tile_width).enumerate() {
+                let row_start = (y0 + row) * self.image_width + x0;
+                pixels[row_start..row_start + tile_width].clone_from_slice(colors);
+            }
+        }
+        pixels
+    }
+
+    /// Runs `shade` on every pixel of the tiles `tile_ids` on `threads` workers, returning each
+    /// tile's pixels row by row in no particular order of tiles
+    fn render_tile_set<T: Send + 'static>(
+        self: &Arc<Self>,
+        objects: &Arc<dyn Hittable>,
+        shade: PixelShader<T>,
+        show_progress: bool,
+        tile_ids: Vec<TileId>,
+    ) -> Vec<(TileId, Vec<T>)> {
+        // Workers pull tiles from a shared counter so busy regions of the image don't leave
+        // the other threads idle
+        let thread_count = self.threads.unwrap_or_else(num_cpus::get);
+        let tile_count = tile_ids.len();
+        let tile_ids = Arc::new(tile_ids);
+        let next_tile = Arc::new(AtomicUsize::new(0));
+        let tiles_done = Arc::new(AtomicUsize::new(0));
+        let seed = rng_seed();
 
-            let s = Arc::clone(&self);
-            let objects = Arc::clone(&objects);
+        let mut thread_handles = Vec::new();
+        for _ in 0..thread_count {
+            let s = Arc::clone(self);
+            let objects = Arc::clone(objects);
+            let tile_ids = Arc::clone(&tile_ids);
+            let next_tile = Arc::clone(&next_tile);
+            let tiles_done = Arc::clone(&tiles_done);
             let handle = thread::spawn(move || {
-                let mut image_data = String::new();
-                for j in batch_start..batch_end {
-                    for i in 0..s.image_width {
-                        let mut pixel_color = Color3::zero();
-                        for _ in 0..s.samples_per_pixel {
-                            let ray = s.get_ray(i, j);
-                            pixel_color =
-                                pixel_color + s.ray_color(ray, objects.as_ref(), s.max_depth);
+                let mut tiles = Vec::new();
+                while let Some(&tile_id) = tile_ids.get(next_tile.fetch_add(1, Ordering::Relaxed)) {
+                    // Tiles get their own stream so the image doesn't depend on which thread
+                    // rendered which tile, or on how many threads there are
+                    if let Some(seed) = seed {
+                        seed_thread_rng(hash_u64(seed ^ hash_u64(tile_id as u64)));
+                    }
+
+                    let (x0, y0, x1, y1) = s.tile_bounds(tile_id);
+                    let mut tile = Vec::with_capacity((x1 - x0) * (y1 - y0));
+                    for j in y0..y1 {
+                        for i in x0..x1 {
+                            tile.push(shade(&s, i, j, objects.as_ref()));
                         }
-                        pixel_color = pixel_color * s.pixel_sample_scale;
-                        pixel_color.write(&mut image_data);
                     }
+                    tiles.push((tile_id, tile));
+                    tiles_done.fetch_add(1, Ordering::Relaxed);
                 }
-                image_data
+                tiles
             });
 
             thread_handles.push(handle);
         }
 
-        for th in thread_handles {
-            let thread_data = th.join().unwrap();
-            image_data.push_str(&thread_data);
+        // The monitor stops by itself once every tile is counted
+        let monitor = (show_progress && tile_count > 0).then(|| {
+            let tiles_done = Arc::clone(&tiles_done);
+            thread::spawn(move || Camera::report_progress(&tiles_done, tile_count))
+        });
+
+        let tiles = thread_handles
+            .into_iter()
+            .flat_map(|th| th.join().unwrap())
+            .collect();
+
+        if let Some(monitor) = monitor {
+            monitor.join().unwrap();
         }
+        tiles
+    }
+
+    /// Point the camera is looking from
+    pub fn center(&self) -> Point3 {
+        self.center
+    }
 
-        let mut file = File::create("image.ppm").expect("Failed to open image file");
-        file.write(image_data.as_bytes())
-            .expect("Failed while writing to file");
-        println!("Done");
+    /// Vertical field of view in degrees, unused by `Projection::Orthographic`
+    pub fn vfov(&self) -> f64 {
+        self.vfov
+    }
+
+    /// Number of tiles the image is split into, see `set_tile_size`
+    pub fn tile_count(&self) -> usize {
+        self.image_width.div_ceil(self.tile_size) * self.image_height.div_ceil(self.tile_size)
+    }
+
+    /// Renders only the tiles `tile_ids` so an image can be split between several machines,
+    /// see `import_and_merge`. Every tile comes with its linear pixels before exposure, row by
+    /// row, and the samples per pixel they were averaged over.
+    ///
+    /// Tiles are seeded by their id, so with the same seed (see `utils::init_rng`) a tile comes
+    /// out the same wherever it's rendered. Ids past `tile_count` are skipped.
+    pub fn export_tiles(
+        self: &Arc<Self>,
+        objects: Arc<dyn Hittable>,
+        tile_ids: impl IntoIterator<Item = TileId>,
+    ) -> Vec<RenderedTile> {
+        let tile_count = self.tile_count();
+        let tile_ids = tile_ids.into_iter().filter(|&id| id < tile_count).collect();
+        let sample_count = self.sqrt_spp * self.sqrt_spp;
+        let mut tiles = self.render_tile_set(&objects, Camera::render_pixel, false, tile_ids);
+        tiles.sort_by_key(|(tile_id, _)| *tile_id);
+        tiles
+            .into_iter()
+            .map(|(tile_id, pixels)| (tile_id, pixels, sample_count))
+            .collect()
+    }
+
+    /// Puts tiles from `export_tiles` back together into the image `render_to_buffer` would
+    /// produce, exposed, denoised and tone mapped. A tile rendered more than once (e.g. with
+    /// different seeds) is averaged weighted by the sample counts, missing tiles are black.
+    ///
+    /// Fails with `InvalidInput` on tiles that don't fit this camera's image and tile size.
+    pub fn import_and_merge(&self, tiles: &[RenderedTile]) -> io::Result<RgbImage> {
+        let pixels = self.merge_tiles(tiles)?;
+        Ok(self.to_image(&self.post_process(pixels)))
+    }
+
+    /// Sample-weighted average of `tiles` as linear pixels, row by row from the top
+    fn merge_tiles(&self, tiles: &[RenderedTile]) -> io::Result<Vec<Color3>> {
+        let mut sums = vec![Color3::zero(); self.image_width * self.image_height];
+        let mut sample_counts = vec![0; self.image_width * self.image_height];
+        for (tile_id, pixels, sample_count) in tiles {
+            if *tile_id >= self.tile_count() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("tile {tile_id} is outside of the image"),
+                ));
+            }
+            let (x0, y0, x1, y1) = self.tile_bounds(*tile_id);
+            if pixels.len() != (x1 - x0) * (y1 - y0) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("tile {tile_id} has {} pixels", pixels.len()),
+                ));
+            }
+            for (row, colors) in pixels.chunks(x1 - x0).enumerate() {
+                let row_start = (y0 + row) * self.image_width + x0;
+                for (k, color) in colors.iter().enumerate() {
+                    sums[row_start + k] += *color * *sample_count as f64;
+                    sample_counts[row_start + k] += sample_count;
+                }
+            }
+        }
+        Ok(sums
+            .into_iter()
+            .zip(sample_counts)
+            .map(|(sum, count)| match count {
+                0 => Color3::zero(),
+                count => sum / count as f64,
+            })
+            .collect())
+    }
+
+    /// Pixels `x0..x1` and rows `y0..y1` covered by tile `tile_id` as `(x0, y0, x1, y1)`, tiles
+    /// being numbered row by row from the top left
+    fn tile_bounds(&self, tile_id: TileId) -> (usize, usize, usize, usize) {
+        let tiles_x = self.image_width.div_ceil(self.tile_size);
+        let x0 = (tile_id % tiles_x) * self.tile_size;
+        let y0 = (tile_id / tiles_x) * self.tile_size;
+        (
+            x0,
+            y0,
+            (x0 + self.tile_size).min(self.image_width),
+            (y0 + self.tile_size).min(self.image_height),
+        )
+    }
+
+    /// Linear color of pixel i, j averaged over all of its samples, before exposure
+    fn render_pixel(&self, i: usize, j: usize, objects: &dyn Hittable) -> Color3 {
+        let mut pixel_color = Color3::zero();
+        for s_j in 0..self.sqrt_spp {
+            for s_i in 0..self.sqrt_spp {
+                let ray = self.get_ray(i, j, s_i, s_j);
+                // A single NaN sample would otherwise poison the whole pixel
+                let sample = self
+                    .ray_color(
+                        ray,
+                        objects,
+                        self.max_depth,
+                        Color3::new(1.0, 1.0, 1.0),
+                        &mut [],
+                    )
+                    .sanitized(Color3::zero());
+                pixel_color += sample;
+            }
+        }
+        let pixel_color = pixel_color * self.pixel_sample_scale;
+        match self.focus_peaking {
+            Some(tolerance) if self.in_focus(i, j, objects, tolerance) => {
+                Color3::new(1.0, 0.0, 0.0)
+            }
+            Some(_) => pixel_color * 0.3,
+            None => pixel_color,
+        }
+    }
+
+    /// Linear color of pixel i, j split by light group, see `set_light_group_output`, averaged
+    /// over all of its samples before exposure
+    fn light_groups_pixel(&self, i: usize, j: usize, objects: &dyn Hittable) -> Vec<Color3> {
+        let mut pixel_groups = vec![Color3::zero(); self.lights.len() + 1];
+        let mut sample_groups = vec![Color3::zero(); self.lights.len()];
+        for s_j in 0..self.sqrt_spp {
+            for s_i in 0..self.sqrt_spp {
+                sample_groups.fill(Color3::zero());
+                let ray = self.get_ray(i, j, s_i, s_j);
+                let sample = self.ray_color(
+                    ray,
+                    objects,
+                    self.max_depth,
+                    Color3::new(1.0, 1.0, 1.0),
+                    &mut sample_groups,
+                );
+                // Same as `render_pixel`, a NaN sample is dropped from every group
+                if !sample.is_finite() || sample_groups.iter().any(|group| !group.is_finite()) {
+                    continue;
+                }
+                let mut other = sample;
+                for (pixel_group, sample_group) in pixel_groups.iter_mut().zip(&sample_groups) {
+                    *pixel_group += *sample_group;
+                    other -= *sample_group;
+                }
+                pixel_groups[self.lights.len()] += other;
+            }
+        }
+        pixel_groups
+            .into_iter()
+            .map(|group| group * self.pixel_sample_scale)
+            .collect()
+    }
+
+    /// Albedo of the first surface seen through pixel i, j, averaged over all of its samples
+    fn albedo_pixel(&self, i: usize, j: usize, objects: &dyn Hittable) -> Color3 {
+        self.first_hit_average(i, j, objects, |hit_record| {
+            hit_record.material.albedo(hit_record)
+        })
+    }
+
+    /// Outward normal of the first surface seen through pixel i, j mapped to `[0, 1]`, averaged
+    /// over all of its samples
+    fn normal_pixel(&self, i: usize, j: usize, objects: &dyn Hittable) -> Color3 {
+        self.first_hit_average(i, j, objects, |hit_record| {
+            0.5 * (hit_record.normal + Color3::new(1.0, 1.0, 1.0))
+        })
+    }
+
+    /// Average of `value` at the first hit of every sample of pixel i, j, black for misses
+    fn first_hit_average(
+        &self,
+        i: usize,
+        j: usize,
+        objects: &dyn Hittable,
+        value: impl Fn(&HitRecord) -> Color3,
+    ) -> Color3 {
+        let mut sum = Color3::zero();
+        for s_j in 0..self.sqrt_spp {
+            for s_i in 0..self.sqrt_spp {
+                let ray = self.get_ray(i, j, s_i, s_j);
+                if let Some(hit_record) = objects.hit(&ray, Interval::new(0.001, f64::INFINITY)) {
+                    sum += value(&hit_record);
+                }
+            }
+        }
+        sum * self.pixel_sample_scale
+    }
+
+    /// Rewrites a single stderr line with the tiles left and an ETA extrapolated from the
+    /// average time per tile so far
+    fn report_progress(tiles_done: &AtomicUsize, total_tiles: usize) {
+        let start = Instant::now();
+        loop {
+            let done = tiles_done.load(Ordering::Relaxed).min(total_tiles);
+            let elapsed = start.elapsed().as_secs_f64();
+            let percent = 100.0 * done as f64 / total_tiles as f64;
+            if done == 0 {
+                eprint!("\rTiles remaining: {total_tiles} ({percent:.0}%)   ");
+            } else {
+                let eta = elapsed / done as f64 * (total_tiles - done) as f64;
+                eprint!(
+                    "\rTiles remaining: {} ({percent:.0}%, ETA {eta:.0}s)   ",
+                    total_tiles - done
+                );
+            }
+
+            if done == total_tiles {
+                eprintln!();
+                break;
+            }
+            thread::sleep(Duration::from_millis(250));
+        }
+    }
+
+    /// Tone maps and gamma encodes linear pixels into 8-bit RGB
+    fn to_image(&self, pixels: &[Color3]) -> RgbImage {
+        self.encode(pixels, self.tone_map, self.gamma)
+    }
+
+    /// Same as `to_image` with a different tone map and gamma, 0 or less for linear output
+    fn encode(&self, pixels: &[Color3], tone_map: ToneMap, gamma: f64) -> RgbImage {
+        let data = pixels
+            .iter()
+            .flat_map(|p| p.to_display_bytes(tone_map, gamma))
+            .collect();
+        let mut image = RgbImage::from_raw(self.image_width, self.image_height, data);
+        if self.embed_color_profile {
+            image.set_color_profile(Some(ColorProfile::from_gamma(gamma)));
+        }
+        image
+    }
+
+    /// Whether the surface seen through the center of pixel i, j is within `tolerance` of the
+    /// focal plane
+    ///
+    /// Camera rays are aimed at points on the focal plane, so `t = 1` is exactly in focus and
+    /// `t * focus_dist` is the hit's depth along the view direction.
+    fn in_focus(&self, i: usize, j: usize, objects: &dyn Hittable, tolerance: f64) -> bool {
+        let pixel_center =
+            self.pixel00_loc + (i as f64 * self.pixel_delta_u) + (j as f64 * self.pixel_delta_v);
+        let ray = match self.projection {
+            Projection::Perspective => Ray::new(self.center, pixel_center - self.center),
+            Projection::Orthographic { .. } => Ray::new(pixel_center - self.forward, self.forward),
+        };
+        objects
+            .hit(&ray, Interval::new(0.001, f64::MAX))
+            .is_some_and(|hit| ((hit.t - 1.0) * self.focus_dist).abs() <= tolerance)
     }
 
     /// Construct a camera ray originating from the defocus disk and directed at a randomly
-    /// sampled point around the pixel location i, j.
-    fn get_ray(&self, i: usize, j: usize) -> Ray {
-        let offset = Vec3::new(random_percentage() - 0.5, random_percentage() - 0.5, 0.0);
+    /// sampled point in the sub-cell s_i, s_j of the pixel location i, j.
+    fn get_ray(&self, i: usize, j: usize, s_i: usize, s_j: usize) -> Ray {
+        let offset = self.sample_square_stratified(s_i, s_j);
         let pixel_center = self.pixel00_loc
             + ((i as f64 + offset.x) * self.pixel_delta_u)
             + ((j as f64 + offset.y) * self.pixel_delta_v);
 
-        let ray_origin = if self.defocus_angle <= 0.0 {
-            self.center
-        } else {
-            // Get defocus disk sample
-            let p = Vec3::random_in_unit_disk();
-            self.center + (self.defocus_disk_u * p.x) + (self.defocus_disk_v * p.y)
+        let (ray_origin, ray_direction) = match self.projection {
+            // Every ray is parallel to the view direction, starting on the camera plane so
+            // `t = 1` is still the focal plane
+            Projection::Orthographic { .. } => (pixel_center - self.forward, self.forward),
+            Projection::Perspective => {
+                let ray_origin = if self.defocus_angle <= 0.0 {
+                    self.center
+                } else {
+                    // Get defocus disk sample
+                    let p = Vec3::random_in_unit_disk();
+                    self.center + (self.defocus_disk_u * p.x) + (self.defocus_disk_v * p.y)
+                };
+                (ray_origin, pixel_center - ray_origin)
+            }
         };
-
-        let ray_direction = pixel_center - ray_origin;
         if self.enable_motion_blur {
-            Ray::new_time(ray_origin, ray_direction, random_percentage())
+            let tm =
+                self.shutter_open + random_percentage() * (self.shutter_close - self.shutter_open);
+            Ray::new_time(ray_origin, ray_direction, tm)
         } else {
             Ray::new(ray_origin, ray_direction)
         }
     }
 
-    fn ray_color(&self, ray: Ray, objects: &dyn Hittable, depth: usize) -> Color3 {
+    /// Random offset from the pixel center drawn from the sub-cell s_i, s_j of the pixel's
+    /// `sqrt_spp × sqrt_spp` grid, then shaped by the pixel filter
+    fn sample_square_stratified(&self, s_i: usize, s_j: usize) -> Vec3 {
+        let u = (s_i as f64 + random_percentage()) * self.recip_sqrt_spp;
+        let v = (s_j as f64 + random_percentage()) * self.recip_sqrt_spp;
+        let (px, py) = self.pixel_filter.offset(u, v);
+        Vec3::new(px, py, 0.0)
+    }
+
+    /// `throughput` is the product of the attenuations along the path so far, i.e. how much
+    /// of this ray's color reaches the pixel. Unless empty, `light_groups` gets what every
+    /// light in `lights` adds to the pixel through this ray.
+    fn ray_color(
+        &self,
+        ray: Ray,
+        objects: &dyn Hittable,
+        depth: usize,
+        throughput: Color3,
+        light_groups: &mut [Color3],
+    ) -> Color3 {
         // Bounce limit exceeded
-        if depth <= 0 {
+        if depth == 0 {
             return Color3::zero();
         }
 
         if let Some(hit_record) = objects.hit(&ray, Interval::new(0.001, f64::MAX)) {
-            if let Some(scatter_record) = hit_record.material.scatter(&ray, &hit_record) {
-                return scatter_record.attenuation
-                    * self.ray_color(scatter_record.scattered, objects, depth - 1);
+            // Debug modes only look at the first hit
+            match self.render_mode {
+                RenderMode::Shaded => {}
+                RenderMode::Normals => {
+                    return 0.5 * (hit_record.normal + Color3::new(1.0, 1.0, 1.0));
+                }
+                RenderMode::Depth { near, far } => {
+                    let distance = hit_record.t * ray.dir.length();
+                    let brightness = 1.0 - ((distance - near) / (far - near)).clamp(0.0, 1.0);
+                    return Color3::new(brightness, brightness, brightness);
+                }
+                RenderMode::LightsOnly => return self.lights_only_color(&ray, objects, hit_record),
+            }
+
+            let color_from_emission =
+                hit_record
+                    .material
+                    .emitted(hit_record.u, hit_record.v, &hit_record.p)
+                    + self.direct_light(&ray, objects, &hit_record, throughput, light_groups);
+
+            // Split into several scattered rays at the branching bounce, a single one otherwise
+            let bounce = self.max_depth - depth;
+            let branches = if bounce == self.branch_depth {
+                self.branch_factor
+            } else {
+                1
+            };
+
+            // Directions towards the light shapes and the sun disk, whichever are in use
+            let shapes_pdf = self
+                .light_shapes
+                .as_ref()
+                .map(|light_shapes| HittablePdf::new(light_shapes.as_ref(), hit_record.p));
+            let shapes_and_sun;
+            let light_pdf: Option<&dyn Pdf> = match (&shapes_pdf, &self.sun) {
+                (Some(shapes_pdf), Some(sun)) => {
+                    shapes_and_sun = MixturePdf::new(shapes_pdf, sun);
+                    Some(&shapes_and_sun)
+                }
+                (Some(shapes_pdf), None) => Some(shapes_pdf),
+                (None, Some(sun)) => Some(sun),
+                (None, None) => None,
+            };
+
+            let mut color_from_scatter = Color3::zero();
+            for _ in 0..branches {
+                let Some(scatter_record) = hit_record.material.scatter(&ray, &hit_record) else {
+                    continue;
+                };
+                let (scattered, mut attenuation) = match (&scatter_record.pdf, light_pdf) {
+                    // Aim half of the rays at the lights, weighting by
+                    // `scattering_pdf / pdf` keeps the estimate unbiased. Rays which reach the
+                    // sun this way get the same weight whichever half picked them.
+                    (Some(pdf), Some(light_pdf)) => {
+                        let mixture = MixturePdf::new(light_pdf, pdf.as_ref());
+                        let scattered = Ray::new_time(hit_record.p, mixture.generate(), ray.tm);
+                        let pdf_value = mixture.value(&scattered.dir);
+                        if pdf_value <= 0.0 {
+                            continue;
+                        }
+                        let scattering_pdf =
+                            hit_record
+                                .material
+                                .scattering_pdf(&ray, &hit_record, &scattered);
+                        (
+                            scattered,
+                            scatter_record.attenuation * scattering_pdf / pdf_value,
+                        )
+                    }
+                    _ => (scatter_record.scattered, scatter_record.attenuation),
+                };
+                let path_throughput = throughput * attenuation / branches as f64;
+
+                let Some(p) = self.roulette(bounce, path_throughput) else {
+                    continue;
+                };
+                attenuation /= p;
+
+                color_from_scatter += attenuation
+                    * self.ray_color(
+                        scattered,
+                        objects,
+                        depth - 1,
+                        throughput * attenuation / branches as f64,
+                        light_groups,
+                    );
+            }
+            return color_from_emission + color_from_scatter / branches as f64;
+        }
+
+        self.miss_color(&ray)
+    }
+
+    /// Russian roulette: continues with probability `p`, the brightest channel of the
+    /// throughput, and returns `p` to divide by so the expected value is unchanged. `None` ends
+    /// the path. Bounces before `russian_roulette_min_bounces` always continue with `p = 1`.
+    fn roulette(&self, bounce: usize, path_throughput: Color3) -> Option<f64> {
+        if !self.russian_roulette || bounce < self.russian_roulette_min_bounces {
+            return Some(1.0);
+        }
+        let p = path_throughput
+            .x
+            .max(path_throughput.y)
+            .max(path_throughput.z)
+            .min(1.0);
+        (random_percentage() < p).then_some(p)
+    }
+
+    /// Background, and the sun if there is one, seen by a ray missing every object
+    fn miss_color(&self, ray: &Ray) -> Color3 {
+        let background = self.background.radiance(ray);
+
+        match &self.sun {
+            Some(sun) => background + sun.radiance(&ray.dir),
+            None => background,
+        }
+    }
+
+    /// First emission along `ray` starting at `hit_record`, passing through every surface which
+    /// doesn't emit light
+    fn lights_only_color(
+        &self,
+        ray: &Ray,
+        objects: &dyn Hittable,
+        mut hit_record: HitRecord,
+    ) -> Color3 {
+        loop {
+            let emitted = hit_record
+                .material
+                .emitted(hit_record.u, hit_record.v, &hit_record.p);
+            if !emitted.near_zero() {
+                return emitted;
+            }
+            match objects.hit(ray, Interval::new(hit_record.t + 0.001, f64::MAX)) {
+                Some(next_hit_record) => hit_record = next_hit_record,
+                None => return self.miss_color(ray),
             }
+        }
+    }
+
+    /// Light reaching a diffuse hit straight from the lights added with `add_light`, reflected
+    /// towards the incoming ray
+    ///
+    /// ## Math
+    /// A Lambertian surface reflects `albedo / π` of the incoming irradiance, which is reduced
+    /// by the cosine between the normal `N` and the direction to the light `L`:
+    /// `albedo / π * E * max(N·L, 0)` for every light which isn't blocked by an object
+    ///
+    /// Unless empty, `light_groups` gets every light's share weighted by `throughput`.
+    fn direct_light(
+        &self,
+        ray: &Ray,
+        objects: &dyn Hittable,
+        hit_record: &HitRecord,
+        throughput: Color3,
+        light_groups: &mut [Color3],
+    ) -> Color3 {
+        if self.lights.is_empty() {
             return Color3::zero();
         }
+        let Some(albedo) = hit_record.material.diffuse_albedo(hit_record) else {
+            return Color3::zero();
+        };
+
+        // Shade the side the ray arrived from, with the normal the material shades with
+        let normal = hit_record.material.shading_normal(hit_record);
+        let normal = if hit_record.is_front_face {
+            normal
+        } else {
+            -normal
+        };
+
+        let mut irradiance = Color3::zero();
+        for (k, light) in self.lights.iter().enumerate() {
+            let sample = light.sample(&hit_record.p);
+            let cos_theta = normal.dot(&sample.to_light.unit());
+            if cos_theta <= 0.0 || sample.irradiance.near_zero() {
+                continue;
+            }
+            // `to_light` spans the whole way to the light, so stop just short of `t = 1`
+            let shadow_ray = Ray::new_time(hit_record.p, sample.to_light, ray.tm);
+            if !objects.occluded(&shadow_ray, Interval::new(0.001, 0.999)) {
+                irradiance += sample.irradiance * cos_theta;
+                if let Some(group) = light_groups.get_mut(k) {
+                    *group += throughput * albedo * sample.irradiance * cos_theta / PI;
+                }
+            }
+        }
+        albedo * irradiance / PI
+    }
 
-        // Color of the sky
+    /// White to light blue gradient going from the bottom to the top of the sky
+    pub fn sky_gradient(ray: &Ray) -> Color3 {
         let unit_direction = ray.dir.unit();
         let a = 0.5 * (unit_direction.y + 1.0);
         (1.0 - a) * Color3::new(1.0, 1.0, 1.0) + a * Color3::new(0.5, 0.7, 1.0)
     }
 }
+
+/// Writes tiles from `Camera::export_tiles` losslessly so they can be moved to the machine
+/// merging them, see `read_tiles`
+///
+/// The format is the tile count, then for every tile its id, sample count, pixel count and
+/// pixels as RGB triples, all little-endian `u64`s and `f64`s.
+pub fn write_tiles(writer: &mut impl Write, tiles: &[RenderedTile]) -> io::Result<()> {
+    writer.write_all(&(tiles.len() as u64).to_le_bytes())?;
+    for (tile_id, pixels, sample_count) in tiles {
+        for n in [*tile_id, *sample_count, pixels.len()] {
+            writer.write_all(&(n as u64).to_le_bytes())?;
+        }
+        for pixel in pixels {
+            for channel in [pixel.x, pixel.y, pixel.z] {
+                writer.write_all(&channel.to_le_bytes())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Reads tiles written by `write_tiles`
+pub fn read_tiles(reader: &mut impl Read) -> io::Result<Vec<RenderedTile>> {
+    fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+        let mut bytes = [0; 8];
+        reader.read_exact(&mut bytes)?;
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    let tile_count = read_u64(reader)?;
+    let mut tiles = Vec::new();
+    for _ in 0..tile_count {
+        let tile_id = read_u64(reader)? as usize;
+        let sample_count = read_u64(reader)? as usize;
+        let pixel_count = read_u64(reader)?;
+        let mut pixels = Vec::new();
+        for _ in 0..pixel_count {
+            let x = f64::from_bits(read_u64(reader)?);
+            let y = f64::from_bits(read_u64(reader)?);
+            let z = f64::from_bits(read_u64(reader)?);
+            pixels.push(Color3::new(x, y, z));
+        }
+        tiles.push((tile_id, pixels, sample_count));
+    }
+    Ok(tiles)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::FRAC_1_SQRT_2;
+
+    use super::*;
+    use crate::hittable::HittableList;
+    use crate::image::load_rgb8;
+    use crate::material::DiffuseLight;
+    use crate::material::Lambertian;
+    use crate::material::Material;
+    use crate::material::NormalMapped;
+    use crate::quad::Quad;
+    use crate::texture::SolidColor;
+    use crate::utils::init_rng;
+
+    #[test]
+    fn builder_defaults() {
+        let camera = Camera::builder().build();
+        assert_eq!((camera.image_width, camera.image_height), (400, 225));
+        assert_eq!(camera.sqrt_spp * camera.sqrt_spp, 100);
+        assert_eq!(camera.max_depth, 50);
+        assert_eq!(camera.vfov(), 40.0);
+        assert_eq!(camera.defocus_angle, 0.0);
+        assert!(!camera.enable_motion_blur);
+        // Looking down -Z with +Y up, so pixels step along +X and -Y
+        assert!(camera.pixel_delta_u.x > 0.0 && camera.pixel_delta_u.y == 0.0);
+        assert!(camera.pixel_delta_v.y < 0.0 && camera.pixel_delta_v.x == 0.0);
+
+        // `Camera::new` goes through the builder
+        let camera = Camera::new(
+            16.0 / 9.0,
+            400,
+            100,
+            50,
+            40.0,
+            Point3::zero(),
+            Point3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            0.0,
+            10.0,
+            false,
+        );
+        let built = Camera::builder().build();
+        assert!((camera.pixel00_loc - built.pixel00_loc).length() < 1e-12);
+    }
+
+    #[test]
+    fn wider_vfov_spreads_the_pixels() {
+        let narrow = Camera::builder().build();
+        let wide = Camera::builder().vfov(90.0).build();
+        let ratio = wide.pixel_delta_u.length() / narrow.pixel_delta_u.length();
+        let expected = 45.0_f64.to_radians().tan() / 20.0_f64.to_radians().tan();
+        assert!((ratio - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn stratified_samples_land_one_per_sub_cell() {
+        let camera = Camera::builder().samples_per_pixel(4).build();
+        assert_eq!(camera.sqrt_spp, 2);
+
+        seed_thread_rng(13);
+        for _ in 0..100 {
+            let mut cells = vec![];
+            for s_j in 0..2 {
+                for s_i in 0..2 {
+                    let offset = camera.sample_square_stratified(s_i, s_j);
+                    assert!(offset.x.abs() <= 0.5 && offset.y.abs() <= 0.5);
+                    // Box filter offsets are in `[-0.5, 0.5)`, each half is a sub-cell
+                    cells.push(((offset.x >= 0.0) as usize, (offset.y >= 0.0) as usize));
+                }
+            }
+            assert_eq!(cells, [(0, 0), (1, 0), (0, 1), (1, 1)]);
+        }
+    }
+
+    #[test]
+    fn gamma_brightens_the_written_pixels() {
+        let background_pixel = |gamma: f64| {
+            let mut camera = Camera::builder()
+                .image_width(4)
+                .samples_per_pixel(1)
+                .build();
+            camera.set_progress(false);
+            camera.set_background(Color3::new(0.25, 0.25, 0.25));
+            camera.set_gamma(gamma);
+            let image = Arc::new(camera).render_to_buffer(Arc::new(HittableList::new()));
+            image.get_pixel(0, 0)
+        };
+        assert_eq!(background_pixel(1.0), [64, 64, 64]);
+        // `0.25^(1/2.2)` is about 0.53
+        assert_eq!(background_pixel(2.2), [136, 136, 136]);
+        // No correction at all is the same as a gamma of 1
+        assert_eq!(background_pixel(0.0), [64, 64, 64]);
+    }
+
+    #[test]
+    fn orthographic_rays_are_parallel() {
+        let camera = Camera::builder()
+            .image_width(20)
+            .look_from(Point3::new(1.0, 2.0, 3.0))
+            .look_at(Point3::new(1.0, 2.0, 0.0))
+            .projection(Projection::Orthographic { scale: 4.0 })
+            .defocus_angle(10.0)
+            .build();
+        let a = camera.get_ray(0, 0, 0, 0);
+        let b = camera.get_ray(15, 7, 0, 0);
+
+        assert!((a.origin - b.origin).length() > 1.0);
+        assert_eq!((a.dir.x, a.dir.y, a.dir.z), (b.dir.x, b.dir.y, b.dir.z));
+        // Straight down the view direction, starting on the camera plane
+        assert!((a.dir.unit() - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-12);
+        assert!((a.origin.z - 3.0).abs() < 1e-12);
+    }
+
+    /// Renders a small Lambertian scene with the shared test seed
+    fn seeded_render(threads: usize, tile_size: usize) -> RgbImage {
+        let mut camera = Camera::builder()
+            .image_width(24)
+            .samples_per_pixel(4)
+            .max_depth(4)
+            .build();
+        camera.set_progress(false);
+        camera.set_threads(Some(threads));
+        camera.set_tile_size(tile_size);
+
+        let mut world = HittableList::new();
+        let gray = Arc::new(Lambertian::new(Color3::new(0.5, 0.5, 0.5)));
+        world.add(Sphere::new(Point3::new(0.0, 0.0, -2.0), 0.5, gray.clone()));
+        world.add(Sphere::new(Point3::new(0.0, -100.5, -2.0), 100.0, gray));
+
+        // The seed is global, every test setting it uses the same one so they can't race
+        init_rng(7);
+        Arc::new(camera).render_to_buffer(Arc::new(world))
+    }
+
+    #[test]
+    fn thread_count_does_not_change_the_image() {
+        assert_eq!(seeded_render(1, 8), seeded_render(4, 8));
+    }
+
+    #[test]
+    fn exposure_scales_the_linear_pixels() {
+        let render = |exposure: f64| {
+            let mut camera = Camera::builder()
+                .image_width(16)
+                .samples_per_pixel(4)
+                .max_depth(4)
+                .build();
+            camera.set_progress(false);
+            camera.set_threads(Some(1));
+            camera.set_gamma(1.0);
+            camera.set_exposure(exposure);
+
+            let mut world = HittableList::new();
+            let gray = Arc::new(Lambertian::new(Color3::new(0.3, 0.3, 0.3)));
+            world.add(Sphere::new(Point3::new(0.0, 0.0, -2.0), 0.5, gray.clone()));
+            world.add(Sphere::new(Point3::new(0.0, -100.5, -2.0), 100.0, gray));
+
+            init_rng(7);
+            Arc::new(camera).render_to_buffer(Arc::new(world))
+        };
+
+        let normal = render(1.0);
+        let bright = render(2.0);
+        let mut compared = 0;
+        for (&a, &b) in normal.as_raw().iter().zip(bright.as_raw()) {
+            if b == 255 {
+                continue;
+            }
+            // Both sides are rounded to bytes
+            assert!((b as i32 - 2 * a as i32).abs() <= 1, "{a} doubled to {b}");
+            compared += 1;
+        }
+        assert!(compared > 0);
+    }
+
+    #[test]
+    fn tiles_cover_every_pixel_once() {
+        let mut camera = Camera::builder().image_width(23).aspect_ratio(2.0).build();
+        camera.set_tile_size(5);
+        assert_eq!(camera.tile_count(), 5 * 3);
+
+        let mut covered = vec![0; 23 * 11];
+        for tile_id in 0..camera.tile_count() {
+            let (x0, y0, x1, y1) = camera.tile_bounds(tile_id);
+            for j in y0..y1 {
+                for i in x0..x1 {
+                    covered[j * 23 + i] += 1;
+                }
+            }
+        }
+        assert!(covered.iter().all(|&count| count == 1));
+
+        // Ragged edge tiles don't depend on which worker picks them up either
+        assert_eq!(seeded_render(1, 5), seeded_render(4, 5));
+    }
+
+    #[test]
+    fn ray_times_stay_within_the_shutter() {
+        let camera = Camera::builder()
+            .enable_motion_blur(true)
+            .shutter_open(0.25)
+            .shutter_close(0.75)
+            .build();
+        seed_thread_rng(19);
+        let times: Vec<f64> = (0..10_000).map(|_| camera.get_ray(3, 4, 0, 0).tm).collect();
+        assert!(times.iter().all(|tm| (0.25..0.75).contains(tm)));
+        let mean = times.iter().sum::<f64>() / times.len() as f64;
+        assert!((mean - 0.5).abs() < 0.01);
+
+        // Without motion blur every ray is at time 0
+        let camera = Camera::builder().shutter_open(0.25).build();
+        assert_eq!(camera.get_ray(3, 4, 0, 0).tm, 0.0);
+    }
+
+    #[test]
+    fn buffer_render_has_sky_on_top() {
+        let mut camera = Camera::builder()
+            .image_width(8)
+            .aspect_ratio(1.0)
+            .samples_per_pixel(4)
+            .max_depth(4)
+            .build();
+        camera.set_progress(false);
+        // Ground sphere below the horizon, the top rows only see the sky
+        let gray = Arc::new(Lambertian::new(Color3::new(0.5, 0.5, 0.5)));
+        let ground = Sphere::new(Point3::new(0.0, -101.0, -1.0), 100.0, gray);
+        let image = Arc::new(camera).render_to_buffer(Arc::new(ground));
+
+        assert_eq!((image.width(), image.height()), (8, 8));
+        for y in 0..2 {
+            for x in 0..8 {
+                // Light blue, the sky gradient has more blue than red above the horizon
+                let [r, g, b] = image.get_pixel(x, y);
+                assert!(b == 255 && r < g && g < b, "{:?}", [r, g, b]);
+            }
+        }
+        // The ground is darker than the sky
+        assert!(image.get_pixel(4, 7)[2] < 255);
+    }
+
+    #[test]
+    fn narrow_filters_spread_samples_less() {
+        seed_thread_rng(41);
+        let variance = |filter: PixelFilter| {
+            let offsets: Vec<f64> = (0..20_000)
+                .map(|_| filter.offset(random_percentage(), random_percentage()).0)
+                .collect();
+            offsets.iter().map(|x| x * x).sum::<f64>() / offsets.len() as f64
+        };
+
+        let box_variance = variance(PixelFilter::Box);
+        let tent_variance = variance(PixelFilter::Tent);
+        let gaussian_variance = variance(PixelFilter::Gaussian { sigma: 0.1 });
+        assert!(gaussian_variance < tent_variance && tent_variance < box_variance);
+
+        // `1/12` for the box, `1/24` for the tent over the pixel and `sigma^2`
+        assert!((box_variance - 1.0 / 12.0).abs() < 0.005);
+        assert!((tent_variance - 1.0 / 24.0).abs() < 0.005);
+        assert!((gaussian_variance - 0.01).abs() < 0.001);
+    }
+
+    #[test]
+    fn heatmap_goes_next_to_the_output() {
+        let mut camera = Camera::builder().build();
+        camera.set_output("renders/scene.png", OutputFormat::Png);
+        assert_eq!(
+            camera.heatmap_path(),
+            Some(PathBuf::from("renders/scene.overexposure.png"))
+        );
+
+        camera.set_output(Output::Stdout, OutputFormat::Ppm);
+        assert_eq!(camera.heatmap_path(), None);
+    }
+
+    #[test]
+    fn studio_background_splits_at_the_horizon() {
+        let background = Background::Studio {
+            sky: Color3::new(0.2, 0.4, 0.8),
+            ground: Color3::new(0.3, 0.2, 0.1),
+        };
+        let color = |dir: Vec3| {
+            let c = background.radiance(&Ray::new(Point3::zero(), dir));
+            (c.x, c.y, c.z)
+        };
+
+        assert_eq!(color(Vec3::new(0.0, -1.0, 0.0)), (0.3, 0.2, 0.1));
+        assert_eq!(color(Vec3::new(0.5, -0.01, -1.0)), (0.3, 0.2, 0.1));
+        assert_eq!(color(Vec3::new(0.0, 1.0, 0.0)), (0.2, 0.4, 0.8));
+        assert_eq!(color(Vec3::new(-0.5, 0.01, -1.0)), (0.2, 0.4, 0.8));
+        let solid = Background::from(Color3::new(0.5, 0.5, 0.5))
+            .radiance(&Ray::new(Point3::zero(), Vec3::new(0.0, -1.0, 0.0)));
+        assert_eq!((solid.x, solid.y, solid.z), (0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn stdout_render_starts_with_the_ppm_header() {
+        let mut camera = Camera::builder()
+            .image_width(4)
+            .aspect_ratio(2.0)
+            .samples_per_pixel(1)
+            .max_depth(2)
+            .build();
+        camera.set_progress(false);
+        camera.set_output(Output::Stdout, OutputFormat::Ppm);
+
+        // The same bytes `render` hands to the locked stdout
+        let material = Arc::new(Lambertian::new(Color3::new(0.5, 0.5, 0.5)));
+        let world = Arc::new(Sphere::new(Point3::new(0.0, 0.0, -1.0), 0.5, material));
+        let mut stdout: Vec<u8> = vec![];
+        Arc::new(camera)
+            .render_to_buffer(world)
+            .write_to(&mut stdout, OutputFormat::Ppm)
+            .unwrap();
+
+        let text = String::from_utf8(stdout).unwrap();
+        assert!(text.starts_with("P3\n4 2\n255\n"));
+        assert_eq!(text.lines().count(), 3 + 4 * 2);
+    }
+
+    #[test]
+    fn metadata_comments_follow_the_setting() {
+        let written = |embed_metadata: bool| {
+            let mut camera = Camera::builder()
+                .image_width(4)
+                .samples_per_pixel(4)
+                .max_depth(2)
+                .build();
+            camera.set_progress(false);
+            camera.set_embed_metadata(embed_metadata);
+            let path = std::env::temp_dir().join(format!(
+                "metadata_{embed_metadata}_{}.ppm",
+                std::process::id()
+            ));
+            camera.set_output(path.to_str().unwrap(), OutputFormat::Ppm);
+
+            let rendered = Arc::new(camera).render(Arc::new(HittableList::new()));
+            let text = std::fs::read_to_string(&path);
+            std::fs::remove_file(&path).unwrap();
+            rendered.unwrap();
+            text.unwrap()
+        };
+
+        let with_metadata = written(true);
+        assert!(with_metadata.starts_with("P3\n"));
+        assert!(
+            with_metadata
+                .lines()
+                .any(|line| line == "# samples_per_pixel 4")
+        );
+        assert!(!written(false).lines().any(|line| line.starts_with('#')));
+    }
+
+    #[test]
+    fn render_to_a_directory_fails() {
+        let mut camera = Camera::builder()
+            .image_width(8)
+            .samples_per_pixel(1)
+            .max_depth(2)
+            .build();
+        camera.set_progress(false);
+        camera.set_output(Output::File(std::env::temp_dir()), OutputFormat::Ppm);
+
+        let material = Arc::new(Lambertian::new(Color3::new(0.5, 0.5, 0.5)));
+        let world = Arc::new(Sphere::new(Point3::new(0.0, 0.0, -1.0), 0.5, material));
+        assert!(Arc::new(camera).render(world).is_err());
+    }
+
+    #[test]
+    fn png_render_reads_back_at_its_size() {
+        let mut camera = Camera::builder()
+            .image_width(12)
+            .aspect_ratio(2.0)
+            .samples_per_pixel(1)
+            .max_depth(2)
+            .build();
+        camera.set_progress(false);
+        let path = std::env::temp_dir().join(format!("tiny_render_{}.png", std::process::id()));
+        camera.set_output(path.to_str().unwrap(), OutputFormat::Png);
+
+        let material = Arc::new(Lambertian::new(Color3::new(0.5, 0.5, 0.5)));
+        let world = Arc::new(Sphere::new(Point3::new(0.0, 0.0, -1.0), 0.5, material));
+        Arc::new(camera).render(world).unwrap();
+
+        let loaded = load_rgb8(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        let (width, height, pixels) = loaded.unwrap();
+        assert_eq!((width, height), (12, 6));
+        assert_eq!(pixels.len(), 12 * 6 * 3);
+    }
+
+    #[test]
+    fn albedo_aov_shows_the_material_color() {
+        let mut camera = Camera::builder()
+            .image_width(9)
+            .aspect_ratio(1.0)
+            .samples_per_pixel(4)
+            .max_depth(2)
+            .build();
+        camera.set_progress(false);
+        camera.set_gamma(1.0);
+        let dir = std::env::temp_dir().join(format!("aov_render_{}", std::process::id()));
+        camera.set_output(dir.join("image.ppm").to_str().unwrap(), OutputFormat::Ppm);
+        camera.set_aov_output(&dir);
+
+        // The directory has to exist before the image is written into it
+        std::fs::create_dir_all(&dir).unwrap();
+        let material = Arc::new(Lambertian::new(Color3::new(0.2, 0.4, 0.6)));
+        let world = Arc::new(Sphere::new(Point3::new(0.0, 0.0, -3.0), 0.5, material));
+        let rendered = Arc::new(camera).render(world);
+
+        let albedo = load_rgb8(dir.join("albedo.ppm").to_str().unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+        rendered.unwrap();
+        let (width, _, pixels) = albedo.unwrap();
+        let center = 3 * (4 * width + 4);
+        assert_eq!(&pixels[center..center + 3], &[51, 102, 153]);
+        // The corners see the sky, which has no albedo
+        assert_eq!(&pixels[0..3], &[0, 0, 0]);
+    }
+
+    #[test]
+    fn empty_scene_shows_the_background() {
+        let mut camera = Camera::builder()
+            .image_width(8)
+            .samples_per_pixel(2)
+            .max_depth(2)
+            .build();
+        camera.set_progress(false);
+        camera.set_background(Color3::zero());
+        let image = Arc::new(camera).render_to_buffer(Arc::new(HittableList::new()));
+        assert!(image.as_raw().iter().all(|&channel| channel == 0));
+
+        // The gradient goes from white at the bottom to light blue at the top
+        let up = Camera::sky_gradient(&Ray::new(Point3::zero(), Vec3::new(0.0, 1.0, 0.0)));
+        let down = Camera::sky_gradient(&Ray::new(Point3::zero(), Vec3::new(0.0, -1.0, 0.0)));
+        assert_eq!((up.x, up.y, up.z), (0.5, 0.7, 1.0));
+        assert_eq!((down.x, down.y, down.z), (1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn lights_are_seen_at_their_emitted_color() {
+        let mut camera = Camera::builder().max_depth(4).build();
+        camera.set_background(Color3::zero());
+
+        let light = Arc::new(DiffuseLight::new(Color3::new(3.0, 2.0, 1.0)));
+        let world = Sphere::new(Point3::new(0.0, 0.0, -2.0), 0.5, light);
+        let ray = Ray::new(Point3::zero(), Vec3::new(0.0, 0.0, -1.0));
+        let color = camera.ray_color(ray, &world, 4, Color3::new(1.0, 1.0, 1.0), &mut []);
+        assert_eq!((color.x, color.y, color.z), (3.0, 2.0, 1.0));
+    }
+
+    #[test]
+    fn debug_modes_color_the_first_hit() {
+        let mut camera = Camera::builder().max_depth(4).build();
+        camera.set_background(Color3::new(0.1, 0.2, 0.3));
+        let gray = Arc::new(Lambertian::new(Color3::new(0.5, 0.5, 0.5)));
+        let world = Sphere::new(Point3::new(0.0, 0.0, -3.0), 1.0, gray);
+        let mut color = |render_mode: RenderMode, direction: Vec3| {
+            camera.set_render_mode(render_mode);
+            let ray = Ray::new(Point3::zero(), direction);
+            let color = camera.ray_color(ray, &world, 4, Color3::new(1.0, 1.0, 1.0), &mut []);
+            (color.x, color.y, color.z)
+        };
+
+        // The center of the sphere faces the camera, `0.5 * ((0, 0, 1) + 1)`
+        let forward = Vec3::new(0.0, 0.0, -1.0);
+        assert_eq!(color(RenderMode::Normals, forward), (0.5, 0.5, 1.0));
+        assert_eq!(
+            color(RenderMode::Normals, Vec3::new(0.0, 1.0, 0.0)),
+            (0.1, 0.2, 0.3)
+        );
+
+        // The hit is 2 units away, halfway between `near` and `far`
+        let depth = RenderMode::Depth {
+            near: 1.0,
+            far: 3.0,
+        };
+        assert_eq!(color(depth, forward), (0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn environment_map_is_looked_up_by_direction() {
+        // 4 × 3 image whose red channel is the pixel's index, row by row from the top
+        let pixels = (0..12).map(|k| Color3::new(k as f64, 0.0, 0.0)).collect();
+        let image = Arc::new(ImageTexture::from_pixels(4, 3, pixels));
+        let background = Background::EnvironmentMap(image);
+        let index = |direction: Vec3| background.radiance(&Ray::new(Point3::zero(), direction)).x;
+
+        // Straight up is the top row, the horizon is the middle one
+        assert_eq!(index(Vec3::new(0.0, 1.0, 0.0)), 2.0);
+        assert_eq!(index(Vec3::new(0.0, 0.0, 1.0)), 5.0);
+        assert_eq!(index(Vec3::new(0.0, 0.0, -3.0)), 7.0);
+        assert_eq!(index(Vec3::new(-1.0, -0.1, 0.01)), 4.0);
+    }
+
+    #[test]
+    fn lights_only_sees_through_unlit_surfaces() {
+        let mut camera = Camera::builder().max_depth(4).build();
+        camera.set_background(Color3::new(0.1, 0.2, 0.3));
+        camera.set_render_mode(RenderMode::LightsOnly);
+
+        let mut world = HittableList::new();
+        let gray = Arc::new(Lambertian::new(Color3::new(0.5, 0.5, 0.5)));
+        let light = Arc::new(DiffuseLight::new(Color3::new(4.0, 4.0, 4.0)));
+        world.add(Sphere::new(Point3::new(0.0, 0.0, -2.0), 0.5, gray.clone()));
+        world.add(Sphere::new(Point3::new(0.0, 0.0, -5.0), 0.5, light));
+        world.add(Sphere::new(Point3::new(2.0, 0.0, -2.0), 0.5, gray));
+
+        let color = |direction: Vec3| {
+            let ray = Ray::new(Point3::zero(), direction);
+            let color = camera.ray_color(ray, &world, 4, Color3::new(1.0, 1.0, 1.0), &mut []);
+            (color.x, color.y, color.z)
+        };
+        // The light behind the gray sphere shows through it
+        assert_eq!(color(Vec3::new(0.0, 0.0, -1.0)), (4.0, 4.0, 4.0));
+        // Gray spheres with nothing behind them show the background
+        assert_eq!(color(Vec3::new(1.0, 0.0, -1.0)), (0.1, 0.2, 0.3));
+        assert_eq!(color(Vec3::new(0.0, 1.0, 0.0)), (0.1, 0.2, 0.3));
+    }
+
+    #[test]
+    fn roulette_spares_the_first_bounces() {
+        let mut camera = Camera::builder().russian_roulette_min_bounces(4).build();
+        camera.set_russian_roulette(true);
+        // A black path would be ended every time once roulette applies
+        for bounce in 0..4 {
+            for _ in 0..100 {
+                assert_eq!(camera.roulette(bounce, Color3::zero()), Some(1.0));
+            }
+        }
+        assert_eq!(camera.roulette(4, Color3::zero()), None);
+        assert_eq!(camera.roulette(4, Color3::new(0.0, 2.0, 0.0)), Some(1.0));
+    }
+
+    #[test]
+    fn light_groups_add_up_to_the_image() {
+        let lit_camera = |lights: &[Light]| {
+            let mut camera = Camera::builder()
+                .image_width(8)
+                .aspect_ratio(1.0)
+                .samples_per_pixel(4)
+                .max_depth(4)
+                .build();
+            camera.set_background(Color3::zero());
+            for light in lights {
+                camera.add_light(*light);
+            }
+            camera
+        };
+        let first = Light::point(Point3::new(-2.0, 3.0, 0.0), Color3::new(8.0, 8.0, 8.0));
+        let second = Light::point(Point3::new(2.0, 3.0, 0.0), Color3::new(0.0, 4.0, 8.0));
+        let both = lit_camera(&[first, second]);
+
+        let mut world = HittableList::new();
+        let gray = Arc::new(Lambertian::new(Color3::new(0.5, 0.5, 0.5)));
+        world.add(Sphere::new(Point3::new(0.0, 0.0, -2.0), 0.5, gray.clone()));
+        world.add(Sphere::new(Point3::new(0.0, -100.5, -2.0), 100.0, gray));
+
+        for (i, j) in [(4, 4), (4, 7), (3, 7)] {
+            seed_thread_rng(7);
+            let groups = both.light_groups_pixel(i, j, &world);
+            seed_thread_rng(7);
+            let image = both.render_pixel(i, j, &world);
+            assert_eq!(groups.len(), 3);
+
+            let sum = groups
+                .iter()
+                .fold(Color3::zero(), |sum, group| sum + *group);
+            assert!((sum - image).length() < 1e-9);
+            assert!(groups[0].length() > 0.0 && groups[1].length() > 0.0);
+            // No emissive objects and a black sky
+            assert!(groups[2].length() < 1e-9);
+
+            // The first group is the image lit by the first light alone
+            seed_thread_rng(7);
+            let first_only = lit_camera(&[first]).render_pixel(i, j, &world);
+            assert!((groups[0] - first_only).length() < 1e-9);
+        }
+    }
+
+    fn tile_camera() -> Arc<Camera> {
+        let mut camera = Camera::builder()
+            .image_width(20)
+            .aspect_ratio(2.0)
+            .samples_per_pixel(4)
+            .max_depth(4)
+            .build();
+        camera.set_progress(false);
+        camera.set_threads(Some(2));
+        camera.set_tile_size(8);
+        Arc::new(camera)
+    }
+
+    #[test]
+    fn exported_tiles_survive_a_round_trip_bit_for_bit() {
+        let camera = tile_camera();
+        // 3 × 2 tiles, the ones on the right and bottom edges cut to 4 × 2
+        assert_eq!(camera.tile_count(), 6);
+
+        let material = Arc::new(Lambertian::new(Color3::new(0.5, 0.5, 0.5)));
+        let world = Arc::new(Sphere::new(Point3::new(0.0, 0.0, -1.0), 0.5, material));
+        let tiles = camera.export_tiles(world, [5, 0, 3, 9]);
+        let ids: Vec<TileId> = tiles.iter().map(|(tile_id, _, _)| *tile_id).collect();
+        assert_eq!(ids, [0, 3, 5]);
+        assert_eq!(tiles[2].1.len(), 4 * 2);
+        assert_eq!(tiles[2].2, 4);
+
+        let mut bytes = Vec::new();
+        write_tiles(&mut bytes, &tiles).unwrap();
+        let imported = read_tiles(&mut bytes.as_slice()).unwrap();
+        assert_eq!(imported.len(), tiles.len());
+        for ((id, pixels, samples), (imported_id, imported_pixels, imported_samples)) in
+            tiles.iter().zip(&imported)
+        {
+            assert_eq!((id, samples), (imported_id, imported_samples));
+            let bits = |pixels: &[Color3]| -> Vec<[u64; 3]> {
+                pixels
+                    .iter()
+                    .map(|p| [p.x.to_bits(), p.y.to_bits(), p.z.to_bits()])
+                    .collect()
+            };
+            assert_eq!(bits(pixels), bits(imported_pixels));
+        }
+
+        // Cut short files are errors
+        assert!(read_tiles(&mut &bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn merged_tiles_are_weighted_by_sample_count() {
+        let camera = tile_camera();
+        let gray = |value: f64, count: usize| vec![Color3::new(value, value, value); count];
+        let tiles = [
+            (0, gray(1.0, 64), 1),
+            (0, gray(5.0, 64), 3),
+            (5, gray(2.0, 8), 16),
+        ];
+        let pixels = camera.merge_tiles(&tiles).unwrap();
+        let at = |i: usize, j: usize| pixels[j * 20 + i].x;
+        assert_eq!(at(0, 0), 4.0);
+        assert_eq!(at(7, 7), 4.0);
+        assert_eq!(at(19, 9), 2.0);
+        // Tiles nobody rendered stay black
+        assert_eq!(at(8, 0), 0.0);
+
+        assert!(camera.merge_tiles(&[(6, gray(1.0, 8), 1)]).is_err());
+        assert!(camera.merge_tiles(&[(5, gray(1.0, 64), 1)]).is_err());
+        assert!(camera.import_and_merge(&tiles).is_ok());
+    }
+
+    #[test]
+    fn point_light_is_blocked_by_occluders() {
+        let mut camera = Camera::builder().build();
+        camera.add_light(Light::point(
+            Point3::new(0.0, 4.0, 0.0),
+            Color3::new(16.0, 16.0, 16.0),
+        ));
+
+        let gray = Arc::new(Lambertian::new(Color3::new(0.5, 0.5, 0.5)));
+        let floor = Quad::new(
+            Point3::new(-1.0, 0.0, -1.0),
+            Vec3::new(0.0, 0.0, 2.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            gray.clone(),
+        );
+        let ray = Ray::new(Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let hit_record = floor
+            .hit(&ray, Interval::new(0.001, f64::INFINITY))
+            .unwrap();
+        let direct_light = |objects: &dyn Hittable| {
+            let white = Color3::new(1.0, 1.0, 1.0);
+            camera
+                .direct_light(&ray, objects, &hit_record, white, &mut [])
+                .x
+        };
+
+        // `E = 16 / 4^2` straight above
+        assert!((direct_light(&floor) - 0.5 / PI).abs() < 1e-12);
+
+        let mut occluded = HittableList::new();
+        occluded.add(Sphere::new(Point3::new(0.0, 2.0, 0.0), 0.5, gray));
+        assert_eq!(direct_light(&occluded), 0.0);
+    }
+
+    #[test]
+    fn direct_light_uses_the_shading_normal() {
+        let mut camera = Camera::builder().build();
+        // `E = 8 / |(2, 2, 0)|^2 = 1` at the origin
+        camera.add_light(Light::point(
+            Point3::new(2.0, 2.0, 0.0),
+            Color3::new(8.0, 8.0, 8.0),
+        ));
+
+        // Tilted 45° from the surface's `+Y` normal towards `+X`, which is the bitangent
+        let tilted = Vec3::new(0.0, 1.0, 1.0).unit();
+        let normal_map = Arc::new(SolidColor::new(0.5 * (tilted + Color3::new(1.0, 1.0, 1.0))));
+        let gray = Arc::new(Lambertian::new(Color3::new(0.5, 0.5, 0.5)));
+        let direct_light = |material: Arc<dyn Material>| {
+            // `u` along +Z and `v` along +X, facing +Y
+            let floor = Quad::new(
+                Point3::new(-1.0, 0.0, -1.0),
+                Vec3::new(0.0, 0.0, 2.0),
+                Vec3::new(2.0, 0.0, 0.0),
+                material,
+            );
+            let ray = Ray::new(Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+            let hit_record = floor
+                .hit(&ray, Interval::new(0.001, f64::INFINITY))
+                .unwrap();
+            let color = camera.direct_light(
+                &ray,
+                &floor,
+                &hit_record,
+                Color3::new(1.0, 1.0, 1.0),
+                &mut [],
+            );
+            color.x
+        };
+
+        // The light lies along the tilted normal, and 45° off the flat one
+        let flat = direct_light(gray.clone());
+        let mapped = direct_light(Arc::new(NormalMapped::new(gray, normal_map)));
+        assert!((flat - 0.5 / PI * FRAC_1_SQRT_2).abs() < 1e-9);
+        assert!((mapped - 0.5 / PI).abs() < 1e-9);
+    }
+
+    #[test]
+    fn russian_roulette_keeps_the_mean() {
+        let mut camera = Camera::builder()
+            .max_depth(8)
+            .russian_roulette_min_bounces(0)
+            .build();
+        let mut world = HittableList::new();
+        let gray = Arc::new(Lambertian::new(Color3::new(0.5, 0.5, 0.5)));
+        world.add(Sphere::new(
+            Point3::new(0.0, -100.5, -1.0),
+            100.0,
+            gray.clone(),
+        ));
+        world.add(Sphere::new(Point3::new(0.0, 0.0, -1.0), 0.5, gray));
+
+        let mut mean = |russian_roulette: bool| {
+            camera.set_russian_roulette(russian_roulette);
+            const SAMPLES: usize = 20_000;
+            let mut sum = 0.0;
+            for seed in 0..4 {
+                seed_thread_rng(seed);
+                for _ in 0..SAMPLES / 4 {
+                    // Down onto the small sphere, where paths bounce between the two
+                    let ray = Ray::new(Point3::new(0.0, 0.2, 0.0), Vec3::new(0.0, -0.3, -1.0));
+                    let color =
+                        camera.ray_color(ray, &world, 8, Color3::new(1.0, 1.0, 1.0), &mut []);
+                    sum += color.y;
+                }
+            }
+            sum / SAMPLES as f64
+        };
+        let without = mean(false);
+        let with = mean(true);
+        assert!(
+            (with - without).abs() < 0.03 * without,
+            "{with} != {without}"
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use std::sync::Arc;
+
+use crate::aabb::AABB;
+use crate::hittable::HitRecord;
+use crate::hittable::Hittable;
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec::Point3;
+use crate::vec::Vec3;
+
+/// Line segment with a radius: a cylinder closed by two hemispherical caps.
+/// Thin capsules are a cheap way to render strands like hair or grass.
+#[derive(Debug)]
+pub struct Capsule {
+    a: Point3,
+    b: Point3,
+    radius: f64,
+    material: Arc<dyn Material>,
+    bbox: AABB,
+}
+
+impl Capsule {
+    /// `a` and `b` are the end points of the capsule's core segment
+    pub fn new(a: Point3, b: Point3, radius: f64, material: Arc<dyn Material>) -> Self {
+        assert!(radius >= 0.0);
+        let rvec = Vec3::new(radius, radius, radius);
+        let box_a = AABB::from_points(a - rvec, a + rvec);
+        let box_b = AABB::from_points(b - rvec, b + rvec);
+        Self {
+            a,
+            b,
+            radius,
+            material,
+            bbox: AABB::from_boxes(&box_a, &box_b),
+        }
+    }
+
+    /// Roots of `t^2 * qa + 2t * h + c = 0`, near root first
+    fn roots(qa: f64, h: f64, c: f64) -> Option<[f64; 2]> {
+        let discriminant = h * h - qa * c;
+        if discriminant < 0.0 || qa == 0.0 {
+            return None;
+        }
+        let sqrtd = discriminant.sqrt();
+        Some([(-h - sqrtd) / qa, (-h + sqrtd) / qa])
+    }
+}
+
+impl Hittable for Capsule {
+    /// ## Math
+    /// ### Variables
+    /// `A`, `B` are the segment end points, `ba = B - A`, `r` is the radius
+    /// Ray: `P(t) = O + t*d`, `oa = O - A`
+    /// ### Calculation
+    /// The side of the capsule is the infinite cylinder around `ba`. Removing the
+    /// component along the axis from `P(t) - A` and requiring its length to be `r` gives
+    /// `t^2 * (ba⋅ba * d⋅d - (ba⋅d)^2) + 2t * (ba⋅ba * d⋅oa - ba⋅oa * ba⋅d) + ba⋅ba * oa⋅oa - (ba⋅oa)^2 - r^2 * ba⋅ba = 0`
+    ///
+    /// A side hit is only valid if its projection on the axis, `y = ba⋅oa + t * ba⋅d`,
+    /// lies within `[0, ba⋅ba]`. Outside of that band the caps take over: spheres of
+    /// radius `r` around `A` (for `y < 0`) and `B` (for `y > ba⋅ba`).
+    ///
+    /// The nearest valid root within `ray_t` is the hit.
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let ba = self.b - self.a;
+        let oa = ray.origin - self.a;
+        let baba = ba.squared_length();
+        let bard = ba.dot(&ray.dir);
+        let baoa = ba.dot(&oa);
+        let dd = ray.dir.squared_length();
+
+        let mut closest: Option<(f64, Vec3)> = None;
+        let mut consider = |t: f64, center: Point3| {
+            if ray_t.surrounds(t) && closest.is_none_or(|(closest_t, _)| t < closest_t) {
+                closest = Some((t, center));
+            }
+        };
+
+        // Cylindrical body
+        let k2 = baba * dd - bard * bard;
+        let k1 = baba * ray.dir.dot(&oa) - baoa * bard;
+        let k0 = baba * oa.squared_length() - baoa * baoa - self.radius.powi(2) * baba;
+        if let Some(roots) = Self::roots(k2, k1, k0) {
+            let band = Interval::new(0.0, baba);
+            for t in roots {
+                let y = baoa + t * bard;
+                if band.contains_closed(y) {
+                    // Closest point on the axis, the normal points away from it
+                    consider(t, self.a + (y / baba) * ba);
+                }
+            }
+        }
+
+        // Hemispherical caps
+        for (center, is_end_cap) in [(self.a, false), (self.b, true)] {
+            let oc = ray.origin - center;
+            let c = oc.squared_length() - self.radius.powi(2);
+            if let Some(roots) = Self::roots(dd, ray.dir.dot(&oc), c) {
+                for t in roots {
+                    let y = baoa + t * bard;
+                    let is_outside_band = if is_end_cap { y > baba } else { y < 0.0 };
+                    if is_outside_band {
+                        consider(t, center);
+                    }
+                }
+            }
+        }
+
+        let (t, center) = closest?;
+        let hit_point = ray.at(t);
+        let normal = (hit_point - center) / self.radius;
+        Some(HitRecord::new(
+            hit_point,
+            normal,
+            ray,
+            Arc::clone(&self.material),
+            t,
+        ))
+    }
+
+    fn boundnig_box(&self) -> &AABB {
+        &self.bbox
+    }
+}
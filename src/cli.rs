@@ -0,0 +1,205 @@
+use ray_tracer::camera::Output;
+use ray_tracer::camera::OutputFormat;
+
+pub const USAGE: &str = "Usage: ray-tracer [OPTIONS]
+
+Options:
+    --width <PIXELS>      Image width [default: 1280]
+    --samples <COUNT>     Samples per pixel [default: 256]
+    --max-depth <COUNT>   Maximum number of bounces per ray [default: 50]
+    --output <PATH>       Output image, written as PNG if it ends in .png, or `-` to write a
+                          PPM to stdout [default: image.ppm]
+    --seed <SEED>         Seed the random number generator for reproducible renders
+    --scene <NAME>        Scene to render: bouncing_spheres, two_spheres, earth, cornell_box
+                          or earth_moon [default: bouncing_spheres]
+    --scene-file <PATH>   Render the JSON scene at PATH, whose camera settings take the place
+                          of `--scene`, `--width`, `--samples` and `--max-depth`, so those
+                          can't be combined with it
+    --metadata            Write the render settings as comments in PPM headers
+    --help                Print this message";
+
+/// Render settings given on the command line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Args {
+    pub width: usize,
+    pub samples: usize,
+    pub max_depth: usize,
+    pub output: String,
+    pub seed: Option<u64>,
+    pub scene: String,
+    pub scene_file: Option<String>,
+    pub metadata: bool,
+    pub help: bool,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            width: 1280,
+            samples: 256,
+            max_depth: 50,
+            output: String::from("image.ppm"),
+            seed: None,
+            scene: String::from("bouncing_spheres"),
+            scene_file: None,
+            metadata: false,
+            help: false,
+        }
+    }
+}
+
+impl Args {
+    /// Parses the arguments following the program name, accepting both `--flag value`
+    /// and `--flag=value`
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+        // Last flag given which the scene file would override
+        let mut scene_flag = None;
+
+        while let Some(arg) = args.next() {
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+
+            if flag == "--help" || flag == "-h" {
+                parsed.help = true;
+                continue;
+            }
+            if flag == "--metadata" {
+                parsed.metadata = true;
+                continue;
+            }
+
+            let value = match inline_value.or_else(|| args.next()) {
+                Some(value) => value,
+                None => return Err(format!("`{flag}` expects a value")),
+            };
+            match flag.as_str() {
+                "--width" => parsed.width = parse_number(&flag, &value)?,
+                "--samples" => parsed.samples = parse_number(&flag, &value)?,
+                "--max-depth" => parsed.max_depth = parse_number(&flag, &value)?,
+                "--output" => parsed.output = value,
+                "--seed" => parsed.seed = Some(parse_number(&flag, &value)?),
+                "--scene" => parsed.scene = value,
+                "--scene-file" => parsed.scene_file = Some(value),
+                _ => return Err(format!("unknown argument `{flag}`")),
+            }
+            if ["--width", "--samples", "--max-depth", "--scene"].contains(&flag.as_str()) {
+                scene_flag = Some(flag);
+            }
+        }
+
+        if let (Some(flag), Some(_)) = (scene_flag, &parsed.scene_file) {
+            return Err(format!(
+                "`{flag}` can't be combined with `--scene-file`, set it in the scene file instead"
+            ));
+        }
+
+        if parsed.width == 0 || parsed.samples == 0 {
+            return Err(String::from("`--width` and `--samples` must be at least 1"));
+        }
+        Ok(parsed)
+    }
+
+    /// Stdout for `-`, the file at the output path otherwise
+    pub fn output_target(&self) -> Output {
+        if self.output == "-" {
+            Output::Stdout
+        } else {
+            Output::from(self.output.as_str())
+        }
+    }
+
+    /// PNG when the output path ends in `.png`, PPM otherwise
+    pub fn output_format(&self) -> OutputFormat {
+        if self.output.to_ascii_lowercase().ends_with(".png") {
+            OutputFormat::Png
+        } else {
+            OutputFormat::Ppm
+        }
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("`{flag}` expects a non-negative integer, got `{value}`"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn no_flags_keep_the_defaults() {
+        assert_eq!(parse(&[]), Ok(Args::default()));
+    }
+
+    #[test]
+    fn flags_map_to_the_config() {
+        let args = parse(&[
+            "--width",
+            "640",
+            "--samples=16",
+            "--max-depth",
+            "8",
+            "--output",
+            "out.png",
+            "--seed=42",
+            "--scene",
+            "cornell_box",
+            "--metadata",
+        ]);
+        let expected = Args {
+            width: 640,
+            samples: 16,
+            max_depth: 8,
+            output: String::from("out.png"),
+            seed: Some(42),
+            scene: String::from("cornell_box"),
+            scene_file: None,
+            metadata: true,
+            help: false,
+        };
+        assert_eq!(args, Ok(expected));
+        assert_eq!(parse(&["-h"]).map(|args| args.help), Ok(true));
+    }
+
+    #[test]
+    fn malformed_flags_are_errors() {
+        assert!(parse(&["--width"]).is_err());
+        assert!(parse(&["--width", "-3"]).is_err());
+        assert!(parse(&["--samples", "many"]).is_err());
+        assert!(parse(&["--width", "0"]).is_err());
+        assert!(parse(&["--frobnicate", "1"]).is_err());
+    }
+
+    #[test]
+    fn scene_file_excludes_the_settings_it_replaces() {
+        let args = parse(&["--scene-file", "scene.json", "--seed", "1", "--output", "-"]).unwrap();
+        assert_eq!(args.scene_file.as_deref(), Some("scene.json"));
+
+        for flag in ["--width", "--samples", "--max-depth"] {
+            let error = parse(&["--scene-file", "scene.json", flag, "4"]).unwrap_err();
+            assert!(error.contains(flag), "{error}");
+        }
+        assert!(parse(&["--scene=earth", "--scene-file=scene.json"]).is_err());
+    }
+
+    #[test]
+    fn output_path_picks_the_target_and_format() {
+        let args = parse(&["--output", "-"]).unwrap();
+        assert!(matches!(args.output_target(), Output::Stdout));
+        assert_eq!(args.output_format(), OutputFormat::Ppm);
+
+        let args = parse(&["--output", "renders/Out.PNG"]).unwrap();
+        assert!(matches!(args.output_target(), Output::File(_)));
+        assert_eq!(args.output_format(), OutputFormat::Png);
+    }
+}
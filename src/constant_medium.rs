@@ -0,0 +1,204 @@
+use std::sync::Arc;
+
+use crate::aabb::AABB;
+use crate::hittable::HitRecord;
+use crate::hittable::Hittable;
+use crate::interval::Interval;
+use crate::material::Isotropic;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::texture::Texture;
+use crate::utils::random_percentage;
+use crate::vec::Color3;
+use crate::vec::Vec3;
+
+/// Volume of uniform density (fog, smoke) filling a closed `boundary`, optionally thinned out
+/// by a density texture
+pub struct ConstantMedium {
+    boundary: Arc<dyn Hittable>,
+    neg_inv_density: f64,
+    phase_function: Arc<dyn Material>,
+    density_texture: Option<Arc<dyn Texture>>, // Fraction of the density at every point
+}
+
+impl ConstantMedium {
+    pub fn new(boundary: Arc<dyn Hittable>, density: f64, albedo: Color3) -> Self {
+        Self::from_phase_function(boundary, density, Arc::new(Isotropic::new(albedo)))
+    }
+
+    pub fn from_texture(
+        boundary: Arc<dyn Hittable>,
+        density: f64,
+        texture: Arc<dyn Texture>,
+    ) -> Self {
+        Self::from_phase_function(
+            boundary,
+            density,
+            Arc::new(Isotropic::from_texture(texture)),
+        )
+    }
+
+    pub fn from_phase_function(
+        boundary: Arc<dyn Hittable>,
+        density: f64,
+        phase_function: Arc<dyn Material>,
+    ) -> Self {
+        assert!(density > 0.0);
+        Self {
+            boundary,
+            neg_inv_density: -1.0 / density,
+            phase_function,
+            density_texture: None,
+        }
+    }
+
+    /// Volume whose density at `p` is `max_density` scaled by the red channel of
+    /// `density_texture` at `p`, clamped to `[0, 1]`, e.g. a `VoxelTexture` holding a baked
+    /// smoke simulation
+    pub fn with_density_texture(
+        boundary: Arc<dyn Hittable>,
+        max_density: f64,
+        density_texture: Arc<dyn Texture>,
+        albedo: Color3,
+    ) -> Self {
+        Self {
+            density_texture: Some(density_texture),
+            ..Self::new(boundary, max_density, albedo)
+        }
+    }
+}
+
+impl Hittable for ConstantMedium {
+    /// ## Math
+    /// ### Variables
+    /// `t1`, `t2` are where the ray enters and leaves the boundary, `ρ` the density
+    /// ### Calculation
+    /// The probability of scattering over a small distance `Δl` is `ρ*Δl`, so the distance
+    /// travelled before scattering is exponentially distributed: `l = -(1/ρ) * ln(r)` with
+    /// `r` uniform in `(0, 1]`.
+    /// ### Outcomes
+    /// - The ray scatters at `t1 + l / |d|` if `l` is shorter than the path inside the volume
+    /// - Otherwise it passes through untouched
+    ///
+    /// With a density texture `ρ` is the maximum density and scattering is delta tracked: a
+    /// tentative scatter at `p` is kept with probability `ρ(p) / ρ`, otherwise the next one is
+    /// sampled from `p` the same way.
+    ///
+    /// The boundary must be convex, a ray leaving and re-entering it only sees the first span.
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let entry = self
+            .boundary
+            .hit(ray, Interval::new(f64::NEG_INFINITY, f64::INFINITY))?;
+        let exit = self
+            .boundary
+            .hit(ray, Interval::new(entry.t + 0.0001, f64::INFINITY))?;
+
+        // Clip the span inside the volume to the queried range, the ray may start inside it
+        let t1 = entry.t.max(ray_t.min).max(0.0);
+        let t2 = exit.t.min(ray_t.max);
+        if t1 >= t2 {
+            return None;
+        }
+
+        let ray_length = ray.dir.length();
+        let distance_inside_boundary = (t2 - t1) * ray_length;
+        let mut hit_distance = 0.0;
+        loop {
+            hit_distance += self.neg_inv_density * (1.0 - random_percentage()).ln();
+            if hit_distance > distance_inside_boundary {
+                return None;
+            }
+            let Some(density_texture) = &self.density_texture else {
+                break;
+            };
+            let p = ray.at(t1 + hit_distance / ray_length);
+            let fraction = density_texture.value(0.0, 0.0, &p).x.clamp(0.0, 1.0);
+            if random_percentage() < fraction {
+                break;
+            }
+        }
+
+        let t = t1 + hit_distance / ray_length;
+        // Normal and facing are arbitrary, the isotropic phase function ignores them
+        let mut hit_record = HitRecord::new(
+            ray.at(t),
+            Vec3::new(1.0, 0.0, 0.0),
+            ray,
+            Arc::clone(&self.phase_function),
+            t,
+        );
+        hit_record.is_front_face = true;
+        Some(hit_record)
+    }
+
+    fn boundnig_box(&self) -> &AABB {
+        self.boundary.boundnig_box()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::quad::make_box;
+    use crate::sphere::Sphere;
+    use crate::texture::VoxelTexture;
+    use crate::utils::seed_thread_rng;
+    use crate::vec::Point3;
+
+    #[test]
+    fn dense_media_scatter_more_rays_than_sparse_ones() {
+        let fraction_scattered = |density: f64| {
+            let white = Arc::new(Lambertian::new(Color3::new(1.0, 1.0, 1.0)));
+            let sphere = Arc::new(Sphere::new(Point3::zero(), 1.0, white));
+            let medium = ConstantMedium::new(sphere, density, Color3::new(1.0, 1.0, 1.0));
+
+            // Straight through the middle, 2 units of medium
+            let ray = Ray::new(Point3::new(0.0, 0.0, -3.0), Vec3::new(0.0, 0.0, 1.0));
+            let hits = (0..1000)
+                .filter(|_| {
+                    medium
+                        .hit(&ray, Interval::new(0.001, f64::INFINITY))
+                        .is_some()
+                })
+                .count();
+            hits as f64 / 1000.0
+        };
+
+        seed_thread_rng(3);
+        assert!(fraction_scattered(5.0) > 0.95);
+        assert!(fraction_scattered(0.01) < 0.1);
+    }
+
+    #[test]
+    fn voxel_density_drives_scattering() {
+        // Dense along x < 1, empty along x > 1
+        let bounds = AABB::from_points(Point3::zero(), Point3::new(2.0, 1.0, 1.0));
+        let texture = Arc::new(VoxelTexture::new([2, 1, 1], vec![1.0, 0.0], bounds));
+        let white = Arc::new(Lambertian::new(Color3::new(1.0, 1.0, 1.0)));
+        let medium = ConstantMedium::with_density_texture(
+            Arc::new(make_box(Point3::zero(), Point3::new(2.0, 1.0, 1.0), white)),
+            2.0,
+            texture,
+            Color3::new(1.0, 1.0, 1.0),
+        );
+
+        seed_thread_rng(5);
+        const RAYS: usize = 4000;
+        let scattered = |x: f64| {
+            let ray = Ray::new(Point3::new(x, 0.5, -1.0), Vec3::new(0.0, 0.0, 1.0));
+            let hits = (0..RAYS)
+                .filter(|_| {
+                    medium
+                        .hit(&ray, Interval::new(0.001, f64::INFINITY))
+                        .is_some()
+                })
+                .count();
+            hits as f64 / RAYS as f64
+        };
+        // A unit of depth at density `ρ` scatters `1 - e^-ρ` of the rays
+        assert!((scattered(0.1) - (1.0 - (-2.0_f64).exp())).abs() < 0.04);
+        assert!((scattered(1.0) - (1.0 - (-1.0_f64).exp())).abs() < 0.04);
+        assert_eq!(scattered(1.9), 0.0);
+    }
+}
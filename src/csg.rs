@@ -0,0 +1,192 @@
+use std::sync::Arc;
+
+use crate::aabb::AABB;
+use crate::hittable::HitRecord;
+use crate::hittable::Hittable;
+use crate::interval::Interval;
+use crate::ray::Ray;
+
+/// Walks the crossings of `a` and `b` along `ray` and returns the first one within `ray_t`
+/// where `inside(in_a, in_b)` changes, i.e. the first surface of the combined solid
+///
+/// ## Math
+/// ### Calculation
+/// Both objects must be closed. Crossings are collected from `ray_t.min` on with no upper
+/// bound: whether the ray starts inside an object is only known from its first crossing, an
+/// exit (`!is_front_face`) meaning it does. Every later crossing toggles that state.
+///
+/// ### Outcomes
+/// - Surfaces of `b` get their normal flipped when `flip_b` is set, a carved out surface
+///   faces into `b`
+fn first_boundary(
+    a: &dyn Hittable,
+    b: &dyn Hittable,
+    ray: &Ray,
+    ray_t: Interval,
+    inside: impl Fn(bool, bool) -> bool,
+    flip_b: bool,
+) -> Option<HitRecord> {
+    let search = Interval::new(ray_t.min, f64::INFINITY);
+    let hits_a = a.hit_all(ray, search.clone());
+    let hits_b = b.hit_all(ray, search);
+
+    let mut in_a = hits_a.first().is_some_and(|hit| !hit.is_front_face);
+    let mut in_b = hits_b.first().is_some_and(|hit| !hit.is_front_face);
+    let mut was_inside = inside(in_a, in_b);
+
+    let mut hits_a = hits_a.into_iter().peekable();
+    let mut hits_b = hits_b.into_iter().peekable();
+    loop {
+        let from_a = match (hits_a.peek(), hits_b.peek()) {
+            (Some(hit_a), Some(hit_b)) => hit_a.t <= hit_b.t,
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (None, None) => return None,
+        };
+        let mut hit_record = if from_a {
+            in_a = !in_a;
+            hits_a.next()?
+        } else {
+            in_b = !in_b;
+            hits_b.next()?
+        };
+        if hit_record.t >= ray_t.max {
+            return None;
+        }
+
+        let is_inside = inside(in_a, in_b);
+        if is_inside != was_inside {
+            if !from_a && flip_b {
+                hit_record.normal = -hit_record.normal;
+                hit_record.is_front_face = !hit_record.is_front_face;
+            }
+            return Some(hit_record);
+        }
+        was_inside = is_inside;
+    }
+}
+
+/// Everything inside `a` but not inside `b`, e.g. a sphere with a bite taken out of it
+pub struct Difference {
+    a: Arc<dyn Hittable>,
+    b: Arc<dyn Hittable>,
+}
+
+impl Difference {
+    /// Both objects must be closed
+    pub fn new(a: Arc<dyn Hittable>, b: Arc<dyn Hittable>) -> Self {
+        Self { a, b }
+    }
+}
+
+impl Hittable for Difference {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        if !self.a.boundnig_box().hit(ray, &ray_t) {
+            return None;
+        }
+        first_boundary(
+            self.a.as_ref(),
+            self.b.as_ref(),
+            ray,
+            ray_t,
+            |in_a, in_b| in_a && !in_b,
+            true,
+        )
+    }
+
+    /// Carving can only remove from `a`
+    fn boundnig_box(&self) -> &AABB {
+        self.a.boundnig_box()
+    }
+}
+
+/// Only what is inside both `a` and `b`
+pub struct Intersection {
+    a: Arc<dyn Hittable>,
+    b: Arc<dyn Hittable>,
+    bbox: AABB,
+}
+
+impl Intersection {
+    /// Both objects must be closed
+    pub fn new(a: Arc<dyn Hittable>, b: Arc<dyn Hittable>) -> Self {
+        let (box_a, box_b) = (a.boundnig_box(), b.boundnig_box());
+        let overlap = |axis: usize| {
+            let (i1, i2) = (box_a.axis_interval(axis), box_b.axis_interval(axis));
+            Interval {
+                min: i1.min.max(i2.min),
+                max: i1.max.min(i2.max),
+            }
+        };
+        let bbox = AABB::new(overlap(0), overlap(1), overlap(2));
+        Self { a, b, bbox }
+    }
+}
+
+impl Hittable for Intersection {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        if self.bbox.is_empty() || !self.bbox.hit(ray, &ray_t) {
+            return None;
+        }
+        first_boundary(
+            self.a.as_ref(),
+            self.b.as_ref(),
+            ray,
+            ray_t,
+            |in_a, in_b| in_a && in_b,
+            false,
+        )
+    }
+
+    fn boundnig_box(&self) -> &AABB {
+        &self.bbox
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::sphere::Sphere;
+    use crate::vec::Color3;
+    use crate::vec::Point3;
+    use crate::vec::Vec3;
+
+    fn sphere(center: Point3, radius: f64) -> Arc<dyn Hittable> {
+        let material = Arc::new(Lambertian::new(Color3::new(0.5, 0.5, 0.5)));
+        Arc::new(Sphere::new(center, radius, material))
+    }
+
+    #[test]
+    fn sphere_minus_sphere_hits_the_carved_surface() {
+        // A bite out of the front of the unit sphere, reaching `z = 0.5`
+        let carved = Difference::new(
+            sphere(Point3::zero(), 1.0),
+            sphere(Point3::new(0.0, 0.0, 1.0), 0.5),
+        );
+        let t_range = Interval::new(0.001, f64::INFINITY);
+
+        let ray = Ray::new(Point3::new(0.0, 0.0, 3.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_record = carved.hit(&ray, t_range.clone()).unwrap();
+        assert!((hit_record.p.z - 0.5).abs() < 1e-9);
+        // The carved surface faces out of the bite, towards the ray
+        assert!((hit_record.normal.z - 1.0).abs() < 1e-9);
+        assert!(hit_record.is_front_face);
+
+        // Away from the bite the outer surface is untouched
+        let ray = Ray::new(Point3::new(0.0, 3.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let hit_record = carved.hit(&ray, t_range.clone()).unwrap();
+        assert!((hit_record.p.y - 1.0).abs() < 1e-9);
+
+        // Only the overlap of the two is left by an intersection
+        let lens = Intersection::new(
+            sphere(Point3::zero(), 1.0),
+            sphere(Point3::new(0.0, 0.0, 1.0), 0.5),
+        );
+        let ray = Ray::new(Point3::new(0.0, 0.0, 3.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_record = lens.hit(&ray, t_range.clone()).unwrap();
+        assert!((hit_record.p.z - 1.0).abs() < 1e-9);
+        let ray = Ray::new(Point3::new(0.0, 3.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!(lens.hit(&ray, t_range).is_none());
+    }
+}
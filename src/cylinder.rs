@@ -0,0 +1,225 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use crate::aabb::AABB;
+use crate::hittable::HitRecord;
+use crate::hittable::Hittable;
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::vec::Point3;
+use crate::vec::Vec3;
+
+/// Cylinder closed by two flat disks
+#[derive(Debug)]
+pub struct Cylinder {
+    base: Point3, // Center of the bottom cap
+    uvw: Onb,     // `w` is the unit axis, `u` and `v` give the angle around it
+    radius: f64,
+    height: f64,
+    material: Arc<dyn Material>,
+    bbox: AABB,
+}
+
+impl Cylinder {
+    /// `axis` points from the bottom cap towards the top one and doesn't need to be a unit
+    /// vector, the cylinder is `height` long along it
+    pub fn new(
+        base: Point3,
+        axis: Vec3,
+        radius: f64,
+        height: f64,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        assert!(radius > 0.0 && height > 0.0);
+        let uvw = Onb::new(&axis);
+        let w = uvw.w();
+        let top = base + height * w;
+
+        // A disk of normal `w` reaches `r * sqrt(1 - w_i^2)` away from its center along axis `i`
+        let extent = radius
+            * Vec3::new(
+                (1.0 - w.x * w.x).max(0.0).sqrt(),
+                (1.0 - w.y * w.y).max(0.0).sqrt(),
+                (1.0 - w.z * w.z).max(0.0).sqrt(),
+            );
+        let box_base = AABB::from_points(base - extent, base + extent);
+        let box_top = AABB::from_points(top - extent, top + extent);
+
+        Self {
+            base,
+            uvw,
+            radius,
+            height,
+            material,
+            bbox: AABB::from_boxes(&box_base, &box_top).pad_to_minimum(),
+        }
+    }
+
+    /// Angle of the point `local` (relative to the base) around the axis, normalized to `[0, 1]`
+    fn angle(&self, local: &Vec3) -> f64 {
+        let x = local.dot(&self.uvw.u());
+        let y = local.dot(&self.uvw.v());
+        (y.atan2(x) + PI) / (2.0 * PI)
+    }
+}
+
+impl Hittable for Cylinder {
+    /// ## Math
+    /// ### Variables
+    /// `B` is the base center, `w` the unit axis, `r` the radius and `h` the height
+    /// Ray: `P(t) = O + t*d`, `ob = O - B`
+    /// ### Calculation
+    /// Removing the component along the axis from `P(t) - B` and requiring its length to be
+    /// `r` gives the infinite cylinder:
+    /// `t^2 * (d⋅d - (w⋅d)^2) + 2t * (d⋅ob - (w⋅d)(w⋅ob)) + ob⋅ob - (w⋅ob)^2 - r^2 = 0`
+    ///
+    /// A side hit is only valid if its height `y = w⋅ob + t * w⋅d` lies within `[0, h]`.
+    ///
+    /// The caps are the planes `y = 0` and `y = h`, hit at `t = (cap_y - w⋅ob) / w⋅d`, and the
+    /// hit point must be within `r` of the axis.
+    ///
+    /// ### Outcomes
+    /// - The nearest valid hit within `ray_t` among the two side roots and two caps
+    /// - Side normals point away from the axis, cap normals along `-w` and `w`
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let w = self.uvw.w();
+        let ob = ray.origin - self.base;
+        let wd = w.dot(&ray.dir);
+        let wob = w.dot(&ob);
+
+        let mut closest: Option<(f64, Vec3)> = None;
+        let mut consider = |t: f64, normal: Vec3| {
+            if ray_t.surrounds(t) && closest.is_none_or(|(closest_t, _)| t < closest_t) {
+                closest = Some((t, normal));
+            }
+        };
+
+        // Side, the quadratic is degenerate for rays parallel to the axis
+        let a = ray.dir.squared_length() - wd * wd;
+        let h = ray.dir.dot(&ob) - wd * wob;
+        let c = ob.squared_length() - wob * wob - self.radius.powi(2);
+        let discriminant = h * h - a * c;
+        if a > 1e-12 && discriminant >= 0.0 {
+            let sqrtd = discriminant.sqrt();
+            let band = Interval::new(0.0, self.height);
+            for t in [(-h - sqrtd) / a, (-h + sqrtd) / a] {
+                let y = wob + t * wd;
+                if band.contains_closed(y) {
+                    let radial = ob + t * ray.dir - y * w;
+                    consider(t, radial / self.radius);
+                }
+            }
+        }
+
+        // Caps
+        if wd.abs() > 1e-12 {
+            for (cap_y, normal) in [(0.0, -w), (self.height, w)] {
+                let t = (cap_y - wob) / wd;
+                let radial = ob + t * ray.dir - cap_y * w;
+                if radial.squared_length() <= self.radius.powi(2) {
+                    consider(t, normal);
+                }
+            }
+        }
+
+        let (t, normal) = closest?;
+        let hit_point = ray.at(t);
+        let mut hit_record = HitRecord::new(hit_point, normal, ray, Arc::clone(&self.material), t);
+
+        // Side: angle around the axis and height. Caps: position on the disk.
+        let local = hit_point - self.base;
+        let y = local.dot(&w);
+        let is_cap = normal.dot(&w).abs() > 0.5;
+        (hit_record.u, hit_record.v) = if is_cap {
+            let radial = local - y * w;
+            (
+                0.5 + 0.5 * radial.dot(&self.uvw.u()) / self.radius,
+                0.5 + 0.5 * radial.dot(&self.uvw.v()) / self.radius,
+            )
+        } else {
+            (self.angle(&local), y / self.height)
+        };
+        (hit_record.tangent, hit_record.bitangent) = if is_cap {
+            (self.uvw.u(), self.uvw.v())
+        } else {
+            // Derivative of `cos(a) u + sin(a) v`, the normal, with respect to the angle `a`
+            let tangent = -normal.dot(&self.uvw.v()) * self.uvw.u()
+                + normal.dot(&self.uvw.u()) * self.uvw.v();
+            (tangent, w)
+        };
+        Some(hit_record)
+    }
+
+    fn boundnig_box(&self) -> &AABB {
+        &self.bbox
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::vec::Color3;
+
+    fn cylinder() -> Cylinder {
+        let material = Arc::new(Lambertian::new(Color3::new(0.5, 0.5, 0.5)));
+        Cylinder::new(Point3::zero(), Vec3::new(0.0, 1.0, 0.0), 1.0, 2.0, material)
+    }
+
+    #[test]
+    fn side_is_hit_exactly_at_the_rims() {
+        let cylinder = cylinder();
+        // Level with the top and bottom rims, the caps are seen edge-on
+        for y in [0.0, 2.0] {
+            let ray = Ray::new(Point3::new(-3.0, y, 0.0), Vec3::new(1.0, 0.0, 0.0));
+            let hit_record = cylinder
+                .hit(&ray, Interval::new(0.001, f64::INFINITY))
+                .unwrap();
+            assert_eq!(hit_record.t, 2.0);
+            assert_eq!(hit_record.normal.x, -1.0);
+        }
+    }
+
+    #[test]
+    fn side_and_caps_are_hit_with_outward_normals() {
+        let cylinder = cylinder();
+        let hit = |origin: Point3, direction: Vec3| {
+            cylinder.hit(
+                &Ray::new(origin, direction),
+                Interval::new(0.001, f64::INFINITY),
+            )
+        };
+
+        let side = hit(Point3::new(0.0, 1.0, 5.0), Vec3::new(0.0, 0.0, -1.0)).unwrap();
+        assert_eq!(side.t, 4.0);
+        assert_eq!(
+            (side.normal.x, side.normal.y, side.normal.z),
+            (0.0, 0.0, 1.0)
+        );
+        assert!((side.v - 0.5).abs() < 1e-12);
+
+        let top = hit(Point3::new(0.5, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0)).unwrap();
+        assert_eq!(top.t, 3.0);
+        assert_eq!(top.normal.y, 1.0);
+        let bottom = hit(Point3::new(0.5, -5.0, 0.0), Vec3::new(0.0, 1.0, 0.0)).unwrap();
+        assert_eq!(bottom.t, 5.0);
+        assert_eq!(bottom.normal.y, -1.0);
+    }
+
+    #[test]
+    fn rays_beyond_the_height_miss() {
+        let cylinder = cylinder();
+        let ray_t = Interval::new(0.001, f64::INFINITY);
+        // Through where the infinite cylinder would be, above the top cap
+        let above = Ray::new(Point3::new(0.0, 2.5, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(cylinder.hit(&above, ray_t.clone()).is_none());
+        // Along the axis, starting past the top and going away from it
+        let along_axis = Ray::new(Point3::new(0.0, 3.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        assert!(cylinder.hit(&along_axis, ray_t.clone()).is_none());
+        // Parallel to the axis, outside of the radius
+        let outside = Ray::new(Point3::new(1.5, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!(cylinder.hit(&outside, ray_t).is_none());
+    }
+}
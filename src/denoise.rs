@@ -0,0 +1,89 @@
+use crate::vec::Color3;
+
+/// Edge-aware blur of a `width × height` image stored row by row
+///
+/// ## Math
+/// ### Variables
+/// `σs` is `sigma_spatial` in pixels and `σc` is `sigma_color` in linear color units
+/// ### Calculation
+/// Each pixel `p` becomes the weighted average of the pixels `q` within `2σs` of it, with
+/// `w(q) = exp(-|p - q|^2 / 2σs^2) * exp(-|c(p) - c(q)|^2 / 2σc^2)`
+/// ### Outcomes
+/// - Neighbors of similar color are averaged together, smoothing noise in flat areas
+/// - Neighbors across an edge differ by much more than `σc` and barely contribute, so the edge
+///   stays sharp
+pub fn bilateral(
+    pixels: &[Color3],
+    width: usize,
+    height: usize,
+    sigma_spatial: f64,
+    sigma_color: f64,
+) -> Vec<Color3> {
+    assert_eq!(pixels.len(), width * height);
+    assert!(sigma_spatial > 0.0 && sigma_color > 0.0);
+    let radius = (2.0 * sigma_spatial).ceil() as usize;
+    let spatial_factor = -0.5 / sigma_spatial.powi(2);
+    let color_factor = -0.5 / sigma_color.powi(2);
+
+    let mut denoised = Vec::with_capacity(pixels.len());
+    for j in 0..height {
+        for i in 0..width {
+            let center = pixels[j * width + i];
+            let mut sum = Color3::zero();
+            let mut weight_sum = 0.0;
+            for y in j.saturating_sub(radius)..(j + radius + 1).min(height) {
+                for x in i.saturating_sub(radius)..(i + radius + 1).min(width) {
+                    let neighbor = pixels[y * width + x];
+                    let distance_squared = (x.abs_diff(i).pow(2) + y.abs_diff(j).pow(2)) as f64;
+                    let weight = (spatial_factor * distance_squared
+                        + color_factor * (neighbor - center).squared_length())
+                    .exp();
+                    sum += weight * neighbor;
+                    weight_sum += weight;
+                }
+            }
+            // The center pixel always has a weight of 1, so `weight_sum` can't be 0
+            denoised.push(sum / weight_sum);
+        }
+    }
+    denoised
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::random_f64;
+    use crate::utils::seed_thread_rng;
+
+    fn variance(pixels: &[Color3]) -> f64 {
+        let mean = pixels.iter().map(|p| p.x).sum::<f64>() / pixels.len() as f64;
+        pixels.iter().map(|p| (p.x - mean).powi(2)).sum::<f64>() / pixels.len() as f64
+    }
+
+    #[test]
+    fn noise_in_flat_patches_is_smoothed() {
+        seed_thread_rng(29);
+        let noisy: Vec<Color3> = (0..16 * 16)
+            .map(|_| {
+                let gray = 0.5 + random_f64(-0.05, 0.05);
+                Color3::new(gray, gray, gray)
+            })
+            .collect();
+        let denoised = bilateral(&noisy, 16, 16, 1.5, 0.2);
+        assert!(variance(&denoised) < 0.25 * variance(&noisy));
+    }
+
+    #[test]
+    fn hard_edges_stay_sharp() {
+        let dark = Color3::new(0.1, 0.1, 0.1);
+        let light = Color3::new(0.9, 0.9, 0.9);
+        let edge: Vec<Color3> = (0..8 * 8)
+            .map(|k| if k % 8 < 4 { dark } else { light })
+            .collect();
+
+        let denoised = bilateral(&edge, 8, 8, 2.0, 0.1);
+        for (before, after) in edge.iter().zip(&denoised) {
+            assert!((*before - *after).length() < 1e-3);
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use crate::aabb::AABB;
+use crate::hittable::HitRecord;
+use crate::hittable::Hittable;
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::vec::Point3;
+use crate::vec::Vec3;
+
+/// Flat circle of `radius` around `center`, facing along `normal`
+#[derive(Debug)]
+pub struct Disk {
+    center: Point3,
+    uvw: Onb, // `w` is the unit normal, `u` and `v` give the angle around it
+    radius: f64,
+    material: Arc<dyn Material>,
+    bbox: AABB,
+}
+
+impl Disk {
+    /// `normal` doesn't need to be a unit vector
+    pub fn new(center: Point3, normal: Vec3, radius: f64, material: Arc<dyn Material>) -> Self {
+        assert!(radius > 0.0);
+        let uvw = Onb::new(&normal);
+        let w = uvw.w();
+
+        // A disk of normal `w` reaches `r * sqrt(1 - w_i^2)` away from its center along axis `i`
+        let extent = radius
+            * Vec3::new(
+                (1.0 - w.x * w.x).max(0.0).sqrt(),
+                (1.0 - w.y * w.y).max(0.0).sqrt(),
+                (1.0 - w.z * w.z).max(0.0).sqrt(),
+            );
+
+        Self {
+            center,
+            uvw,
+            radius,
+            material,
+            bbox: AABB::from_points(center - extent, center + extent).pad_to_minimum(),
+        }
+    }
+}
+
+impl Hittable for Disk {
+    /// ## Math
+    /// ### Variables
+    /// `C` is the center, `n` the unit normal and `r` the radius
+    /// Ray: `P(t) = O + t*d`
+    /// ### Calculation
+    /// The disk's plane is `n ⋅ (P - C) = 0`. Substituting the ray gives
+    /// `t = n ⋅ (C - O) / (n ⋅ d)`, which doesn't exist if the ray is parallel to the plane.
+    ///
+    /// ### Outcomes
+    /// - The point is on the disk if `|P(t) - C|^2 <= r^2`
+    /// - `u` is the angle around the normal normalized to `[0, 1]`, `v` the distance from the
+    ///   center over the radius
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let normal = self.uvw.w();
+        let denom = normal.dot(&ray.dir);
+        // Ray is parallel to the plane
+        if denom.abs() < 1e-8 {
+            return None;
+        }
+
+        let t = normal.dot(&(self.center - ray.origin)) / denom;
+        if !ray_t.surrounds(t) {
+            return None;
+        }
+
+        let intersection = ray.at(t);
+        let radial = intersection - self.center;
+        if radial.squared_length() > self.radius.powi(2) {
+            return None;
+        }
+
+        let mut hit_record =
+            HitRecord::new(intersection, normal, ray, Arc::clone(&self.material), t);
+        let x = radial.dot(&self.uvw.u());
+        let y = radial.dot(&self.uvw.v());
+        (hit_record.u, hit_record.v) = (
+            (y.atan2(x) + PI) / (2.0 * PI),
+            radial.length() / self.radius,
+        );
+        Some(hit_record)
+    }
+
+    fn boundnig_box(&self) -> &AABB {
+        &self.bbox
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::vec::Color3;
+
+    #[test]
+    fn disk_is_hit_up_to_its_rim() {
+        let material = Arc::new(Lambertian::new(Color3::new(0.5, 0.5, 0.5)));
+        let disk = Disk::new(
+            Point3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, 3.0, 0.0),
+            2.0,
+            material,
+        );
+        let from_above = |x: f64| {
+            let ray = Ray::new(Point3::new(x, 4.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+            disk.hit(&ray, Interval::new(0.001, f64::INFINITY))
+        };
+
+        let center = from_above(0.0).unwrap();
+        assert_eq!(center.t, 3.0);
+        assert_eq!(center.v, 0.0);
+        assert_eq!(center.normal.y, 1.0);
+        assert!(center.is_front_face);
+
+        let inside_rim = from_above(1.999).unwrap();
+        assert!((inside_rim.v - 0.9995).abs() < 1e-12);
+        assert!(from_above(2.001).is_none());
+
+        // Thin along the normal, as wide as the disk across it
+        let bbox = disk.boundnig_box();
+        assert_eq!((bbox.min().x, bbox.max().x), (-2.0, 2.0));
+        assert!(bbox.min().y < 1.0 && bbox.max().y > 1.0);
+    }
+}
@@ -0,0 +1,101 @@
+use crate::aabb::AABB;
+use crate::hittable::HitRecord;
+use crate::hittable::Hittable;
+use crate::hittable::HittableList;
+use crate::interval::Interval;
+use crate::ray::Ray;
+use crate::transform::Transform;
+use crate::transform::world_pdf;
+use crate::vec::Point3;
+use crate::vec::Vec3;
+
+/// Node of a scene graph, places its children with a local transform relative to its parent
+///
+/// Groups can be nested, a child group's transform is relative to its parent so moving a
+/// parent moves everything below it (e.g. rotating a shoulder swings the whole arm).
+pub struct Group {
+    children: HittableList,
+    transform: Transform,
+    inverse: Transform,
+    bbox: AABB,
+}
+
+impl Group {
+    pub fn new(children: HittableList, transform: Transform) -> Self {
+        let bbox = transform.apply_box(children.boundnig_box());
+        Self {
+            children,
+            inverse: transform.inverse(),
+            transform,
+            bbox,
+        }
+    }
+
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn local_ray(&self, ray: &Ray) -> Ray {
+        Ray::new_time(
+            self.inverse.apply_point(ray.origin),
+            self.inverse.apply_vector(ray.dir),
+            ray.tm,
+        )
+    }
+
+    fn to_world(&self, mut hit_record: HitRecord) -> HitRecord {
+        hit_record.p = self.transform.apply_point(hit_record.p);
+        hit_record.normal = self.inverse.apply_transpose(hit_record.normal).unit();
+        hit_record.tangent = self.transform.apply_vector(hit_record.tangent);
+        hit_record.bitangent = self.transform.apply_vector(hit_record.bitangent);
+        hit_record
+    }
+}
+
+impl Hittable for Group {
+    /// The ray is taken into the group's local space by the inverse transform. Since the
+    /// transform is affine the ray parameter `t` is the same in both spaces.
+    ///
+    /// Hit points and surface directions go back through the transform and normals through
+    /// the inverse-transpose, which keeps them perpendicular under non-uniform scaling.
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        self.children
+            .hit(&self.local_ray(ray), ray_t)
+            .map(|hit_record| self.to_world(hit_record))
+    }
+
+    fn boundnig_box(&self) -> &AABB {
+        &self.bbox
+    }
+
+    fn occluded(&self, ray: &Ray, ray_t: Interval) -> bool {
+        self.children.occluded(&self.local_ray(ray), ray_t)
+    }
+
+    fn hit_all(&self, ray: &Ray, ray_t: Interval) -> Vec<HitRecord> {
+        self.children
+            .hit_all(&self.local_ray(ray), ray_t)
+            .into_iter()
+            .map(|hit_record| self.to_world(hit_record))
+            .collect()
+    }
+
+    /// The transform may scale, the children's density is corrected with `world_pdf`
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        let local_direction = self.inverse.apply_vector(*direction);
+        let local_pdf = self
+            .children
+            .pdf_value(&self.inverse.apply_point(*origin), &local_direction);
+        world_pdf(
+            local_pdf,
+            direction,
+            &local_direction,
+            self.inverse.determinant(),
+        )
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        let local_direction = self.children.random(&self.inverse.apply_point(*origin));
+        self.transform.apply_vector(local_direction)
+    }
+}
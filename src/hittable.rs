@@ -1,9 +1,11 @@
 use std::sync::Arc;
 
 use crate::aabb::AABB;
+use crate::bvh::BVHNode;
 use crate::interval::Interval;
 use crate::material::Material;
 use crate::ray::Ray;
+use crate::utils::random_u64;
 use crate::vec::Point3;
 use crate::vec::Vec3;
 
@@ -13,7 +15,12 @@ pub struct HitRecord {
     pub normal: Vec3,
     pub material: Arc<dyn Material>,
     pub t: f64,
+    pub u: f64, // Surface coordinates of the hit point
+    pub v: f64,
     pub is_front_face: bool,
+    pub tangent: Vec3, // Directions of increasing `u` and `v` along the surface, not unit vectors.
+    pub bitangent: Vec3, // Zero when the object doesn't provide them.
+    pub object_id: Option<usize>, // Set when the hit object was tagged by `HittableList::assign_ids`
 }
 
 impl HitRecord {
@@ -30,7 +37,21 @@ impl HitRecord {
             normal: outward_normal,
             material,
             t,
+            u: 0.0,
+            v: 0.0,
             is_front_face: ray.dir.dot(&outward_normal) < 0.0,
+            tangent: Vec3::zero(),
+            bitangent: Vec3::zero(),
+            object_id: None,
+        }
+    }
+
+    /// The normal on the side the ray arrived from, `normal` itself always points outward
+    pub fn facing_normal(&self) -> Vec3 {
+        if self.is_front_face {
+            self.normal
+        } else {
+            -self.normal
         }
     }
 }
@@ -39,6 +60,99 @@ pub trait Hittable: Send + Sync {
     fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord>;
 
     fn boundnig_box(&self) -> &AABB;
+
+    /// Whether anything is hit within `ray_t`. Meant for shadow and occlusion rays which
+    /// don't need to know what was hit, so implementations should avoid building a `HitRecord`.
+    fn occluded(&self, ray: &Ray, ray_t: Interval) -> bool {
+        self.hit(ray, ray_t).is_some()
+    }
+
+    /// Stable id assigned at scene build time, see `HittableList::assign_ids`
+    fn id(&self) -> Option<usize> {
+        None
+    }
+
+    /// Every surface crossing within `ray_t`, sorted by `t`. Used by CSG, which needs both the
+    /// entries and the exits of closed objects.
+    ///
+    /// The default calls `hit` again just past each crossing, objects whose `hit` only reports
+    /// the first crossing have to override it. Crossings closer than `1e-4` along `t` to the
+    /// previous one (e.g. two faces meeting at an edge) are merged into it.
+    fn hit_all(&self, ray: &Ray, ray_t: Interval) -> Vec<HitRecord> {
+        let mut hits = Vec::new();
+        let mut t_min = ray_t.min;
+        while let Some(hit_record) = self.hit(ray, Interval::new(t_min, ray_t.max)) {
+            t_min = hit_record.t + 1e-4;
+            hits.push(hit_record);
+        }
+        hits
+    }
+
+    /// Probability density, per unit solid angle, of `random` returning `direction` from `origin`
+    fn pdf_value(&self, _origin: &Point3, _direction: &Vec3) -> f64 {
+        0.0
+    }
+
+    /// Random direction from `origin` towards the object, used to aim scattered rays at lights
+    fn random(&self, _origin: &Point3) -> Vec3 {
+        Vec3::new(1.0, 0.0, 0.0)
+    }
+}
+
+/// Tags an object with an id which is reported through `Hittable::id` and on its hit records
+pub struct Identified {
+    id: usize,
+    object: Arc<dyn Hittable>,
+}
+
+impl Identified {
+    pub fn new(id: usize, object: Arc<dyn Hittable>) -> Self {
+        Self { id, object }
+    }
+}
+
+impl Identified {
+    fn tag(&self, mut hit_record: HitRecord) -> HitRecord {
+        // Keep the id of the innermost tagged object
+        hit_record.object_id.get_or_insert(self.id);
+        hit_record
+    }
+}
+
+impl Hittable for Identified {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        self.object
+            .hit(ray, ray_t)
+            .map(|hit_record| self.tag(hit_record))
+    }
+
+    fn boundnig_box(&self) -> &AABB {
+        self.object.boundnig_box()
+    }
+
+    fn occluded(&self, ray: &Ray, ray_t: Interval) -> bool {
+        self.object.occluded(ray, ray_t)
+    }
+
+    fn id(&self) -> Option<usize> {
+        Some(self.id)
+    }
+
+    fn hit_all(&self, ray: &Ray, ray_t: Interval) -> Vec<HitRecord> {
+        self.object
+            .hit_all(ray, ray_t)
+            .into_iter()
+            .map(|hit_record| self.tag(hit_record))
+            .collect()
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        self.object.pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        self.object.random(origin)
+    }
 }
 
 pub struct HittableList {
@@ -50,22 +164,84 @@ impl HittableList {
     pub fn new() -> HittableList {
         HittableList {
             objects: vec![],
-            bbox: AABB::from_points(Point3::zero(), Point3::zero()),
+            bbox: AABB::empty(),
         }
     }
 
     pub fn add(&mut self, object: impl Hittable + 'static) {
-        self.bbox = AABB::from_boxes(&self.bbox, object.boundnig_box());
-        self.objects.push(Arc::new(object));
+        self.add_shared(Arc::new(object));
+    }
+
+    /// Adds an object that is already shared, without wrapping it again. Adding the same `Arc`
+    /// several times (e.g. under different transforms) instances it without copying.
+    pub fn add_shared(&mut self, object: Arc<dyn Hittable>) {
+        // The first object's box is the list's box, there's nothing to merge it with yet
+        self.bbox = if self.objects.is_empty() {
+            object.boundnig_box().clone()
+        } else {
+            AABB::from_boxes(&self.bbox, object.boundnig_box())
+        };
+        self.objects.push(object);
+    }
+
+    pub fn len(&self) -> usize {
+        self.objects.len()
     }
 
-    pub fn get_objects(&mut self) -> &mut Vec<Arc<dyn Hittable>> {
-        &mut self.objects
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    /// Reserves room for at least `additional` more objects
+    pub fn reserve(&mut self, additional: usize) {
+        self.objects.reserve(additional);
+    }
+
+    /// Tags every object that doesn't have an id yet with sequential ids in list order,
+    /// starting above the largest id already in use so no two objects share one
+    pub fn assign_ids(&mut self) {
+        let mut next_id = self
+            .objects
+            .iter()
+            .filter_map(|object| object.id())
+            .max()
+            .map_or(0, |max_id| max_id + 1);
+        for object in &mut self.objects {
+            if object.id().is_none() {
+                *object = Arc::new(Identified::new(next_id, Arc::clone(object)));
+                next_id += 1;
+            }
+        }
+    }
+
+    pub fn find_by_id(&self, id: usize) -> Option<&Arc<dyn Hittable>> {
+        self.objects.iter().find(|object| object.id() == Some(id))
+    }
+
+    /// Read-only, objects go through `add` so the bounding box stays up to date
+    pub fn objects(&self) -> &[Arc<dyn Hittable>] {
+        &self.objects
+    }
+
+    /// Builds a BVH over the objects using every core, see `BVHNode::par_new`
+    pub fn par_build_bvh(&self) -> BVHNode {
+        BVHNode::par_new(self)
+    }
+}
+
+impl Default for HittableList {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl Hittable for HittableList {
     fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        // Rays that miss the box enclosing every object can't hit any of them
+        if !self.bbox.hit(ray, &ray_t) {
+            return None;
+        }
+
         let mut current_hit_record: Option<HitRecord> = None;
         for object in &self.objects {
             let current_max = ray_t
@@ -79,6 +255,108 @@ impl Hittable for HittableList {
     }
 
     fn boundnig_box(&self) -> &AABB {
-        todo!()
+        &self.bbox
+    }
+
+    fn occluded(&self, ray: &Ray, ray_t: Interval) -> bool {
+        self.bbox.hit(ray, &ray_t)
+            && self
+                .objects
+                .iter()
+                .any(|object| object.occluded(ray, ray_t.clone()))
+    }
+
+    /// Average of the objects' densities, `random` picks every object with the same probability
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        if self.objects.is_empty() {
+            return 0.0;
+        }
+        let sum: f64 = self
+            .objects
+            .iter()
+            .map(|object| object.pdf_value(origin, direction))
+            .sum();
+        sum / self.objects.len() as f64
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        if self.objects.is_empty() {
+            return Vec3::new(1.0, 0.0, 0.0);
+        }
+        let index = random_u64(0, self.objects.len() as u64 - 1) as usize;
+        self.objects[index].random(origin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::sphere::Sphere;
+    use crate::vec::Color3;
+
+    fn sphere(x: f64) -> Arc<dyn Hittable> {
+        let material = Arc::new(Lambertian::new(Color3::new(0.5, 0.5, 0.5)));
+        Arc::new(Sphere::new(Point3::new(x, 0.0, 0.0), 0.5, material))
+    }
+
+    #[test]
+    fn hit_all_reports_a_grazing_ray_once() {
+        // Lists go through the default `hit_all`
+        let mut list = HittableList::new();
+        list.add_shared(sphere(0.0));
+
+        // All but tangent to the sphere, the two crossings are a hair apart
+        let ray = Ray::new(
+            Point3::new(-2.0, 0.5 - 1e-12, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+        );
+        let hits = list.hit_all(&ray, Interval::new(0.001, f64::INFINITY));
+        assert_eq!(hits.len(), 1);
+
+        let ray = Ray::new(Point3::new(-2.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let hits = list.hit_all(&ray, Interval::new(0.001, f64::INFINITY));
+        let ts: Vec<f64> = hits.iter().map(|hit| hit.t).collect();
+        assert_eq!(ts, [1.5, 2.5]);
+    }
+
+    #[test]
+    fn assigned_ids_skip_the_ones_in_use() {
+        let mut list = HittableList::new();
+        list.add_shared(sphere(0.0));
+        list.add_shared(Arc::new(Identified::new(1, sphere(1.0))));
+        list.add_shared(sphere(2.0));
+        list.add_shared(Arc::new(Identified::new(0, sphere(3.0))));
+        list.assign_ids();
+
+        let ids: Vec<Option<usize>> = list.objects().iter().map(|object| object.id()).collect();
+        assert_eq!(ids, [Some(2), Some(1), Some(3), Some(0)]);
+
+        // Ids are stable once assigned
+        list.add_shared(sphere(4.0));
+        list.assign_ids();
+        assert_eq!(list.objects()[0].id(), Some(2));
+        assert_eq!(list.objects()[4].id(), Some(4));
+
+        let found = list.find_by_id(3).unwrap();
+        assert_eq!(found.boundnig_box().min().x, 1.5);
+        assert!(list.find_by_id(5).is_none());
+    }
+
+    #[test]
+    fn shared_objects_are_added_without_copying() {
+        let shared = sphere(2.0);
+        let mut list = HittableList::new();
+        list.add_shared(Arc::clone(&shared));
+        list.add_shared(Arc::clone(&shared));
+
+        assert_eq!(list.len(), 2);
+        assert!(Arc::ptr_eq(&list.objects()[0], &shared));
+        assert!(Arc::ptr_eq(&list.objects()[0], &list.objects()[1]));
+        // Two handles in the list plus ours
+        assert_eq!(Arc::strong_count(&shared), 3);
+
+        let bbox = list.boundnig_box();
+        assert_eq!((bbox.min().x, bbox.max().x), (1.5, 2.5));
     }
 }
@@ -0,0 +1,454 @@
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::png::ColorProfile;
+use crate::png::read_png;
+use crate::png::write_png;
+use crate::vec::Color3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Ppm, // Plain-text P3 pixmap
+    Png,
+}
+
+impl OutputFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Ppm => "ppm",
+            OutputFormat::Png => "png",
+        }
+    }
+}
+
+/// Where a rendered image ends up
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Output {
+    File(PathBuf),
+    Stdout, // For piping into other tools, status messages then go to stderr
+}
+
+impl From<&str> for Output {
+    fn from(path: &str) -> Self {
+        Output::File(PathBuf::from(path))
+    }
+}
+
+/// 8-bit RGB image in memory, rows go from top to bottom
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RgbImage {
+    width: usize,
+    height: usize,
+    data: Vec<u8>,                       // Interleaved R, G, B samples
+    comments: Vec<String>,               // Written as `#` lines in PPM headers, ignored by PNG
+    color_profile: Option<ColorProfile>, // Written as PNG chunks, ignored by PPM
+}
+
+impl RgbImage {
+    /// Black image
+    pub fn new(width: usize, height: usize) -> Self {
+        Self::from_raw(width, height, vec![0; width * height * 3])
+    }
+
+    /// `data` holds `width * height` interleaved R, G, B samples
+    pub fn from_raw(width: usize, height: usize, data: Vec<u8>) -> Self {
+        assert_eq!(data.len(), width * height * 3);
+        Self {
+            width,
+            height,
+            data,
+            comments: Vec::new(),
+            color_profile: None,
+        }
+    }
+
+    /// Adds a line of text written as a `# ` comment after the magic number of PPM files, which
+    /// PPM readers skip. Lines must not contain line breaks.
+    pub fn add_comment(&mut self, comment: impl Into<String>) {
+        let comment = comment.into();
+        assert!(!comment.contains(['\n', '\r']));
+        self.comments.push(comment);
+    }
+
+    pub fn comments(&self) -> &[String] {
+        &self.comments
+    }
+
+    /// Embeds `color_profile` in PNG outputs so color-managed viewers decode the samples
+    /// correctly, none by default
+    pub fn set_color_profile(&mut self, color_profile: Option<ColorProfile>) {
+        self.color_profile = color_profile;
+    }
+
+    pub fn color_profile(&self) -> Option<ColorProfile> {
+        self.color_profile
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get_pixel(&self, x: usize, y: usize) -> [u8; 3] {
+        let i = self.index(x, y);
+        [self.data[i], self.data[i + 1], self.data[i + 2]]
+    }
+
+    pub fn put_pixel(&mut self, x: usize, y: usize, pixel: [u8; 3]) {
+        let i = self.index(x, y);
+        self.data[i..i + 3].copy_from_slice(&pixel);
+    }
+
+    pub fn as_raw(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Writes the image to `path`, whatever its extension is
+    pub fn save(&self, path: impl AsRef<Path>, format: OutputFormat) -> io::Result<()> {
+        let file = File::create(path)?;
+        self.write_to(&mut BufWriter::new(file), format)
+    }
+
+    /// Writes the image to a file or stdout
+    pub fn save_to(&self, output: &Output, format: OutputFormat) -> io::Result<()> {
+        match output {
+            Output::File(path) => self.save(path, format),
+            Output::Stdout => self.write_to(&mut BufWriter::new(io::stdout().lock()), format),
+        }
+    }
+
+    /// Encodes the image into `writer`
+    pub fn write_to(&self, writer: &mut impl Write, format: OutputFormat) -> io::Result<()> {
+        match format {
+            OutputFormat::Ppm => {
+                let mut image_data = String::new();
+                image_data.push_str("P3\n");
+                for comment in &self.comments {
+                    image_data.push_str(&format!("# {comment}\n"));
+                }
+                image_data.push_str(&format!("{} {}\n255\n", self.width, self.height));
+                for pixel in self.data.chunks_exact(3) {
+                    image_data.push_str(&format!("{} {} {}\n", pixel[0], pixel[1], pixel[2]));
+                }
+                writer.write_all(image_data.as_bytes())?;
+            }
+            OutputFormat::Png => write_png(
+                writer,
+                self.width,
+                self.height,
+                &self.data,
+                self.color_profile,
+            )?,
+        }
+        writer.flush()
+    }
+
+    fn index(&self, x: usize, y: usize) -> usize {
+        assert!(x < self.width && y < self.height);
+        (y * self.width + x) * 3
+    }
+}
+
+/// Loads an image file as 8-bit RGB, returning `(width, height, pixels)`.
+///
+/// Supports PNG and binary (P6) or plain-text (P3) PPM, picked by the file extension.
+pub fn load_rgb8(path: &str) -> io::Result<(usize, usize, Vec<u8>)> {
+    let data = fs::read(path)?;
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("png") => read_png(&data),
+        Some("ppm") => read_ppm(&data),
+        _ => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("unsupported image format: {}", path),
+        )),
+    }
+}
+
+/// Loads a Radiance `.hdr` (RGBE) image as linear colors, returning `(width, height, pixels)`
+/// with rows from the top
+pub fn load_hdr(path: &str) -> io::Result<(usize, usize, Vec<Color3>)> {
+    read_hdr(&fs::read(path)?)
+}
+
+/// Decodes an RGBE image stored flat or with the run-length encoding of newer files
+///
+/// Every pixel is a shared exponent `e` and three mantissas, the color being
+/// `(r, g, b) * 2^(e - 136)`, or black when `e` is 0.
+fn read_hdr(data: &[u8]) -> io::Result<(usize, usize, Vec<Color3>)> {
+    let mut pos = 0;
+    let mut next_line = || -> io::Result<&[u8]> {
+        let start = pos;
+        let end = start
+            + data[start..]
+                .iter()
+                .position(|byte| *byte == b'\n')
+                .ok_or_else(|| invalid_data("truncated HDR header"))?;
+        pos = end + 1;
+        Ok(&data[start..end])
+    };
+
+    if !next_line()?.starts_with(b"#?") {
+        return Err(invalid_data("not a Radiance HDR file"));
+    }
+    // Header variables run until an empty line
+    loop {
+        let line = next_line()?;
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with(b"FORMAT=") && line != b"FORMAT=32-bit_rle_rgbe" {
+            return Err(invalid_data("only RGBE HDR files are supported"));
+        }
+    }
+
+    // Only the standard orientation, rows from the top and pixels from the left
+    let resolution = String::from_utf8_lossy(next_line()?).into_owned();
+    let (height, width) = match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+        ["-Y", height, "+X", width] => (height.parse().ok(), width.parse().ok()),
+        _ => (None, None),
+    };
+    let (Some(height), Some(width)) = (height, width) else {
+        return Err(invalid_data("unsupported HDR resolution line"));
+    };
+
+    let mut pixels = Vec::with_capacity(width * height);
+    let mut scanline = vec![[0u8; 4]; width];
+    for _ in 0..height {
+        pos = read_hdr_scanline(data, pos, &mut scanline)?;
+        pixels.extend(scanline.iter().map(|&[r, g, b, e]| {
+            if e == 0 {
+                Color3::zero()
+            } else {
+                let scale = 2.0_f64.powi(e as i32 - 136);
+                Color3::new(r as f64 * scale, g as f64 * scale, b as f64 * scale)
+            }
+        }));
+    }
+    Ok((width, height, pixels))
+}
+
+/// Reads one scanline of RGBE pixels starting at `pos`, returning the position after it
+fn read_hdr_scanline(data: &[u8], mut pos: usize, scanline: &mut [[u8; 4]]) -> io::Result<usize> {
+    let width = scanline.len();
+    let truncated = || invalid_data("truncated HDR pixel data");
+    let start = data.get(pos..pos + 4).ok_or_else(truncated)?;
+
+    // Newer files start run-length encoded scanlines with `2, 2` and the width
+    let is_rle = (8..0x8000).contains(&width)
+        && start[0] == 2
+        && start[1] == 2
+        && ((start[2] as usize) << 8 | start[3] as usize) == width;
+    if !is_rle {
+        for pixel in scanline.iter_mut() {
+            let bytes = data.get(pos..pos + 4).ok_or_else(truncated)?;
+            if bytes[..3] == [1, 1, 1] {
+                return Err(invalid_data(
+                    "old-style HDR run-length encoding is not supported",
+                ));
+            }
+            pixel.copy_from_slice(bytes);
+            pos += 4;
+        }
+        return Ok(pos);
+    }
+    pos += 4;
+
+    // Each channel is stored separately as runs (count above 128) or literal bytes
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let count = *data.get(pos).ok_or_else(truncated)? as usize;
+            pos += 1;
+            if count > 128 {
+                let count = count - 128;
+                let value = *data.get(pos).ok_or_else(truncated)?;
+                pos += 1;
+                if count > width - x {
+                    return Err(invalid_data("HDR run overflows the scanline"));
+                }
+                for pixel in &mut scanline[x..x + count] {
+                    pixel[channel] = value;
+                }
+                x += count;
+            } else {
+                if count == 0 || count > width - x {
+                    return Err(invalid_data("invalid HDR literal run"));
+                }
+                let values = data.get(pos..pos + count).ok_or_else(truncated)?;
+                for (pixel, value) in scanline[x..x + count].iter_mut().zip(values) {
+                    pixel[channel] = *value;
+                }
+                pos += count;
+                x += count;
+            }
+        }
+    }
+    Ok(pos)
+}
+
+/// Decodes a P3 or P6 PPM with a maximum value of up to 255
+fn read_ppm(data: &[u8]) -> io::Result<(usize, usize, Vec<u8>)> {
+    let mut header = PpmTokens { data, pos: 0 };
+    let magic = header.next()?;
+    if magic != b"P3" && magic != b"P6" {
+        return Err(invalid_data("not a P3/P6 PPM file"));
+    }
+    let width = header.next_number()?;
+    let height = header.next_number()?;
+    let max_value = header.next_number()?;
+    if max_value == 0 || max_value > 255 {
+        return Err(invalid_data(
+            "only PPMs with a maximum value up to 255 are supported",
+        ));
+    }
+
+    let sample_count = width * height * 3;
+    let mut samples = if magic == b"P6" {
+        data.get(header.pos..header.pos + sample_count)
+            .ok_or_else(|| invalid_data("not enough pixel data"))?
+            .to_vec()
+    } else {
+        (0..sample_count)
+            .map(|_| header.next_number().map(|sample| sample as u8))
+            .collect::<io::Result<Vec<u8>>>()?
+    };
+
+    if max_value != 255 {
+        for sample in samples.iter_mut() {
+            *sample = (*sample as usize * 255 / max_value) as u8;
+        }
+    }
+    Ok((width, height, samples))
+}
+
+/// Whitespace separated PPM header tokens, `#` starts a comment running to the end of the line
+struct PpmTokens<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> PpmTokens<'a> {
+    /// Returns the next token and skips the single whitespace byte following it, which is
+    /// what separates the header from binary pixel data
+    fn next(&mut self) -> io::Result<&'a [u8]> {
+        loop {
+            match self.data.get(self.pos) {
+                Some(b'#') => {
+                    while self.data.get(self.pos).is_some_and(|byte| *byte != b'\n') {
+                        self.pos += 1;
+                    }
+                }
+                Some(byte) if byte.is_ascii_whitespace() => self.pos += 1,
+                Some(_) => break,
+                None => return Err(invalid_data("truncated PPM")),
+            }
+        }
+
+        let start = self.pos;
+        while self
+            .data
+            .get(self.pos)
+            .is_some_and(|byte| !byte.is_ascii_whitespace())
+        {
+            self.pos += 1;
+        }
+        let token = &self.data[start..self.pos];
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn next_number(&mut self) -> io::Result<usize> {
+        let token = self.next()?;
+        std::str::from_utf8(token)
+            .ok()
+            .and_then(|token| token.parse().ok())
+            .ok_or_else(|| invalid_data("invalid number in PPM"))
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_and_binary_ppms_read_back() {
+        let plain = b"P3\n# made by hand\n2 1\n15\n15 0 0  0 15 3\n";
+        assert_eq!(
+            read_ppm(plain).unwrap(),
+            (2, 1, vec![255, 0, 0, 0, 255, 51])
+        );
+
+        let mut binary = b"P6 1 2 255\n".to_vec();
+        binary.extend_from_slice(&[1, 2, 3, 4, 5, 6]);
+        assert_eq!(read_ppm(&binary).unwrap(), (1, 2, vec![1, 2, 3, 4, 5, 6]));
+
+        // What `write_to` writes, comments included
+        let mut image = RgbImage::from_raw(2, 2, (0..12).map(|i| i * 20).collect());
+        image.add_comment("seed 7");
+        let mut ppm = Vec::new();
+        image.write_to(&mut ppm, OutputFormat::Ppm).unwrap();
+        assert_eq!(read_ppm(&ppm).unwrap(), (2, 2, image.as_raw().to_vec()));
+    }
+
+    #[test]
+    fn broken_ppms_are_rejected() {
+        assert!(read_ppm(b"P5\n1 1\n255\n\0").is_err());
+        assert!(read_ppm(b"P3\n1 1\n65535\n0 0 0\n").is_err());
+        assert!(read_ppm(b"P3\n2 1\n255\n0 0 0\n").is_err());
+        assert!(read_ppm(b"P6\n2 1\n255\n\0\0\0").is_err());
+    }
+
+    fn hdr(width: usize, height: usize, pixel_data: &[u8]) -> Vec<u8> {
+        let header = format!("#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {height} +X {width}\n");
+        [header.as_bytes(), pixel_data].concat()
+    }
+
+    #[test]
+    fn flat_hdr_pixels_are_decoded() {
+        // Exponent 129 scales the mantissas by `2^-7`, an exponent of 0 is black
+        let data = hdr(2, 1, &[128, 64, 0, 129, 200, 200, 200, 0]);
+        let (width, height, pixels) = read_hdr(&data).unwrap();
+        assert_eq!((width, height), (2, 1));
+        assert_eq!((pixels[0].x, pixels[0].y, pixels[0].z), (1.0, 0.5, 0.0));
+        assert_eq!((pixels[1].x, pixels[1].y, pixels[1].z), (0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn run_length_encoded_hdr_scanlines_are_decoded() {
+        let mut scanline = vec![2, 2, 0, 8];
+        scanline.extend_from_slice(&[128 + 8, 128]); // Red, one run
+        scanline.extend_from_slice(&[8, 0, 16, 32, 48, 64, 80, 96, 112]); // Green, literals
+        scanline.extend_from_slice(&[128 + 3, 0, 5, 1, 2, 3, 4, 5]); // Blue, a run and literals
+        scanline.extend_from_slice(&[128 + 8, 129]); // Exponent
+        let (width, height, pixels) = read_hdr(&hdr(8, 1, &scanline)).unwrap();
+
+        assert_eq!((width, height), (8, 1));
+        for (x, pixel) in pixels.iter().enumerate() {
+            let blue = if x < 3 { 0.0 } else { (x - 2) as f64 / 128.0 };
+            assert_eq!((pixel.x, pixel.y, pixel.z), (1.0, x as f64 / 8.0, blue));
+        }
+
+        // A run longer than the scanline is an error, not a panic
+        let mut overflowing = scanline.clone();
+        overflowing[4] = 128 + 9;
+        assert!(read_hdr(&hdr(8, 1, &overflowing)).is_err());
+        assert!(read_hdr(&hdr(8, 1, &scanline[..10])).is_err());
+        assert!(read_hdr(b"P6\n").is_err());
+    }
+}
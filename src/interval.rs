@@ -17,22 +17,42 @@ impl Interval {
         Interval { min, max }
     }
 
+    /// An interval containing nothing; unioning it with another interval yields that interval
     pub fn empty() -> Interval {
-        Interval { min: 0.0, max: 0.0 }
+        Interval {
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min > self.max
+    }
+
+    pub fn is_finite(&self) -> bool {
+        self.min.is_finite() && self.max.is_finite()
     }
 
     pub fn expand(&self, delta: f64) -> Interval {
         let padding = delta / 2.0;
         Interval {
-            min: self.min + padding,
-            max: self.max - padding,
+            min: self.min - padding,
+            max: self.max + padding,
         }
     }
 
+    /// Open containment, for ray parameter ranges where a hit exactly at `min` would be the
+    /// surface the ray starts from
     pub fn surrounds(&self, x: f64) -> bool {
         self.min < x && x < self.max
     }
 
+    /// Closed containment, for bands and parametric bounds on a surface (quad edges, capsule
+    /// body) where a point exactly on the boundary still belongs to the surface
+    pub fn contains_closed(&self, x: f64) -> bool {
+        self.min <= x && x <= self.max
+    }
+
     pub fn clamp(&self, x: f64) -> f64 {
         x.max(self.min).min(self.max)
     }
@@ -41,3 +61,47 @@ impl Interval {
         self.max - self.min
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_and_finite_predicates() {
+        assert!(Interval::empty().is_empty());
+        assert!(!Interval::empty().is_finite());
+
+        let unit = Interval::new(0.0, 1.0);
+        assert!(!unit.is_empty());
+        assert!(unit.is_finite());
+
+        // A single point is not empty
+        assert!(!Interval::new(2.0, 2.0).is_empty());
+
+        let unbounded = Interval::new(0.0, f64::INFINITY);
+        assert!(!unbounded.is_empty());
+        assert!(!unbounded.is_finite());
+        assert!(!Interval::new(f64::NEG_INFINITY, 0.0).is_finite());
+    }
+
+    #[test]
+    fn expand_widens_both_ends() {
+        let expanded = Interval::new(1.0, 2.0).expand(0.2);
+        assert!((expanded.min - 0.9).abs() < 1e-12);
+        assert!((expanded.max - 2.1).abs() < 1e-12);
+    }
+
+    #[test]
+    fn endpoints_are_only_in_the_closed_interval() {
+        let unit = Interval::new(0.0, 1.0);
+        assert!(!unit.surrounds(0.0) && !unit.surrounds(1.0));
+        assert!(unit.contains_closed(0.0) && unit.contains_closed(1.0));
+        assert!(unit.surrounds(0.5) && unit.contains_closed(0.5));
+        assert!(!unit.contains_closed(-1e-12) && !unit.contains_closed(1.0 + 1e-12));
+
+        assert_eq!(unit.clamp(-2.0), 0.0);
+        assert_eq!(unit.clamp(1.0), 1.0);
+        assert_eq!(unit.clamp(3.0), 1.0);
+        assert_eq!(unit.clamp(0.25), 0.25);
+    }
+}
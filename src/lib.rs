@@ -0,0 +1,32 @@
+pub mod aabb;
+pub mod bvh;
+pub mod camera;
+pub mod capsule;
+pub mod constant_medium;
+pub mod csg;
+pub mod cylinder;
+pub mod denoise;
+pub mod disk;
+pub mod group;
+pub mod hittable;
+pub mod image;
+pub mod interval;
+pub mod light;
+pub mod material;
+pub mod obj;
+pub mod onb;
+pub mod pdf;
+pub mod perlin;
+pub mod plane;
+pub mod png;
+pub mod quad;
+pub mod ray;
+pub mod scene;
+pub mod scenes;
+pub mod sphere;
+pub mod sun;
+pub mod texture;
+pub mod transform;
+pub mod triangle;
+pub mod utils;
+pub mod vec;
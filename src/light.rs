@@ -0,0 +1,212 @@
+use crate::utils::random_percentage;
+use crate::vec::Color3;
+use crate::vec::Point3;
+use crate::vec::Vec3;
+
+/// Light sampled directly with shadow rays at every diffuse hit, see `Camera::add_light`
+///
+/// These lights have no geometry: camera and bounce rays never hit them, so adding an
+/// emissive object at the same place as well would count its light twice.
+#[derive(Debug, Clone, Copy)]
+pub enum Light {
+    /// Emits `intensity` equally in every direction from `position`
+    Point { position: Point3, intensity: Color3 },
+    /// Point light restricted to a cone around `direction`, fading out between the inner
+    /// and outer half-angles
+    Spot {
+        position: Point3,
+        direction: Vec3, // Unit vector along the cone axis
+        cos_inner: f64,
+        cos_outer: f64,
+        intensity: Color3,
+    },
+    /// Parallelogram `corner + a*u + b*v` with `a`, `b` in `[0, 1]`, emitting `radiance` from
+    /// the side `u × v` points to
+    Area {
+        corner: Point3,
+        u: Vec3,
+        v: Vec3,
+        radiance: Color3,
+    },
+}
+
+/// Light arriving at a point from one sample of a light
+#[derive(Debug, Clone, Copy)]
+pub struct LightSample {
+    pub to_light: Vec3, // From the shaded point to the sampled point on the light, not normalized
+    pub irradiance: Color3, // Incoming light on a surface facing the light, before `N·L`
+}
+
+impl Light {
+    pub fn point(position: Point3, intensity: Color3) -> Self {
+        Light::Point {
+            position,
+            intensity,
+        }
+    }
+
+    /// `inner_angle` and `outer_angle` are the cone half-angles in degrees
+    pub fn spot(
+        position: Point3,
+        direction: Vec3,
+        inner_angle: f64,
+        outer_angle: f64,
+        intensity: Color3,
+    ) -> Self {
+        assert!(0.0 <= inner_angle && inner_angle <= outer_angle && outer_angle < 180.0);
+        Light::Spot {
+            position,
+            direction: direction.unit(),
+            cos_inner: inner_angle.to_radians().cos(),
+            cos_outer: outer_angle.to_radians().cos(),
+            intensity,
+        }
+    }
+
+    pub fn area(corner: Point3, u: Vec3, v: Vec3, radiance: Color3) -> Self {
+        Light::Area {
+            corner,
+            u,
+            v,
+            radiance,
+        }
+    }
+
+    /// Samples the light as seen from `p`
+    ///
+    /// ## Math
+    /// Point and spot lights fall off with the inverse square of the distance `r`:
+    /// `E = I / r^2`, scaled by the cone falloff for spot lights.
+    ///
+    /// Area lights pick a uniformly random point on the parallelogram, so the sample is divided
+    /// by the pdf `1 / A` and the solid angle it covers is `A * cos(θ_light) / r^2`:
+    /// `E = L * A * cos(θ_light) / r^2` where `θ_light` is the angle between the light's normal
+    /// and the direction back to `p`.
+    pub fn sample(&self, p: &Point3) -> LightSample {
+        match *self {
+            Light::Point {
+                position,
+                intensity,
+            } => {
+                let to_light = position - *p;
+                LightSample {
+                    to_light,
+                    irradiance: intensity / to_light.squared_length(),
+                }
+            }
+            Light::Spot {
+                position,
+                direction,
+                cos_inner,
+                cos_outer,
+                intensity,
+            } => {
+                let to_light = position - *p;
+                let cos_theta = (-to_light.unit()).dot(&direction);
+                // Smoothstep between the outer and inner cone
+                let falloff = if cos_inner > cos_outer {
+                    let x = ((cos_theta - cos_outer) / (cos_inner - cos_outer)).clamp(0.0, 1.0);
+                    x * x * (3.0 - 2.0 * x)
+                } else if cos_theta >= cos_outer {
+                    1.0
+                } else {
+                    0.0
+                };
+                LightSample {
+                    to_light,
+                    irradiance: falloff * intensity / to_light.squared_length(),
+                }
+            }
+            Light::Area {
+                corner,
+                u,
+                v,
+                radiance,
+            } => {
+                let point = corner + random_percentage() * u + random_percentage() * v;
+                let to_light = point - *p;
+                let n = u.cross(v);
+                let area = n.length();
+                let distance_squared = to_light.squared_length();
+                let cos_light = -n.dot(&to_light) / (area * distance_squared.sqrt());
+                let irradiance = if cos_light > 0.0 {
+                    radiance * area * cos_light / distance_squared
+                } else {
+                    Color3::zero()
+                };
+                LightSample {
+                    to_light,
+                    irradiance,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::seed_thread_rng;
+
+    #[test]
+    fn point_lights_fall_off_with_the_squared_distance() {
+        let light = Light::point(Point3::new(0.0, 4.0, 0.0), Color3::new(8.0, 8.0, 8.0));
+        let near = light.sample(&Point3::new(0.0, 2.0, 0.0));
+        let far = light.sample(&Point3::zero());
+        assert_eq!(near.irradiance.x, 2.0);
+        assert_eq!(far.irradiance.x, 0.5);
+        assert_eq!(
+            (far.to_light.x, far.to_light.y, far.to_light.z),
+            (0.0, 4.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn spot_lights_fade_out_of_their_cone() {
+        let light = Light::spot(
+            Point3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, -1.0, 0.0),
+            20.0,
+            40.0,
+            Color3::new(1.0, 1.0, 1.0),
+        );
+        let at_angle = |degrees: f64| {
+            let (sin, cos) = degrees.to_radians().sin_cos();
+            let p = Point3::new(sin, 1.0 - cos, 0.0); // Unit distance from the light
+            light.sample(&p).irradiance.x
+        };
+        assert_eq!(at_angle(0.0), 1.0);
+        assert_eq!(at_angle(15.0), 1.0);
+        let halfway = at_angle(30.0);
+        assert!(0.0 < halfway && halfway < 1.0);
+        assert_eq!(at_angle(45.0), 0.0);
+    }
+
+    #[test]
+    fn area_lights_dim_with_the_angle_they_are_seen_at() {
+        // Small light facing down at the origin, 10 units above
+        let light = Light::area(
+            Point3::new(-0.05, 10.0, -0.05),
+            Vec3::new(0.1, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 0.1),
+            Color3::new(100.0, 100.0, 100.0),
+        );
+        seed_thread_rng(23);
+        let mean_irradiance = |p: Point3| {
+            (0..1000)
+                .map(|_| light.sample(&p).irradiance.x)
+                .sum::<f64>()
+                / 1000.0
+        };
+
+        // `E ≈ L * A * cos(θ) / r^2`
+        let below = mean_irradiance(Point3::zero());
+        assert!((below - 100.0 * 0.01 / 100.0).abs() < 1e-4);
+        // Seen 60° off the light's normal from the same distance
+        let (sin, cos) = 60.0_f64.to_radians().sin_cos();
+        let aside = mean_irradiance(Point3::new(10.0 * sin, 10.0 - 10.0 * cos, 0.0));
+        assert!((aside / below - 0.5).abs() < 0.01);
+        // Nothing is emitted from the back
+        assert_eq!(mean_irradiance(Point3::new(0.0, 20.0, 0.0)), 0.0);
+    }
+}
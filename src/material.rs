@@ -1,14 +1,26 @@
 use crate::hittable::HitRecord;
+use crate::onb::Onb;
+use crate::pdf::CosinePdf;
+use crate::pdf::Pdf;
 use crate::ray::Ray;
+use crate::texture::SolidColor;
+use crate::texture::Texture;
 use crate::utils::random_percentage;
 use crate::vec::Color3;
+use crate::vec::Point3;
 use crate::vec::Vec3;
+use std::f64::consts::PI;
 use std::fmt::Debug;
+use std::sync::Arc;
 
 #[derive(Debug)]
 pub struct ScatterRecord {
     pub scattered: Ray,
     pub attenuation: Color3,
+    /// Distribution `scattered` was drawn from. When set, the renderer may draw its own direction
+    /// from it (mixed with directions towards the lights) and weight the result with
+    /// `Material::scattering_pdf`
+    pub pdf: Option<Box<dyn Pdf>>,
 }
 
 impl ScatterRecord {
@@ -16,6 +28,15 @@ impl ScatterRecord {
         Self {
             scattered,
             attenuation,
+            pdf: None,
+        }
+    }
+
+    fn with_pdf(scattered: Ray, attenuation: Color3, pdf: Box<dyn Pdf>) -> Self {
+        Self {
+            scattered,
+            attenuation,
+            pdf: Some(pdf),
         }
     }
 }
@@ -24,16 +45,59 @@ pub trait Material: Debug + Send + Sync {
     fn scatter(&self, _ray_in: &Ray, _hit_record: &HitRecord) -> Option<ScatterRecord> {
         None
     }
+
+    /// Light given off by the surface itself at the surface coordinates `u`, `v` of `p`
+    fn emitted(&self, _u: f64, _v: f64, _p: &Point3) -> Color3 {
+        Color3::zero()
+    }
+
+    /// Albedo of the surface's diffuse lobe at the hit point, `None` for surfaces without one.
+    /// Lights added with `Camera::add_light` are only sampled directly on diffuse surfaces.
+    fn diffuse_albedo(&self, _hit_record: &HitRecord) -> Option<Color3> {
+        None
+    }
+
+    /// Density of the surface scattering light from `ray_in` towards `scattered`, per unit solid
+    /// angle. Only used for scatter records which carry a `pdf`.
+    fn scattering_pdf(&self, _ray_in: &Ray, _hit_record: &HitRecord, _scattered: &Ray) -> f64 {
+        0.0
+    }
+
+    /// Base color of the surface at the hit point, written to the albedo AOV (see
+    /// `Camera::set_aov_output`). Mid-gray for materials without a meaningful one.
+    fn albedo(&self, _hit_record: &HitRecord) -> Color3 {
+        Color3::new(0.5, 0.5, 0.5)
+    }
+
+    /// Outward normal the surface is shaded with, the geometric one unless the material
+    /// perturbs it (e.g. `NormalMapped`)
+    fn shading_normal(&self, hit_record: &HitRecord) -> Vec3 {
+        hit_record.normal
+    }
 }
 
 #[derive(Debug)]
 pub struct Lambertian {
-    albedo: Color3,
+    texture: Arc<dyn Texture>,
+    cosine_sampling: bool, // Sample `Vec3::random_cosine_direction` instead of `N + random_unit`
 }
 
 impl Lambertian {
     pub fn new(albedo: Color3) -> Self {
-        Self { albedo }
+        Self::from_texture(Arc::new(SolidColor::new(albedo)))
+    }
+
+    pub fn from_texture(texture: Arc<dyn Texture>) -> Self {
+        Self {
+            texture,
+            cosine_sampling: false,
+        }
+    }
+
+    /// Scatter with `Vec3::random_cosine_direction` rotated onto the normal, which follows
+    /// the cosine distribution exactly instead of through rejection sampling a unit vector
+    pub fn set_cosine_sampling(&mut self, cosine_sampling: bool) {
+        self.cosine_sampling = cosine_sampling;
     }
 }
 
@@ -60,18 +124,229 @@ impl Material for Lambertian {
     /// as the direction to avoid degenerate vectors.
     ///
     /// ### Outcome
-    /// - `attenuation` = surface color (albedo)
+    /// - `attenuation` = surface color (albedo), sampled from the texture at the hit point
     /// - `scattered` = ray starting at `P` with direction `scatter_direction`
+    ///
+    /// `N + random_unit_vector()` follows the cosine distribution `cos θ / π`, which is the
+    /// `pdf` handed to the renderer. With cosine sampling enabled the direction is instead
+    /// drawn from that distribution directly in the normal's `Onb`.
+    ///
+    /// `N` is the normal on the side the ray arrived from, so the inside of a sphere is lit
+    /// like its outside.
     fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
-        let mut scatter_direction = hit_record.normal + Vec3::random_unit();
+        let normal = hit_record.facing_normal();
+        let mut scatter_direction = if self.cosine_sampling {
+            Onb::new(&normal).transform(&Vec3::random_cosine_direction())
+        } else {
+            normal + Vec3::random_unit()
+        };
 
         // Catch degenerate scatter direction
         if scatter_direction.near_zero() {
-            scatter_direction = hit_record.normal;
+            scatter_direction = normal;
         }
 
         let scattered = Ray::new_time(hit_record.p, scatter_direction, ray_in.tm);
-        Some(ScatterRecord::new(scattered, self.albedo))
+        let attenuation = self
+            .texture
+            .value(hit_record.u, hit_record.v, &hit_record.p);
+        Some(ScatterRecord::with_pdf(
+            scattered,
+            attenuation,
+            Box::new(CosinePdf::new(&normal)),
+        ))
+    }
+
+    /// `cos θ / π` between the facing normal and the scattered direction
+    fn scattering_pdf(&self, _ray_in: &Ray, hit_record: &HitRecord, scattered: &Ray) -> f64 {
+        let cos_theta = hit_record.facing_normal().dot(&scattered.dir.unit());
+        (cos_theta / PI).max(0.0)
+    }
+
+    fn diffuse_albedo(&self, hit_record: &HitRecord) -> Option<Color3> {
+        Some(
+            self.texture
+                .value(hit_record.u, hit_record.v, &hit_record.p),
+        )
+    }
+
+    fn albedo(&self, hit_record: &HitRecord) -> Color3 {
+        self.texture
+            .value(hit_record.u, hit_record.v, &hit_record.p)
+    }
+}
+
+/// Emits light uniformly and doesn't scatter any incoming light
+#[derive(Debug)]
+pub struct DiffuseLight {
+    emit: Color3,
+}
+
+impl DiffuseLight {
+    pub fn new(emit: Color3) -> Self {
+        Self { emit }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn emitted(&self, _u: f64, _v: f64, _p: &Point3) -> Color3 {
+        self.emit
+    }
+}
+
+/// Diffuse surface that also glows, e.g. a screen showing an image or a lamp shade
+#[derive(Debug)]
+pub struct EmissiveLambertian {
+    base: Lambertian,
+    emission: Arc<dyn Texture>,
+}
+
+impl EmissiveLambertian {
+    pub fn new(albedo: Color3, emit: Color3) -> Self {
+        Self::from_textures(
+            Arc::new(SolidColor::new(albedo)),
+            Arc::new(SolidColor::new(emit)),
+        )
+    }
+
+    /// `albedo` colors the diffuse bounce and `emission` the light given off
+    pub fn from_textures(albedo: Arc<dyn Texture>, emission: Arc<dyn Texture>) -> Self {
+        Self {
+            base: Lambertian::from_texture(albedo),
+            emission,
+        }
+    }
+}
+
+impl Material for EmissiveLambertian {
+    /// Scatters exactly like `Lambertian`
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+        self.base.scatter(ray_in, hit_record)
+    }
+
+    fn emitted(&self, u: f64, v: f64, p: &Point3) -> Color3 {
+        self.emission.value(u, v, p)
+    }
+
+    fn diffuse_albedo(&self, hit_record: &HitRecord) -> Option<Color3> {
+        self.base.diffuse_albedo(hit_record)
+    }
+
+    fn scattering_pdf(&self, ray_in: &Ray, hit_record: &HitRecord, scattered: &Ray) -> f64 {
+        self.base.scattering_pdf(ray_in, hit_record, scattered)
+    }
+
+    fn albedo(&self, hit_record: &HitRecord) -> Color3 {
+        self.base.albedo(hit_record)
+    }
+}
+
+/// Shades `material` with its normals perturbed by a normal map, adding surface detail without
+/// extra geometry
+#[derive(Debug)]
+pub struct NormalMapped {
+    material: Arc<dyn Material>,
+    normal_map: Arc<dyn Texture>,
+}
+
+impl NormalMapped {
+    /// `normal_map` holds tangent space normals encoded as colors, `(0.5, 0.5, 1)` leaving the
+    /// surface untouched
+    pub fn new(material: Arc<dyn Material>, normal_map: Arc<dyn Texture>) -> Self {
+        Self {
+            material,
+            normal_map,
+        }
+    }
+
+    /// ## Math
+    /// The map's color `c` decodes to the tangent space normal `n_t = 2c - 1`, where `x`
+    /// points along increasing `u`, `y` along increasing `v` and `z` along the outward normal
+    /// `N`. The hit's `tangent` and `bitangent` are made perpendicular to `N` and to each other
+    /// (Gram-Schmidt) to give the `T`, `B`, `N` frame, and `n_t` goes to world space as
+    /// `n_t.x * T + n_t.y * B + n_t.z * N`.
+    ///
+    /// Objects which don't provide surface directions fall back to the `Onb` of `N`, whose
+    /// tangent is arbitrary.
+    fn perturbed(&self, hit_record: &HitRecord) -> HitRecord {
+        let color = self
+            .normal_map
+            .value(hit_record.u, hit_record.v, &hit_record.p);
+        let tangent_normal = 2.0 * color - Vec3::new(1.0, 1.0, 1.0);
+
+        let normal = hit_record.normal;
+        let tangent = hit_record.tangent - normal.dot(&hit_record.tangent) * normal;
+        let bitangent = hit_record.bitangent - normal.dot(&hit_record.bitangent) * normal;
+        let mut perturbed = hit_record.clone();
+        perturbed.normal = if tangent.near_zero() || bitangent.near_zero() {
+            Onb::new(&normal).transform(&tangent_normal)
+        } else {
+            let tangent = tangent.unit();
+            let bitangent = (bitangent - tangent.dot(&bitangent) * tangent).unit();
+            tangent_normal.x * tangent + tangent_normal.y * bitangent + tangent_normal.z * normal
+        }
+        .unit();
+        perturbed
+    }
+}
+
+impl Material for NormalMapped {
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+        self.material.scatter(ray_in, &self.perturbed(hit_record))
+    }
+
+    fn emitted(&self, u: f64, v: f64, p: &Point3) -> Color3 {
+        self.material.emitted(u, v, p)
+    }
+
+    fn diffuse_albedo(&self, hit_record: &HitRecord) -> Option<Color3> {
+        self.material.diffuse_albedo(hit_record)
+    }
+
+    fn scattering_pdf(&self, ray_in: &Ray, hit_record: &HitRecord, scattered: &Ray) -> f64 {
+        self.material
+            .scattering_pdf(ray_in, &self.perturbed(hit_record), scattered)
+    }
+
+    fn albedo(&self, hit_record: &HitRecord) -> Color3 {
+        self.material.albedo(hit_record)
+    }
+
+    fn shading_normal(&self, hit_record: &HitRecord) -> Vec3 {
+        self.material.shading_normal(&self.perturbed(hit_record))
+    }
+}
+
+/// Phase function of participating media, scatters equally in every direction
+#[derive(Debug)]
+pub struct Isotropic {
+    texture: Arc<dyn Texture>,
+}
+
+impl Isotropic {
+    pub fn new(albedo: Color3) -> Self {
+        Self::from_texture(Arc::new(SolidColor::new(albedo)))
+    }
+
+    pub fn from_texture(texture: Arc<dyn Texture>) -> Self {
+        Self { texture }
+    }
+}
+
+impl Material for Isotropic {
+    /// Scatters towards a uniformly random direction on the unit sphere, the surface normal
+    /// is meaningless inside a volume
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+        let scattered = Ray::new_time(hit_record.p, Vec3::random_unit(), ray_in.tm);
+        let attenuation = self
+            .texture
+            .value(hit_record.u, hit_record.v, &hit_record.p);
+        Some(ScatterRecord::new(scattered, attenuation))
+    }
+
+    fn albedo(&self, hit_record: &HitRecord) -> Color3 {
+        self.texture
+            .value(hit_record.u, hit_record.v, &hit_record.p)
     }
 }
 
@@ -82,9 +357,28 @@ pub struct Metal {
 }
 
 impl Metal {
+    /// `fuzz` is clamped to 1, larger values would send most rays below the surface
     pub fn new(albedo: Color3, fuzz: f64) -> Self {
         assert!(fuzz >= 0.0);
-        Self { albedo, fuzz }
+        Self {
+            albedo,
+            fuzz: fuzz.min(1.0),
+        }
+    }
+
+    /// Polished gold
+    pub fn gold() -> Self {
+        Self::new(Color3::new(1.0, 0.766, 0.336), 0.0)
+    }
+
+    /// Polished copper
+    pub fn copper() -> Self {
+        Self::new(Color3::new(0.955, 0.638, 0.538), 0.0)
+    }
+
+    /// Polished aluminum
+    pub fn aluminum() -> Self {
+        Self::new(Color3::new(0.913, 0.922, 0.924), 0.0)
     }
 }
 
@@ -112,35 +406,137 @@ impl Material for Metal {
     /// ### Outcome
     /// - `attenuation` = surface color (albedo)
     /// - `scattered` = ray starting at hit point, moving in `r`
-    /// - The material does not absorb light; it reflects it directionally.
+    /// - Fuzzed rays ending up below the surface (`r ⋅ N <= 0` with `N` facing the incoming
+    ///   ray) are absorbed, otherwise the material reflects all light directionally.
     fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
         let mut reflected = Vec3::reflect(&ray_in.dir, &hit_record.normal).unit();
 
         if self.fuzz > 0.0 {
-            reflected = reflected + self.fuzz * Vec3::random_unit();
+            reflected += self.fuzz * Vec3::random_unit();
+        }
+
+        if reflected.dot(&hit_record.facing_normal()) <= 0.0 {
+            return None;
         }
 
         let scattered = Ray::new_time(hit_record.p, reflected, ray_in.tm);
         Some(ScatterRecord::new(scattered, self.albedo))
     }
+
+    fn albedo(&self, _hit_record: &HitRecord) -> Color3 {
+        self.albedo
+    }
+}
+
+/// Metal whose reflections are smeared more along one direction on the surface than the
+/// other, like brushed steel
+#[derive(Debug)]
+pub struct BrushedMetal {
+    albedo: Color3,
+    fuzz_u: f64,   // Fuzz along `tangent`
+    fuzz_v: f64,   // Fuzz across `tangent`
+    tangent: Vec3, // World space direction of the brush strokes, projected onto the surface
+}
+
+impl BrushedMetal {
+    /// Both fuzz values are clamped to 1 like `Metal`'s
+    pub fn new(albedo: Color3, fuzz_u: f64, fuzz_v: f64, tangent: Vec3) -> Self {
+        assert!(fuzz_u >= 0.0 && fuzz_v >= 0.0);
+        assert!(!tangent.near_zero());
+        Self {
+            albedo,
+            fuzz_u: fuzz_u.min(1.0),
+            fuzz_v: fuzz_v.min(1.0),
+            tangent,
+        }
+    }
+}
+
+impl Material for BrushedMetal {
+    /// ## Math
+    /// ### Variables
+    /// - `N` → outward normal, `T` → brush direction
+    /// - `(x, y, z)` → random unit vector
+    ///
+    /// ### Calculation
+    /// The tangent frame is `t = unit(T - (T⋅N)N)` and `b = N × t`. Where `T` is parallel to
+    /// the normal any frame around `N` is used instead (see `Onb`).
+    ///
+    /// The mirror direction `r` is perturbed by `fuzz_u * x * t + fuzz_v * y * b`.
+    ///
+    /// ### Outcome
+    /// - `attenuation` = albedo
+    /// - Rays perturbed below the surface (against the facing normal) are absorbed
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+        let normal = hit_record.normal;
+        let along = self.tangent - self.tangent.dot(&normal) * normal;
+        let (t, b) = if along.near_zero() {
+            let uvw = Onb::new(&normal);
+            (uvw.u(), uvw.v())
+        } else {
+            let t = along.unit();
+            (t, normal.cross(t))
+        };
+
+        let jitter = Vec3::random_unit();
+        let reflected = Vec3::reflect(&ray_in.dir, &normal).unit()
+            + self.fuzz_u * jitter.x * t
+            + self.fuzz_v * jitter.y * b;
+        if reflected.dot(&hit_record.facing_normal()) <= 0.0 {
+            return None;
+        }
+
+        let scattered = Ray::new_time(hit_record.p, reflected, ray_in.tm);
+        Some(ScatterRecord::new(scattered, self.albedo))
+    }
+
+    fn albedo(&self, _hit_record: &HitRecord) -> Color3 {
+        self.albedo
+    }
 }
 
 #[derive(Debug)]
 pub struct Dielectric {
     refraction_index: f64,
+    tint: Color3, // Attenuation of refracted rays, white for clear materials
 }
 
 impl Dielectric {
     pub fn new(refraction_index: f64) -> Self {
-        Self { refraction_index }
+        Self::new_colored(refraction_index, Color3::new(1.0, 1.0, 1.0))
+    }
+
+    /// Tinted glass, e.g. gems or wine. Every refraction is attenuated by `tint` while
+    /// reflections stay white, so a ray going through a solid object is tinted twice: once on
+    /// the way in and once on the way out.
+    pub fn new_colored(refraction_index: f64, tint: Color3) -> Self {
+        Self {
+            refraction_index,
+            tint,
+        }
+    }
+
+    /// Common window glass (IOR 1.5)
+    pub fn glass() -> Self {
+        Self::new(1.5)
     }
 
-    /// Schlick's approximation for reflectance
-    fn reflectance(&self, cosine: f64) -> f64 {
-        let r0 = (1.0 - self.refraction_index) / (1.0 + self.refraction_index);
-        let r0 = r0 * r0;
-        r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
+    /// Water at room temperature (IOR 1.333)
+    pub fn water() -> Self {
+        Self::new(1.333)
     }
+
+    /// Diamond (IOR 2.417)
+    pub fn diamond() -> Self {
+        Self::new(2.417)
+    }
+}
+
+/// Schlick's approximation for reflectance
+fn reflectance(cosine: f64, refraction_index: f64) -> f64 {
+    let r0 = (1.0 - refraction_index) / (1.0 + refraction_index);
+    let r0 = r0 * r0;
+    r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
 }
 
 impl Material for Dielectric {
@@ -172,7 +568,8 @@ impl Material for Dielectric {
     /// - Otherwise → refract using Snell’s law
     ///
     /// In either case:
-    /// - `attenuation` = white (no absorption)
+    /// - `attenuation` = white for reflections, the tint for refractions (white unless made
+    ///   with `new_colored`)
     /// - `scattered` = new ray with reflected or refracted direction
     fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
         let refraction_index = if hit_record.is_front_face {
@@ -181,20 +578,294 @@ impl Material for Dielectric {
             self.refraction_index
         };
 
+        // Rays leaving the material hit the back face, Snell's law wants the normal against them
+        let normal = hit_record.facing_normal();
         let unit_direction = ray_in.dir.unit();
-        let cos_theta = unit_direction.negate().dot(&hit_record.normal).min(1.0);
+        let cos_theta = (-unit_direction).dot(&normal).min(1.0);
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
 
-        let direction;
         let cannot_refract = refraction_index * sin_theta > 1.0;
-        if cannot_refract || self.reflectance(cos_theta) > random_percentage() {
+        let (direction, attenuation) = if cannot_refract
+            || reflectance(cos_theta, self.refraction_index) > random_percentage()
+        {
             // Cannot refract
-            direction = Vec3::reflect(&unit_direction, &hit_record.normal);
+            (
+                Vec3::reflect(&unit_direction, &normal),
+                Color3::new(1.0, 1.0, 1.0),
+            )
         } else {
-            direction = Vec3::refract(&unit_direction, &hit_record.normal, refraction_index);
-        }
+            (
+                Vec3::refract(&unit_direction, &normal, refraction_index),
+                self.tint,
+            )
+        };
 
         let scattered = Ray::new_time(hit_record.p, direction, ray_in.tm);
-        Some(ScatterRecord::new(scattered, Color3::new(1.0, 1.0, 1.0)))
+        Some(ScatterRecord::new(scattered, attenuation))
+    }
+
+    fn albedo(&self, _hit_record: &HitRecord) -> Color3 {
+        self.tint
+    }
+}
+
+#[derive(Debug)]
+pub struct CarPaint {
+    base: Color3,
+    flake: f64,
+    coat_refraction_index: f64,
+}
+
+impl CarPaint {
+    /// `base`: Tint of the metallic body under the clearcoat
+    /// `flake`: How much the metallic flakes perturb the body reflection (0 = smooth)
+    /// `coat_refraction_index`: Refraction index of the clearcoat layer
+    pub fn new(base: Color3, flake: f64, coat_refraction_index: f64) -> Self {
+        assert!(flake >= 0.0);
+        Self {
+            base,
+            flake,
+            coat_refraction_index,
+        }
+    }
+}
+
+impl Material for CarPaint {
+    /// ## Math
+    /// ### Concept
+    /// Car paint is a thin clear dielectric coat over a tinted metallic body made of
+    /// many tiny, slightly misaligned flakes.
+    ///
+    /// ### Calculation
+    /// The coat reflects with probability `R(θ)` given by Schlick's approximation
+    /// using the coat's refraction index, producing a sharp untinted highlight.
+    ///
+    /// Otherwise the ray reaches the body and reflects off a flake whose normal is
+    /// `N' = unit(N + flake * random_unit_vector())`, tinted by the base color.
+    /// Flake reflections that end up below the surface are absorbed. `N` faces the incoming
+    /// ray, so the back of a surface is painted the same way.
+    ///
+    /// ### Outcome
+    /// - Coat: `attenuation` = white, `scattered` = mirror reflection about `N`
+    /// - Body: `attenuation` = base color, `scattered` = reflection about `N'`
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+        let unit_direction = ray_in.dir.unit();
+        let normal = hit_record.facing_normal();
+        let cos_theta = (-unit_direction).dot(&normal).min(1.0);
+
+        if reflectance(cos_theta, self.coat_refraction_index) > random_percentage() {
+            let reflected = Vec3::reflect(&unit_direction, &normal);
+            let scattered = Ray::new_time(hit_record.p, reflected, ray_in.tm);
+            return Some(ScatterRecord::new(scattered, Color3::new(1.0, 1.0, 1.0)));
+        }
+
+        let flake_normal = (normal + self.flake * Vec3::random_unit()).unit();
+        let reflected = Vec3::reflect(&unit_direction, &flake_normal);
+        if reflected.dot(&normal) <= 0.0 {
+            return None;
+        }
+
+        let scattered = Ray::new_time(hit_record.p, reflected, ray_in.tm);
+        Some(ScatterRecord::new(scattered, self.base))
+    }
+
+    fn albedo(&self, _hit_record: &HitRecord) -> Color3 {
+        self.base
+    }
+}
+
+/// Debug material that shades front faces and back faces with different colors, so inside-out
+/// geometry (e.g. triangles with flipped winding) stands out
+#[derive(Debug)]
+pub struct FaceOrientation {
+    front: Color3,
+    back: Color3,
+}
+
+impl FaceOrientation {
+    pub fn new(front: Color3, back: Color3) -> Self {
+        Self { front, back }
+    }
+}
+
+impl Default for FaceOrientation {
+    /// Blue front faces and red back faces
+    fn default() -> Self {
+        Self::new(Color3::new(0.1, 0.2, 0.8), Color3::new(0.8, 0.1, 0.1))
+    }
+}
+
+impl Material for FaceOrientation {
+    /// Scatters like `Lambertian` with the albedo picked by `is_front_face`, back faces
+    /// scatter to the side the ray arrived from
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+        let normal = hit_record.facing_normal();
+        let mut scatter_direction = normal + Vec3::random_unit();
+        if scatter_direction.near_zero() {
+            scatter_direction = normal;
+        }
+
+        let albedo = if hit_record.is_front_face {
+            self.front
+        } else {
+            self.back
+        };
+        let scattered = Ray::new_time(hit_record.p, scatter_direction, ray_in.tm);
+        Some(ScatterRecord::new(scattered, albedo))
+    }
+
+    fn albedo(&self, hit_record: &HitRecord) -> Color3 {
+        if hit_record.is_front_face {
+            self.front
+        } else {
+            self.back
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::Hittable;
+    use crate::interval::Interval;
+    use crate::quad::Quad;
+    use crate::texture::WaveNormalMap;
+    use crate::utils::seed_thread_rng;
+
+    #[test]
+    fn normal_map_follows_the_quad_uv() {
+        let normal_map = Arc::new(WaveNormalMap::new(2.0, 0.05));
+        let material = Arc::new(NormalMapped::new(
+            Arc::new(Lambertian::new(Color3::new(0.5, 0.5, 0.5))),
+            normal_map.clone(),
+        ));
+        // `u` runs along +Z and `v` along +Y, so the quad faces -X
+        let quad = Quad::new(
+            Point3::zero(),
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            material.clone(),
+        );
+
+        let mut normals = Vec::new();
+        for (u, v) in [(0.1, 0.3), (0.3, 0.3), (0.6, 0.8)] {
+            let ray = Ray::new(Point3::new(1.0, v, u), Vec3::new(-1.0, 0.0, 0.0));
+            let hit_record = quad.hit(&ray, Interval::new(0.001, f64::INFINITY)).unwrap();
+            let normal = material.perturbed(&hit_record).normal;
+
+            // Tangent space `x`, `y`, `z` is +Z, +Y, -X in world space
+            let tangent_normal =
+                2.0 * normal_map.value(u, v, &hit_record.p) - Vec3::new(1.0, 1.0, 1.0);
+            let expected = Vec3::new(-tangent_normal.z, tangent_normal.y, tangent_normal.x).unit();
+            assert!(
+                (normal - expected).length() < 1e-9,
+                "{normal} != {expected}"
+            );
+            normals.push(normal);
+        }
+        assert!((normals[0] - normals[1]).length() > 1e-3);
+        assert!((normals[1] - normals[2]).length() > 1e-3);
+    }
+
+    /// Hit record of `ray` on a gray floor facing +Y around the origin, standing in for the
+    /// material under test
+    fn floor_hit(ray: &Ray) -> HitRecord {
+        let floor = Quad::new(
+            Point3::new(-10.0, 0.0, -10.0),
+            Vec3::new(0.0, 0.0, 20.0),
+            Vec3::new(20.0, 0.0, 0.0),
+            Arc::new(Lambertian::new(Color3::new(0.5, 0.5, 0.5))),
+        );
+        floor.hit(ray, Interval::new(0.001, f64::INFINITY)).unwrap()
+    }
+
+    #[test]
+    fn metal_fuzz_is_clamped_to_one() {
+        assert_eq!(Metal::new(Color3::zero(), 5.0).fuzz, 1.0);
+        assert_eq!(Metal::new(Color3::zero(), 0.3).fuzz, 0.3);
+    }
+
+    #[test]
+    fn fuzzed_grazing_reflections_below_the_surface_are_absorbed() {
+        seed_thread_rng(31);
+        // Coming in almost parallel to the floor
+        let ray = Ray::new(Point3::new(-1.0, 0.01, 0.0), Vec3::new(1.0, -0.01, 0.0));
+        let hit_record = floor_hit(&ray);
+
+        let fuzzy = Metal::new(Color3::new(0.8, 0.8, 0.8), 1.0);
+        let scattered: Vec<ScatterRecord> = (0..1000)
+            .filter_map(|_| fuzzy.scatter(&ray, &hit_record))
+            .collect();
+        // About half of the perturbations point into the floor
+        assert!(scattered.len() > 300 && scattered.len() < 700);
+        assert!(scattered.iter().all(|record| record.scattered.dir.y > 0.0));
+
+        let mirror = Metal::new(Color3::new(0.8, 0.8, 0.8), 0.0);
+        assert!(mirror.scatter(&ray, &hit_record).is_some());
+    }
+
+    #[test]
+    fn colored_glass_tints_transmitted_rays() {
+        seed_thread_rng(37);
+        let ray = Ray::new(Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let hit_record = floor_hit(&ray);
+        let attenuations = |glass: &Dielectric, transmitted: bool| -> Vec<(f64, f64, f64)> {
+            (0..200)
+                .filter_map(|_| glass.scatter(&ray, &hit_record))
+                .filter(|record| (record.scattered.dir.y < 0.0) == transmitted)
+                .map(|record| {
+                    let a = record.attenuation;
+                    (a.x, a.y, a.z)
+                })
+                .collect()
+        };
+
+        let green = Dielectric::new_colored(1.5, Color3::new(0.2, 0.9, 0.3));
+        let transmitted = attenuations(&green, true);
+        assert!(!transmitted.is_empty());
+        assert!(transmitted.iter().all(|&a| a == (0.2, 0.9, 0.3)));
+        // Reflections off the surface stay white
+        assert!(
+            attenuations(&green, false)
+                .iter()
+                .all(|&a| a == (1.0, 1.0, 1.0))
+        );
+
+        let clear = Dielectric::glass();
+        assert!(
+            attenuations(&clear, true)
+                .iter()
+                .all(|&a| a == (1.0, 1.0, 1.0))
+        );
+    }
+
+    #[test]
+    fn brushed_metal_spreads_along_the_brush() {
+        seed_thread_rng(43);
+        let ray = Ray::new(Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let hit_record = floor_hit(&ray);
+        // Brushed along X, so the reflections smear along X and stay tight along Z
+        let brushed = BrushedMetal::new(
+            Color3::new(0.8, 0.8, 0.8),
+            0.6,
+            0.05,
+            Vec3::new(1.0, 0.0, 0.0),
+        );
+
+        let directions: Vec<Vec3> = (0..5000)
+            .filter_map(|_| brushed.scatter(&ray, &hit_record))
+            .map(|record| record.scattered.dir.unit())
+            .collect();
+        assert!(directions.iter().all(|dir| dir.y > 0.0));
+        let spread = |component: fn(&Vec3) -> f64| {
+            directions
+                .iter()
+                .map(|dir| component(dir).powi(2))
+                .sum::<f64>()
+                / directions.len() as f64
+        };
+        let along = spread(|dir| dir.x);
+        let across = spread(|dir| dir.z);
+        assert!(along > 20.0 * across, "{along} along, {across} across");
     }
 }
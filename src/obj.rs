@@ -0,0 +1,206 @@
+use std::fs;
+use std::io;
+use std::sync::Arc;
+
+use crate::hittable::HittableList;
+use crate::material::Material;
+use crate::triangle::Triangle;
+use crate::vec::Point3;
+
+/// Axis pointing up in the file being imported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UpAxis {
+    #[default]
+    Y,
+    Z, // Blender and most CAD exports
+}
+
+/// Handedness of the coordinate system used by the file being imported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Handedness {
+    #[default]
+    Right,
+    Left,
+}
+
+/// How to map the file's coordinates to this crate's right-handed, Y-up convention
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ObjOptions {
+    pub up_axis: UpAxis,
+    pub handedness: Handedness,
+}
+
+impl ObjOptions {
+    /// Converts a point from the file's coordinate system into right-handed Y-up
+    fn convert_point(&self, p: Point3) -> Point3 {
+        // A left-handed system is a right-handed one with its depth axis mirrored
+        let p = match (self.handedness, self.up_axis) {
+            (Handedness::Right, _) => p,
+            (Handedness::Left, UpAxis::Y) => Point3::new(p.x, p.y, -p.z),
+            (Handedness::Left, UpAxis::Z) => Point3::new(p.x, -p.y, p.z),
+        };
+        match self.up_axis {
+            UpAxis::Y => p,
+            // Rotate -90° around X so +Z becomes +Y
+            UpAxis::Z => Point3::new(p.x, p.z, -p.y),
+        }
+    }
+}
+
+/// Loads the geometry of a Wavefront OBJ file as a list of triangles sharing `material`.
+///
+/// Only `v` and `f` lines are used, everything else (normals, texture coordinates, groups,
+/// materials...) is ignored. Polygons are triangulated as a fan around their first vertex.
+pub fn load_obj(path: &str, material: Arc<dyn Material>) -> io::Result<HittableList> {
+    load_obj_with_options(path, material, ObjOptions::default())
+}
+
+/// Same as `load_obj` but converts the file's up axis and handedness to this crate's
+/// convention, so e.g. Z-up exports stand upright
+pub fn load_obj_with_options(
+    path: &str,
+    material: Arc<dyn Material>,
+    options: ObjOptions,
+) -> io::Result<HittableList> {
+    let contents = fs::read_to_string(path)?;
+    parse_obj(&contents, material, options)
+}
+
+fn parse_obj(
+    contents: &str,
+    material: Arc<dyn Material>,
+    options: ObjOptions,
+) -> io::Result<HittableList> {
+    let mut vertices: Vec<Point3> = Vec::new();
+    let mut world = HittableList::new();
+
+    for (line_index, line) in contents.lines().enumerate() {
+        let line_number = line_index + 1;
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("v") => {
+                let mut coords = [0.0; 3];
+                for coord in coords.iter_mut() {
+                    *coord = tokens
+                        .next()
+                        .and_then(|token| token.parse().ok())
+                        .ok_or_else(|| {
+                            invalid_data(line_number, "expected 3 vertex coordinates")
+                        })?;
+                }
+                vertices.push(options.convert_point(Point3::new(coords[0], coords[1], coords[2])));
+            }
+            Some("f") => {
+                let mut indices = tokens
+                    .map(|token| resolve_index(token, vertices.len(), line_number))
+                    .collect::<io::Result<Vec<usize>>>()?;
+                if indices.len() < 3 {
+                    return Err(invalid_data(
+                        line_number,
+                        "a face needs at least 3 vertices",
+                    ));
+                }
+                // Mirroring an axis turns counter-clockwise faces clockwise, flip them back
+                if options.handedness == Handedness::Left {
+                    indices.reverse();
+                }
+
+                for k in 1..indices.len() - 1 {
+                    world.add(Triangle::new(
+                        vertices[indices[0]],
+                        vertices[indices[k]],
+                        vertices[indices[k + 1]],
+                        Arc::clone(&material),
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(world)
+}
+
+/// Converts a face vertex reference (`i`, `i/t`, `i//n` or `i/t/n`) to an index into
+/// `vertices`. Indices start at 1, negative ones count back from the last vertex read.
+fn resolve_index(token: &str, vertex_count: usize, line_number: usize) -> io::Result<usize> {
+    let index: i64 = token
+        .split('/')
+        .next()
+        .and_then(|index| index.parse().ok())
+        .ok_or_else(|| invalid_data(line_number, "invalid face vertex index"))?;
+
+    let resolved = if index < 0 {
+        vertex_count as i64 + index
+    } else {
+        index - 1
+    };
+    if resolved < 0 || resolved >= vertex_count as i64 {
+        return Err(invalid_data(line_number, "face vertex index out of range"));
+    }
+    Ok(resolved as usize)
+}
+
+fn invalid_data(line_number: usize, message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("line {}: {}", line_number, message),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::Hittable;
+    use crate::interval::Interval;
+    use crate::material::Lambertian;
+    use crate::ray::Ray;
+    use crate::vec::Color3;
+    use crate::vec::Vec3;
+
+    fn gray() -> Arc<dyn Material> {
+        Arc::new(Lambertian::new(Color3::new(0.5, 0.5, 0.5)))
+    }
+
+    #[test]
+    fn quad_is_split_into_two_triangles() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/quad.obj");
+        let world = load_obj(path, gray()).unwrap();
+        assert_eq!(world.len(), 2);
+
+        let ray = Ray::new(Point3::new(0.25, 0.75, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_record = world
+            .hit(&ray, Interval::new(0.001, f64::INFINITY))
+            .unwrap();
+        assert!(hit_record.is_front_face);
+    }
+
+    #[test]
+    fn negative_indices_count_back_from_the_last_vertex() {
+        let contents = "v 0 0 0\nv 1 0 0\nv 0 1 0\nf -3 -2 -1\n";
+        let world = parse_obj(contents, gray(), ObjOptions::default()).unwrap();
+        assert_eq!(world.len(), 1);
+    }
+
+    #[test]
+    fn z_up_files_stand_upright() {
+        let contents = "v 0 0 0\nv 1 0 0\nv 0 0 2\nf 1 2 3\n";
+        let options = ObjOptions {
+            up_axis: UpAxis::Z,
+            handedness: Handedness::Right,
+        };
+        let world = parse_obj(contents, gray(), options).unwrap();
+        assert_eq!(world.boundnig_box().max().y, 2.0);
+    }
+
+    #[test]
+    fn malformed_lines_are_errors() {
+        let out_of_range = parse_obj("v 0 0 0\nf 1 2 3\n", gray(), ObjOptions::default());
+        assert_eq!(
+            out_of_range.err().unwrap().to_string(),
+            "line 2: face vertex index out of range"
+        );
+        assert!(parse_obj("v 0 0\n", gray(), ObjOptions::default()).is_err());
+        assert!(parse_obj("v 0 0 0\nf 1 1\n", gray(), ObjOptions::default()).is_err());
+    }
+}
@@ -0,0 +1,66 @@
+use crate::vec::Vec3;
+
+/// Orthonormal basis `u`, `v`, `w` built around a given `w` axis
+#[derive(Debug, Clone, Copy)]
+pub struct Onb {
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+}
+
+impl Onb {
+    /// Basis whose `w` axis points along `n`, which doesn't need to be a unit vector
+    pub fn new(n: &Vec3) -> Self {
+        let w = n.unit();
+        // Any vector that isn't parallel to `w` works as a starting point
+        let a = if w.x.abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let v = w.cross(a).unit();
+        let u = w.cross(v);
+        Self { u, v, w }
+    }
+
+    pub fn u(&self) -> Vec3 {
+        self.u
+    }
+
+    pub fn v(&self) -> Vec3 {
+        self.v
+    }
+
+    pub fn w(&self) -> Vec3 {
+        self.w
+    }
+
+    /// Converts `local`, expressed in this basis, to world coordinates
+    pub fn transform(&self, local: &Vec3) -> Vec3 {
+        local.x * self.u + local.y * self.v + local.z * self.w
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::seed_thread_rng;
+
+    #[test]
+    fn cosine_directions_average_two_thirds() {
+        seed_thread_rng(23);
+        let normal = Vec3::new(1.0, 2.0, -2.0);
+        let onb = Onb::new(&normal);
+        let samples = 50_000;
+
+        // `E[cos θ]` under `cos θ / π` over the hemisphere is `2/3`
+        let mut sum = 0.0;
+        for _ in 0..samples {
+            let direction = onb.transform(&Vec3::random_cosine_direction());
+            assert!((direction.length() - 1.0).abs() < 1e-9);
+            sum += direction.dot(&normal.unit());
+        }
+        let mean = sum / samples as f64;
+        assert!((mean - 2.0 / 3.0).abs() < 0.01, "mean cosine {mean}");
+    }
+}
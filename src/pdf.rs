@@ -0,0 +1,167 @@
+use std::f64::consts::PI;
+use std::fmt;
+use std::fmt::Debug;
+
+use crate::hittable::Hittable;
+use crate::onb::Onb;
+use crate::utils::random_percentage;
+use crate::vec::Point3;
+use crate::vec::Vec3;
+
+/// Distribution of directions used to importance sample scattered rays
+pub trait Pdf: Debug + Send + Sync {
+    /// Probability density of `generate` returning `dir`, per unit solid angle
+    fn value(&self, dir: &Vec3) -> f64;
+
+    /// Random direction following the distribution
+    fn generate(&self) -> Vec3;
+}
+
+/// Directions over the hemisphere around a normal, weighted by their cosine with it
+#[derive(Debug)]
+pub struct CosinePdf {
+    uvw: Onb,
+}
+
+impl CosinePdf {
+    pub fn new(normal: &Vec3) -> Self {
+        Self {
+            uvw: Onb::new(normal),
+        }
+    }
+}
+
+impl Pdf for CosinePdf {
+    /// `cos θ / π` above the surface, 0 below it
+    fn value(&self, dir: &Vec3) -> f64 {
+        let cos_theta = dir.unit().dot(&self.uvw.w());
+        (cos_theta / PI).max(0.0)
+    }
+
+    fn generate(&self) -> Vec3 {
+        self.uvw.transform(&Vec3::random_cosine_direction())
+    }
+}
+
+/// Directions from `origin` towards the objects, see `Hittable::random`
+pub struct HittablePdf<'a> {
+    objects: &'a dyn Hittable,
+    origin: Point3,
+}
+
+impl<'a> HittablePdf<'a> {
+    pub fn new(objects: &'a dyn Hittable, origin: Point3) -> Self {
+        Self { objects, origin }
+    }
+}
+
+impl Debug for HittablePdf<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HittablePdf")
+            .field("origin", &self.origin)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Pdf for HittablePdf<'_> {
+    fn value(&self, dir: &Vec3) -> f64 {
+        self.objects.pdf_value(&self.origin, dir)
+    }
+
+    fn generate(&self) -> Vec3 {
+        self.objects.random(&self.origin)
+    }
+}
+
+/// Picks either distribution with equal probability
+#[derive(Debug)]
+pub struct MixturePdf<'a> {
+    p: [&'a dyn Pdf; 2],
+}
+
+impl<'a> MixturePdf<'a> {
+    pub fn new(p0: &'a dyn Pdf, p1: &'a dyn Pdf) -> Self {
+        Self { p: [p0, p1] }
+    }
+}
+
+impl Pdf for MixturePdf<'_> {
+    fn value(&self, dir: &Vec3) -> f64 {
+        0.5 * self.p[0].value(dir) + 0.5 * self.p[1].value(dir)
+    }
+
+    fn generate(&self) -> Vec3 {
+        if random_percentage() < 0.5 {
+            self.p[0].generate()
+        } else {
+            self.p[1].generate()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::interval::Interval;
+    use crate::material::DiffuseLight;
+    use crate::quad::Quad;
+    use crate::ray::Ray;
+    use crate::utils::seed_thread_rng;
+    use crate::vec::Color3;
+
+    /// Mean and variance of `samples`
+    fn mean_and_variance(samples: &[f64]) -> (f64, f64) {
+        let n = samples.len() as f64;
+        let mean = samples.iter().sum::<f64>() / n;
+        let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n;
+        (mean, variance)
+    }
+
+    #[test]
+    fn sampling_the_light_converges_faster() {
+        // Small light of unit radiance, 2 units above a surface facing up at the origin
+        let emit = Arc::new(DiffuseLight::new(Color3::new(1.0, 1.0, 1.0)));
+        let light = Quad::new(
+            Point3::new(-0.25, 2.0, -0.25),
+            Vec3::new(0.5, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 0.5),
+            emit,
+        );
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+
+        // Estimates of `∫ L cos θ / π dω` over the hemisphere, `L` is 1 towards the light
+        let estimate = |direction: Vec3, pdf: f64| {
+            let ray = Ray::new(Point3::zero(), direction);
+            if pdf <= 0.0 || !light.occluded(&ray, Interval::new(0.001, f64::INFINITY)) {
+                return 0.0;
+            }
+            normal.dot(&direction.unit()) / PI / pdf
+        };
+
+        seed_thread_rng(29);
+        const SAMPLES: usize = 20_000;
+        let uniform: Vec<f64> = (0..SAMPLES)
+            .map(|_| estimate(Vec3::random_on_hemisphere(normal), 1.0 / (2.0 * PI)))
+            .collect();
+
+        let cosine = CosinePdf::new(&normal);
+        let towards_light = HittablePdf::new(&light, Point3::zero());
+        let mixture = MixturePdf::new(&cosine, &towards_light);
+        let weighted: Vec<f64> = (0..SAMPLES)
+            .map(|_| {
+                let direction = mixture.generate();
+                estimate(direction, mixture.value(&direction))
+            })
+            .collect();
+
+        // A small light far away is about `A cos θ / (π r^2)`
+        let expected = 0.25 / (PI * 4.0);
+        let (uniform_mean, uniform_variance) = mean_and_variance(&uniform);
+        let (weighted_mean, weighted_variance) = mean_and_variance(&weighted);
+        assert!((weighted_mean - expected).abs() < 0.05 * expected);
+        assert!((uniform_mean - expected).abs() < 0.3 * expected);
+        assert!(weighted_variance * 10.0 < uniform_variance);
+    }
+}
@@ -0,0 +1,137 @@
+use std::sync::Arc;
+
+use crate::aabb::AABB;
+use crate::hittable::HitRecord;
+use crate::hittable::Hittable;
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec::Point3;
+use crate::vec::Vec3;
+
+/// How far from `point` the bounding box of a plane reaches. Planes are infinite but boxes
+/// have to stay finite for the surface area heuristic.
+const PLANE_EXTENT: f64 = 1e6;
+
+/// Infinite plane through `point`, facing along `normal`
+///
+/// Best kept in a `HittableList` next to the BVH rather than inside it, its box would
+/// swallow every other node.
+#[derive(Debug)]
+pub struct Plane {
+    point: Point3,
+    normal: Vec3,
+    material: Arc<dyn Material>,
+    bbox: AABB,
+}
+
+impl Plane {
+    /// `normal` doesn't need to be a unit vector
+    pub fn new(point: Point3, normal: Vec3, material: Arc<dyn Material>) -> Self {
+        let normal = normal.unit();
+
+        // Box of a disk of radius `PLANE_EXTENT`, so axis aligned planes stay flat
+        let extent = PLANE_EXTENT
+            * Vec3::new(
+                (1.0 - normal.x * normal.x).max(0.0).sqrt(),
+                (1.0 - normal.y * normal.y).max(0.0).sqrt(),
+                (1.0 - normal.z * normal.z).max(0.0).sqrt(),
+            );
+
+        Self {
+            point,
+            normal,
+            material,
+            bbox: AABB::from_points(point - extent, point + extent).pad_to_minimum(),
+        }
+    }
+}
+
+impl Hittable for Plane {
+    /// ## Math
+    /// ### Variables
+    /// `P0` is the point on the plane and `n` the unit normal
+    /// Ray: `P(t) = O + t*d`
+    /// ### Calculation
+    /// The plane is `n ⋅ (P - P0) = 0`. Substituting the ray gives
+    /// `t = n ⋅ (P0 - O) / (n ⋅ d)`, which doesn't exist if the ray is parallel to the plane.
+    ///
+    /// ### Outcomes
+    /// - Every `t` within `ray_t` is a hit, `u` and `v` are left at 0
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let denom = self.normal.dot(&ray.dir);
+        // Ray is parallel to the plane
+        if denom.abs() < 1e-8 {
+            return None;
+        }
+
+        let t = self.normal.dot(&(self.point - ray.origin)) / denom;
+        if !ray_t.surrounds(t) {
+            return None;
+        }
+
+        Some(HitRecord::new(
+            ray.at(t),
+            self.normal,
+            ray,
+            Arc::clone(&self.material),
+            t,
+        ))
+    }
+
+    fn boundnig_box(&self) -> &AABB {
+        &self.bbox
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::vec::Color3;
+
+    fn ground() -> Plane {
+        let material = Arc::new(Lambertian::new(Color3::new(0.5, 0.5, 0.5)));
+        Plane::new(
+            Point3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, 2.0, 0.0),
+            material,
+        )
+    }
+
+    #[test]
+    fn plane_is_hit_from_above() {
+        let ray = Ray::new(Point3::new(3.0, 4.0, -2.0), Vec3::new(0.0, -1.0, 0.0));
+        let record = ground()
+            .hit(&ray, Interval::new(0.001, f64::INFINITY))
+            .unwrap();
+
+        assert_eq!(record.t, 3.0);
+        assert_eq!((record.p.x, record.p.y, record.p.z), (3.0, 1.0, -2.0));
+        assert!(record.is_front_face);
+        assert_eq!(record.facing_normal().y, 1.0);
+    }
+
+    #[test]
+    fn parallel_rays_miss() {
+        let ray = Ray::new(Point3::new(0.0, 2.0, 0.0), Vec3::new(1.0, 0.0, 1.0));
+        assert!(
+            ground()
+                .hit(&ray, Interval::new(0.001, f64::INFINITY))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn backface_hits_face_the_ray() {
+        let ray = Ray::new(Point3::new(0.0, -1.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let record = ground()
+            .hit(&ray, Interval::new(0.001, f64::INFINITY))
+            .unwrap();
+
+        assert_eq!(record.t, 2.0);
+        assert!(!record.is_front_face);
+        assert_eq!(record.normal.y, 1.0);
+        assert_eq!(record.facing_normal().y, -1.0);
+    }
+}
@@ -0,0 +1,603 @@
+use std::io;
+use std::io::Write;
+
+/// Largest payload of a stored (uncompressed) deflate block
+const MAX_STORED_BLOCK: usize = 65535;
+
+/// How the samples of a PNG are encoded, written as chunks color-managed viewers read to
+/// display the image as intended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorProfile {
+    /// `sRGB` chunk, with the `gAMA` chunk of 1/2.2 the spec asks for next to it
+    Srgb,
+    /// `gAMA` chunk alone, the exponent samples were encoded with times 100000 (e.g. 50000
+    /// for the square root `Color3::to_bytes` applies)
+    Gamma(u32),
+}
+
+impl ColorProfile {
+    /// Profile matching samples raised to `1/gamma`, `Srgb` when `gamma` is close to 2.2.
+    /// A `gamma` of 0 or less means linear samples.
+    pub fn from_gamma(gamma: f64) -> Self {
+        if gamma <= 0.0 {
+            ColorProfile::Gamma(100000)
+        } else if (gamma - 2.2).abs() < 0.05 {
+            ColorProfile::Srgb
+        } else {
+            ColorProfile::Gamma((100000.0 / gamma).round() as u32)
+        }
+    }
+}
+
+/// Writes 8-bit RGB `pixels` (row-major, 3 bytes per pixel) as a PNG, with the chunks of
+/// `color_profile` after the header when given.
+///
+/// The image data is zlib-wrapped using stored deflate blocks, so the file is not compressed
+/// but is still a valid PNG any viewer can open.
+pub fn write_png(
+    writer: &mut impl Write,
+    width: usize,
+    height: usize,
+    pixels: &[u8],
+    color_profile: Option<ColorProfile>,
+) -> io::Result<()> {
+    assert_eq!(pixels.len(), width * height * 3);
+    writer.write_all(b"\x89PNG\r\n\x1a\n")?;
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&(width as u32).to_be_bytes());
+    ihdr.extend_from_slice(&(height as u32).to_be_bytes());
+    ihdr.extend_from_slice(&[
+        8, // Bit depth
+        2, // Color type: RGB
+        0, // Compression method: deflate
+        0, // Filter method: adaptive
+        0, // Interlace method: none
+    ]);
+    write_chunk(writer, b"IHDR", &ihdr)?;
+
+    // Both have to come before the image data
+    match color_profile {
+        Some(ColorProfile::Srgb) => {
+            write_chunk(writer, b"sRGB", &[0])?; // Rendering intent: perceptual
+            write_chunk(writer, b"gAMA", &45455u32.to_be_bytes())?;
+        }
+        Some(ColorProfile::Gamma(gamma)) => write_chunk(writer, b"gAMA", &gamma.to_be_bytes())?,
+        None => {}
+    }
+
+    // Every scanline is prefixed by its filter type, 0 meaning unfiltered
+    let mut raw = Vec::with_capacity(height * (width * 3 + 1));
+    for row in pixels.chunks(width * 3) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+    write_chunk(writer, b"IDAT", &zlib_stored(&raw))?;
+
+    write_chunk(writer, b"IEND", &[])
+}
+
+fn write_chunk(writer: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(kind)?;
+    writer.write_all(data)?;
+    let crc = crc32(&[kind.as_slice(), data]);
+    writer.write_all(&crc.to_be_bytes())
+}
+
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let block_count = data.len().div_ceil(MAX_STORED_BLOCK).max(1);
+    let mut out = Vec::with_capacity(data.len() + block_count * 5 + 6);
+    out.extend_from_slice(&[0x78, 0x01]); // Deflate, 32K window, no preset dictionary
+
+    let mut blocks = data.chunks(MAX_STORED_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        let is_final = blocks.peek().is_none();
+        let len = block.len() as u16;
+        out.push(is_final as u8);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn crc32(parts: &[&[u8]]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in parts.iter().flat_map(|part| part.iter()) {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    const MOD_ADLER: u32 = 65521;
+    let mut a = 1u32;
+    let mut b = 0u32;
+    for byte in data {
+        a = (a + *byte as u32) % MOD_ADLER;
+        b = (b + a) % MOD_ADLER;
+    }
+    (b << 16) | a
+}
+
+/// Decodes a non-interlaced PNG with 8 or 16 bits per channel into 8-bit RGB pixels.
+/// Returns `(width, height, pixels)`, alpha is dropped.
+pub fn read_png(data: &[u8]) -> io::Result<(usize, usize, Vec<u8>)> {
+    let mut header = None;
+    let mut palette: &[u8] = &[];
+    let mut compressed = Vec::new();
+    for (kind, body) in chunks(data)? {
+        match kind {
+            b"IHDR" if body.len() >= 13 => header = Some(body),
+            b"PLTE" => palette = body,
+            b"IDAT" => compressed.extend_from_slice(body),
+            _ => {}
+        }
+    }
+
+    let header = header.ok_or_else(|| invalid_data("missing IHDR chunk"))?;
+    let width = u32::from_be_bytes(header[0..4].try_into().unwrap()) as usize;
+    let height = u32::from_be_bytes(header[4..8].try_into().unwrap()) as usize;
+    let (bit_depth, color_type, interlace) = (header[8], header[9], header[12]);
+    if interlace != 0 {
+        return Err(unsupported("interlaced PNGs are not supported"));
+    }
+    if bit_depth != 8 && !(bit_depth == 16 && color_type != 3) {
+        return Err(unsupported("only 8 and 16 bit PNGs are supported"));
+    }
+    let channels = match color_type {
+        0 | 3 => 1, // Grayscale, palette
+        2 => 3,     // RGB
+        4 => 2,     // Grayscale + alpha
+        6 => 4,     // RGBA
+        _ => return Err(invalid_data("unknown color type")),
+    };
+    let bytes_per_sample = bit_depth as usize / 8;
+    let bpp = channels * bytes_per_sample;
+
+    let raw = inflate_zlib(&compressed)?;
+    let unfiltered = unfilter(&raw, width, height, bpp)?;
+
+    let mut pixels = Vec::with_capacity(width * height * 3);
+    for texel in unfiltered.chunks(bpp) {
+        // Only keep the most significant byte of 16 bit samples
+        let sample = |channel: usize| texel[channel * bytes_per_sample];
+        match color_type {
+            0 | 4 => pixels.extend_from_slice(&[sample(0); 3]),
+            2 | 6 => pixels.extend_from_slice(&[sample(0), sample(1), sample(2)]),
+            _ => {
+                let index = sample(0) as usize * 3;
+                let entry = palette
+                    .get(index..index + 3)
+                    .ok_or_else(|| invalid_data("palette index out of range"))?;
+                pixels.extend_from_slice(entry);
+            }
+        }
+    }
+
+    Ok((width, height, pixels))
+}
+
+/// The color profile chunks of a PNG, `None` if it has neither `sRGB` nor `gAMA`
+pub fn read_color_profile(data: &[u8]) -> io::Result<Option<ColorProfile>> {
+    let mut gamma = None;
+    for (kind, body) in chunks(data)? {
+        match kind {
+            b"sRGB" => return Ok(Some(ColorProfile::Srgb)),
+            b"gAMA" if body.len() == 4 => {
+                gamma = Some(ColorProfile::Gamma(u32::from_be_bytes(
+                    body.try_into().unwrap(),
+                )))
+            }
+            _ => {}
+        }
+    }
+    Ok(gamma)
+}
+
+/// Type and body of every chunk up to `IEND`, after checking the signature
+fn chunks(data: &[u8]) -> io::Result<Vec<(&[u8], &[u8])>> {
+    if data.len() < 8 || &data[..8] != b"\x89PNG\r\n\x1a\n" {
+        return Err(invalid_data("not a PNG file"));
+    }
+
+    let mut chunks = Vec::new();
+    let mut pos = 8;
+    while pos + 8 <= data.len() {
+        let len = u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+        let kind = &data[pos + 4..pos + 8];
+        if kind == b"IEND" {
+            break;
+        }
+        let body = data
+            .get(pos + 8..pos + 8 + len)
+            .ok_or_else(|| invalid_data("truncated chunk"))?;
+        chunks.push((kind, body));
+        pos += len + 12;
+    }
+    Ok(chunks)
+}
+
+/// Reverses the per-scanline filters, returning the bare samples
+fn unfilter(raw: &[u8], width: usize, height: usize, bpp: usize) -> io::Result<Vec<u8>> {
+    let stride = width * bpp;
+    if raw.len() < height * (stride + 1) {
+        return Err(invalid_data("not enough image data"));
+    }
+
+    let mut out = vec![0u8; height * stride];
+    for y in 0..height {
+        let filter = raw[y * (stride + 1)];
+        let line = &raw[y * (stride + 1) + 1..(y + 1) * (stride + 1)];
+        let (previous, current) = out.split_at_mut(y * stride);
+        let up_line = if y > 0 {
+            &previous[(y - 1) * stride..]
+        } else {
+            &[][..]
+        };
+        let current = &mut current[..stride];
+
+        for x in 0..stride {
+            let left = if x >= bpp { current[x - bpp] } else { 0 };
+            let up = up_line.get(x).copied().unwrap_or(0);
+            let up_left = if x >= bpp {
+                up_line.get(x - bpp).copied().unwrap_or(0)
+            } else {
+                0
+            };
+            let predictor = match filter {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                4 => paeth(left, up, up_left),
+                _ => return Err(invalid_data("unknown filter type")),
+            };
+            current[x] = line[x].wrapping_add(predictor);
+        }
+    }
+    Ok(out)
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let pa = (p - a as i16).abs();
+    let pb = (p - b as i16).abs();
+    let pc = (p - c as i16).abs();
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+/// Order in which the code length code lengths are stored in a dynamic block
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    buffer: u64,
+    count: u32,
+}
+
+impl BitReader<'_> {
+    fn bits(&mut self, n: u32) -> io::Result<u32> {
+        while self.count < n {
+            let byte = *self
+                .data
+                .get(self.pos)
+                .ok_or_else(|| invalid_data("unexpected end of deflate stream"))?;
+            self.buffer |= (byte as u64) << self.count;
+            self.pos += 1;
+            self.count += 8;
+        }
+        let value = (self.buffer & ((1u64 << n) - 1)) as u32;
+        self.buffer >>= n;
+        self.count -= n;
+        Ok(value)
+    }
+
+    /// Drops the bits left in the current byte
+    fn align_to_byte(&mut self) {
+        self.buffer = 0;
+        self.count = 0;
+    }
+}
+
+/// Canonical Huffman code, decoded one bit at a time
+struct Huffman {
+    counts: [u16; 16], // Number of codes of each length
+    symbols: Vec<u16>, // Symbols ordered by code
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0u16; 16];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+
+        let mut offsets = [0u16; 16];
+        for length in 1..15 {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+        let mut symbols = vec![0u16; lengths.len()];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                symbols[offsets[length as usize] as usize] = symbol as u16;
+                offsets[length as usize] += 1;
+            }
+        }
+        Self { counts, symbols }
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> io::Result<u16> {
+        let mut code: i32 = 0; // Code read so far
+        let mut first: i32 = 0; // First code of the current length
+        let mut index: i32 = 0; // Index of the first symbol of the current length
+        for length in 1..16 {
+            code |= reader.bits(1)? as i32;
+            let count = self.counts[length] as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(invalid_data("invalid Huffman code"))
+    }
+}
+
+fn inflate_zlib(data: &[u8]) -> io::Result<Vec<u8>> {
+    if data.len() < 2 || data[0] & 0x0F != 8 || data[1] & 0x20 != 0 {
+        return Err(invalid_data("unsupported zlib stream"));
+    }
+
+    let mut reader = BitReader {
+        data: &data[2..],
+        pos: 0,
+        buffer: 0,
+        count: 0,
+    };
+    let mut out = Vec::new();
+    loop {
+        let is_final = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                reader.align_to_byte();
+                let header = reader
+                    .data
+                    .get(reader.pos..reader.pos + 4)
+                    .ok_or_else(|| invalid_data("truncated stored block"))?;
+                let len = u16::from_le_bytes([header[0], header[1]]) as usize;
+                let block = reader
+                    .data
+                    .get(reader.pos + 4..reader.pos + 4 + len)
+                    .ok_or_else(|| invalid_data("truncated stored block"))?;
+                out.extend_from_slice(block);
+                reader.pos += 4 + len;
+            }
+            1 => {
+                let mut lengths = [0u8; 288];
+                lengths[..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..].fill(8);
+                let literals = Huffman::new(&lengths);
+                let distances = Huffman::new(&[5; 30]);
+                inflate_block(&mut reader, &mut out, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = read_dynamic_codes(&mut reader)?;
+                inflate_block(&mut reader, &mut out, &literals, &distances)?;
+            }
+            _ => return Err(invalid_data("invalid deflate block type")),
+        }
+        if is_final {
+            return Ok(out);
+        }
+    }
+}
+
+fn read_dynamic_codes(reader: &mut BitReader) -> io::Result<(Huffman, Huffman)> {
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+
+    let mut code_length_lengths = [0u8; 19];
+    for &symbol in CODE_LENGTH_ORDER.iter().take(code_length_count) {
+        code_length_lengths[symbol] = reader.bits(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_length_lengths);
+
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let symbol = code_lengths.decode(reader)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *lengths
+                    .last()
+                    .ok_or_else(|| invalid_data("repeat without previous length"))?;
+                (previous, 3 + reader.bits(2)?)
+            }
+            17 => (0, 3 + reader.bits(3)?),
+            _ => (0, 11 + reader.bits(7)?),
+        };
+        lengths.extend(std::iter::repeat_n(value, repeat as usize));
+    }
+    if lengths.len() > literal_count + distance_count {
+        return Err(invalid_data("too many code lengths"));
+    }
+
+    Ok((
+        Huffman::new(&lengths[..literal_count]),
+        Huffman::new(&lengths[literal_count..]),
+    ))
+}
+
+fn inflate_block(
+    reader: &mut BitReader,
+    out: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> io::Result<()> {
+    loop {
+        let symbol = literals.decode(reader)? as usize;
+        match symbol {
+            0..=255 => out.push(symbol as u8),
+            256 => return Ok(()),
+            _ => {
+                let index = symbol - 257;
+                if index >= LENGTH_BASE.len() {
+                    return Err(invalid_data("invalid length symbol"));
+                }
+                let length =
+                    LENGTH_BASE[index] as usize + reader.bits(LENGTH_EXTRA[index] as u32)? as usize;
+
+                let index = distances.decode(reader)? as usize;
+                if index >= DIST_BASE.len() {
+                    return Err(invalid_data("invalid distance symbol"));
+                }
+                let distance =
+                    DIST_BASE[index] as usize + reader.bits(DIST_EXTRA[index] as u32)? as usize;
+                if distance > out.len() {
+                    return Err(invalid_data("distance too far back"));
+                }
+
+                // Copy byte by byte since the match may overlap the bytes being written
+                let start = out.len() - distance;
+                for k in 0..length {
+                    out.push(out[start + k]);
+                }
+            }
+        }
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn unsupported(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(color_profile: Option<ColorProfile>) -> Vec<u8> {
+        let pixels: Vec<u8> = (0..2 * 3 * 3).map(|i| (i * 14) as u8).collect();
+        let mut png = Vec::new();
+        write_png(&mut png, 2, 3, &pixels, color_profile).unwrap();
+        assert_eq!(read_png(&png).unwrap(), (2, 3, pixels));
+        png
+    }
+
+    #[test]
+    fn color_profile_chunks_round_trip() {
+        for color_profile in [
+            None,
+            Some(ColorProfile::Srgb),
+            Some(ColorProfile::Gamma(50000)),
+        ] {
+            let png = encode(color_profile);
+            assert_eq!(read_color_profile(&png).unwrap(), color_profile);
+        }
+    }
+
+    #[test]
+    fn color_profile_chunks_follow_the_header() {
+        let png = encode(Some(ColorProfile::Srgb));
+        let kinds: Vec<&[u8]> = chunks(&png)
+            .unwrap()
+            .into_iter()
+            .map(|(kind, _)| kind)
+            .collect();
+        assert_eq!(kinds, [b"IHDR", b"sRGB", b"gAMA", b"IDAT"]);
+    }
+
+    #[test]
+    fn color_profile_from_gamma() {
+        assert_eq!(ColorProfile::from_gamma(2.2), ColorProfile::Srgb);
+        assert_eq!(ColorProfile::from_gamma(2.0), ColorProfile::Gamma(50000));
+        assert_eq!(ColorProfile::from_gamma(0.0), ColorProfile::Gamma(100000));
+    }
+
+    #[test]
+    fn checksums_match_the_reference_values() {
+        assert_eq!(crc32(&[b"123456789"]), 0xCBF4_3926);
+        assert_eq!(crc32(&[b"1234", b"56789"]), 0xCBF4_3926);
+        assert_eq!(crc32(&[b"IEND"]), 0xAE42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    }
+
+    #[test]
+    fn stored_blocks_inflate_back() {
+        // Empty, a single block and several blocks
+        for len in [0, 1000, MAX_STORED_BLOCK * 2 + 10] {
+            let data: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+            assert_eq!(inflate_zlib(&zlib_stored(&data)).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn huffman_blocks_inflate() {
+        // Streams written by zlib at level 9, with a fixed and a dynamic Huffman code
+        let fixed = [
+            120, 218, 203, 72, 205, 201, 201, 87, 200, 64, 39, 1, 104, 3, 8, 177,
+        ];
+        assert_eq!(inflate_zlib(&fixed).unwrap(), b"hello hello hello hello");
+
+        let dynamic = [
+            120, 218, 29, 137, 193, 17, 0, 48, 16, 68, 106, 69, 255, 61, 92, 178, 227, 5, 80, 48,
+            136, 114, 134, 227, 39, 54, 223, 62, 56, 242, 15, 67,
+        ];
+        assert_eq!(
+            inflate_zlib(&dynamic).unwrap(),
+            b"aabbaabcaacaccbabbaaabababaaacaabaabcbca"
+        );
+    }
+
+    #[test]
+    fn corrupt_streams_are_rejected() {
+        assert!(inflate_zlib(&[0x78]).is_err());
+        let mut stored = zlib_stored(&[1, 2, 3]);
+        stored.truncate(6);
+        assert!(inflate_zlib(&stored).is_err());
+        assert!(read_png(b"not a png").is_err());
+    }
+}
@@ -0,0 +1,248 @@
+use std::sync::Arc;
+
+use crate::aabb::AABB;
+use crate::hittable::HitRecord;
+use crate::hittable::Hittable;
+use crate::hittable::HittableList;
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::utils::random_percentage;
+use crate::vec::Point3;
+use crate::vec::Vec3;
+
+/// Parallelogram spanned by the edges `u` and `v` starting from the corner `q`
+#[derive(Debug)]
+pub struct Quad {
+    q: Point3,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3, // Used to find the planar coordinates of a point, `n / (n ⋅ n)`
+    normal: Vec3,
+    d: f64, // Plane constant, `normal ⋅ q`
+    area: f64,
+    material: Arc<dyn Material>,
+    bbox: AABB,
+}
+
+impl Quad {
+    pub fn new(q: Point3, u: Vec3, v: Vec3, material: Arc<dyn Material>) -> Self {
+        let n = u.cross(v);
+        let normal = n.unit();
+        let d = normal.dot(&q);
+        let w = n / n.squared_length();
+
+        // Bounding box of all four corners
+        let bbox_diagonal1 = AABB::from_points(q, q + u + v);
+        let bbox_diagonal2 = AABB::from_points(q + u, q + v);
+        let bbox = AABB::from_boxes(&bbox_diagonal1, &bbox_diagonal2).pad_to_minimum();
+
+        Self {
+            q,
+            u,
+            v,
+            w,
+            normal,
+            d,
+            area: n.length(),
+            material,
+            bbox,
+        }
+    }
+}
+
+impl Hittable for Quad {
+    /// ## Math
+    /// ### Variables
+    /// `Q` is the corner, `u` and `v` the edges and `n = u × v` the plane normal
+    /// Ray: `P(t) = O + t*d`
+    /// ### Calculation
+    /// The quad's plane is `n ⋅ P = D` with `D = n ⋅ Q`. Substituting the ray gives
+    /// `t = (D - n ⋅ O) / (n ⋅ d)`, which doesn't exist if the ray is parallel to the plane.
+    ///
+    /// The hit point is written in the plane's coordinates `p = P - Q = α*u + β*v` with
+    /// `α = w ⋅ (p × v)` and `β = w ⋅ (u × p)` where `w = n / (n ⋅ n)`.
+    ///
+    /// ### Outcomes
+    /// - The point is on the quad if both `α` and `β` are within `[0, 1]`
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let denom = self.normal.dot(&ray.dir);
+        // Ray is parallel to the plane
+        if denom.abs() < 1e-8 {
+            return None;
+        }
+
+        let t = (self.d - self.normal.dot(&ray.origin)) / denom;
+        if !ray_t.surrounds(t) {
+            return None;
+        }
+
+        let intersection = ray.at(t);
+        let planar_hit = intersection - self.q;
+        let alpha = self.w.dot(&planar_hit.cross(self.v));
+        let beta = self.w.dot(&self.u.cross(planar_hit));
+        let unit = Interval::new(0.0, 1.0);
+        if !unit.contains_closed(alpha) || !unit.contains_closed(beta) {
+            return None;
+        }
+
+        let mut hit_record = HitRecord::new(
+            intersection,
+            self.normal,
+            ray,
+            Arc::clone(&self.material),
+            t,
+        );
+        (hit_record.u, hit_record.v) = (alpha, beta);
+        (hit_record.tangent, hit_record.bitangent) = (self.u, self.v);
+        Some(hit_record)
+    }
+
+    fn boundnig_box(&self) -> &AABB {
+        &self.bbox
+    }
+
+    /// ## Math
+    /// A uniformly random point on the quad has a density of `1 / A` per unit area. Seen from
+    /// `origin` at distance `r`, the area `dA` covers the solid angle `dω = dA * cos θ / r^2`
+    /// where `θ` is the angle between the quad's normal and the direction, so the density per
+    /// unit solid angle is `r^2 / (cos θ * A)`
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        let ray = Ray::new(*origin, *direction);
+        let Some(hit_record) = self.hit(&ray, Interval::new(0.001, f64::INFINITY)) else {
+            return 0.0;
+        };
+
+        let distance_squared = hit_record.t * hit_record.t * direction.squared_length();
+        let cosine = (direction.dot(&self.normal) / direction.length()).abs();
+        distance_squared / (cosine * self.area)
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        let p = self.q + random_percentage() * self.u + random_percentage() * self.v;
+        p - *origin
+    }
+}
+
+/// Axis-aligned box between the opposite corners `a` and `b`, made of six quads sharing
+/// `material` whose normals all point outwards
+pub fn make_box(a: Point3, b: Point3, material: Arc<dyn Material>) -> HittableList {
+    let min = a.min(&b);
+    let max = a.max(&b);
+
+    let dx = Vec3::new(max.x - min.x, 0.0, 0.0);
+    let dy = Vec3::new(0.0, max.y - min.y, 0.0);
+    let dz = Vec3::new(0.0, 0.0, max.z - min.z);
+
+    // Edges are ordered so that `u × v` faces away from the box
+    let faces = [
+        (Point3::new(min.x, min.y, max.z), dx, dy),  // Front
+        (Point3::new(max.x, min.y, max.z), -dz, dy), // Right
+        (Point3::new(max.x, min.y, min.z), -dx, dy), // Back
+        (Point3::new(min.x, min.y, min.z), dz, dy),  // Left
+        (Point3::new(min.x, max.y, max.z), dx, -dz), // Top
+        (Point3::new(min.x, min.y, min.z), dx, dz),  // Bottom
+    ];
+
+    let mut sides = HittableList::new();
+    sides.reserve(faces.len());
+    for (q, u, v) in faces {
+        sides.add(Quad::new(q, u, v, Arc::clone(&material)));
+    }
+    sides
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::vec::Color3;
+
+    fn gray() -> Arc<dyn Material> {
+        Arc::new(Lambertian::new(Color3::new(0.5, 0.5, 0.5)))
+    }
+
+    fn unit_quad() -> Quad {
+        Quad::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            gray(),
+        )
+    }
+
+    #[test]
+    fn quad_is_hit_inside_and_missed_outside() {
+        let quad = unit_quad();
+        let towards_quad = |x: f64, y: f64| {
+            let ray = Ray::new(Point3::new(x, y, 2.0), Vec3::new(0.0, 0.0, -1.0));
+            quad.hit(&ray, Interval::new(0.001, f64::INFINITY))
+        };
+
+        let hit_record = towards_quad(0.5, 0.5).unwrap();
+        assert_eq!(hit_record.t, 2.0);
+        assert_eq!((hit_record.u, hit_record.v), (0.5, 0.5));
+        assert!(hit_record.is_front_face);
+
+        assert!(towards_quad(1.001, 0.5).is_none());
+        assert!(towards_quad(0.5, -0.001).is_none());
+
+        // Parallel to the plane
+        let ray = Ray::new(Point3::new(0.5, 0.5, 1.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(
+            quad.hit(&ray, Interval::new(0.001, f64::INFINITY))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn flat_quad_box_is_padded() {
+        let bbox = unit_quad().boundnig_box().clone();
+        assert_eq!((bbox.min().x, bbox.max().x), (0.0, 1.0));
+        assert_eq!((bbox.min().y, bbox.max().y), (0.0, 1.0));
+        assert!(bbox.min().z < 0.0 && bbox.max().z > 0.0);
+    }
+
+    #[test]
+    fn every_box_face_is_hit_from_outside() {
+        let cuboid = make_box(
+            Point3::new(1.0, 2.0, 3.0),
+            Point3::new(-1.0, -2.0, -3.0),
+            gray(),
+        );
+        let half = Vec3::new(1.0, 2.0, 3.0);
+        for axis in 0..3 {
+            for side in [-1.0, 1.0] {
+                let mut direction = Vec3::zero();
+                direction[axis] = side;
+                let face_center = direction * half;
+                let ray = Ray::new(face_center + 5.0 * direction, -direction);
+                let hit_record = cuboid
+                    .hit(&ray, Interval::new(0.001, f64::INFINITY))
+                    .unwrap();
+                assert!((hit_record.p - face_center).length() < 1e-9);
+                // Outward normals face the incoming ray
+                assert!(hit_record.is_front_face);
+                assert_eq!(hit_record.normal[axis], side);
+            }
+        }
+    }
+
+    #[test]
+    fn rays_skimming_past_the_box_corners_miss() {
+        let cuboid = make_box(Point3::zero(), Point3::new(1.0, 1.0, 1.0), gray());
+        let ray_t = Interval::new(0.001, f64::INFINITY);
+        for (x, y) in [
+            (-0.001, -0.001),
+            (1.001, 1.001),
+            (-0.001, 1.001),
+            (1.001, -0.001),
+        ] {
+            let ray = Ray::new(Point3::new(x, y, 3.0), Vec3::new(0.0, 0.0, -1.0));
+            assert!(cuboid.hit(&ray, ray_t.clone()).is_none());
+        }
+        // Diagonally past the corner at (1, 1, 1)
+        let ray = Ray::new(Point3::new(3.0, 1.001, 3.0), Vec3::new(-1.0, 0.0, -1.0));
+        assert!(cuboid.hit(&ray, ray_t).is_none());
+    }
+}
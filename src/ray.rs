@@ -5,7 +5,7 @@ use crate::vec::Vec3;
 pub struct Ray {
     pub origin: Point3,
     pub dir: Vec3,
-    pub tm: f64,
+    pub tm: f64, // Shutter time in [0, 1], only moving objects look at it so static ones stay sharp
 }
 
 impl Ray {
@@ -22,6 +22,6 @@ impl Ray {
     }
 
     pub fn at(&self, t: f64) -> Point3 {
-        return self.origin + t * self.dir;
+        self.origin + t * self.dir
     }
 }
@@ -0,0 +1,525 @@
+use std::fs;
+use std::io;
+use std::sync::Arc;
+
+use crate::camera::Camera;
+use crate::hittable::HittableList;
+use crate::material::Dielectric;
+use crate::material::Lambertian;
+use crate::material::Material;
+use crate::material::Metal;
+use crate::sphere::Sphere;
+use crate::vec::Vec3;
+
+/// Scene description loaded from a JSON file
+///
+/// ```json
+/// {
+///     "camera": { "image_width": 400, "look_from": [13, 2, 3], "look_at": [0, 0, 0] },
+///     "objects": [
+///         {
+///             "type": "sphere", "center": [0, 1, 0], "radius": 1,
+///             "material": { "type": "dielectric", "refraction_index": 1.5 }
+///         }
+///     ]
+/// }
+/// ```
+///
+/// Camera fields are named like the `CameraBuilder` setters, missing ones keep the builder's
+/// defaults. Materials are `lambertian` (`albedo`), `metal` (`albedo`, `fuzz`) and
+/// `dielectric` (`refraction_index`, optional `tint`).
+pub struct Scene;
+
+impl Scene {
+    pub fn load(path: &str) -> io::Result<(Camera, HittableList)> {
+        let source = fs::read_to_string(path)?;
+        Scene::parse(&source)
+    }
+
+    pub fn parse(source: &str) -> io::Result<(Camera, HittableList)> {
+        let root = JsonParser::new(source).parse_document()?;
+
+        let camera = match root.get("camera") {
+            Some(camera) => parse_camera(camera)?,
+            None => Camera::builder().build(),
+        };
+
+        let mut world = HittableList::new();
+        if let Some(objects) = root.get("objects") {
+            for object in objects.as_array("objects")? {
+                parse_object(object, &mut world)?;
+            }
+        }
+        Ok((camera, world))
+    }
+}
+
+fn parse_camera(camera: &Json) -> io::Result<Camera> {
+    let mut builder = Camera::builder();
+    // Starting from the builder's defaults, `build` panics on a shutter closing before it opens
+    // and the view direction is undefined when `look_from` and `look_at` are the same point
+    let (mut shutter_open, mut shutter_close) = (0.0, 1.0);
+    let (mut look_from, mut look_at) = (Vec3::zero(), Vec3::new(0.0, 0.0, -1.0));
+    for (key, value) in camera.as_object("camera")? {
+        builder = match key.as_str() {
+            "aspect_ratio" => {
+                let aspect_ratio = value.as_f64(key)?;
+                if aspect_ratio <= 0.0 {
+                    return Err(invalid_data("`aspect_ratio` must be positive"));
+                }
+                builder.aspect_ratio(aspect_ratio)
+            }
+            "image_width" => {
+                let image_width = value.as_usize(key)?;
+                if image_width == 0 {
+                    return Err(invalid_data("`image_width` must be at least 1"));
+                }
+                builder.image_width(image_width)
+            }
+            "samples_per_pixel" => builder.samples_per_pixel(value.as_usize(key)?),
+            "max_depth" => builder.max_depth(value.as_usize(key)?),
+            "vfov" => builder.vfov(value.as_f64(key)?),
+            "look_from" => {
+                look_from = value.as_vec3(key)?;
+                builder.look_from(look_from)
+            }
+            "look_at" => {
+                look_at = value.as_vec3(key)?;
+                builder.look_at(look_at)
+            }
+            "v_up" => builder.v_up(value.as_vec3(key)?),
+            "defocus_angle" => builder.defocus_angle(value.as_f64(key)?),
+            "focus_dist" => builder.focus_dist(value.as_f64(key)?),
+            "enable_motion_blur" => builder.enable_motion_blur(value.as_bool(key)?),
+            "shutter_open" => {
+                shutter_open = value.as_f64(key)?;
+                builder.shutter_open(shutter_open)
+            }
+            "shutter_close" => {
+                shutter_close = value.as_f64(key)?;
+                builder.shutter_close(shutter_close)
+            }
+            _ => return Err(invalid_data(&format!("unknown camera field `{}`", key))),
+        };
+    }
+    if (look_from - look_at).near_zero() {
+        return Err(invalid_data(
+            "`look_from` and `look_at` can't be the same point",
+        ));
+    }
+    if shutter_open > shutter_close {
+        return Err(invalid_data(
+            "`shutter_open` can't be after `shutter_close`",
+        ));
+    }
+    Ok(builder.build())
+}
+
+fn parse_object(object: &Json, world: &mut HittableList) -> io::Result<()> {
+    match object.field("type")?.as_str("type")? {
+        "sphere" => {
+            let center = object.field("center")?.as_vec3("center")?;
+            let radius = object.field("radius")?.as_f64("radius")?;
+            if radius < 0.0 {
+                return Err(invalid_data("sphere radius can't be negative"));
+            }
+            let material = parse_material(object.field("material")?)?;
+            world.add(Sphere::new(center, radius, material));
+        }
+        other => return Err(invalid_data(&format!("unknown object type `{}`", other))),
+    }
+    Ok(())
+}
+
+fn parse_material(material: &Json) -> io::Result<Arc<dyn Material>> {
+    let material: Arc<dyn Material> = match material.field("type")?.as_str("type")? {
+        "lambertian" => Arc::new(Lambertian::new(
+            material.field("albedo")?.as_vec3("albedo")?,
+        )),
+        "metal" => {
+            let fuzz = material.field("fuzz")?.as_f64("fuzz")?;
+            if fuzz < 0.0 {
+                return Err(invalid_data("metal fuzz can't be negative"));
+            }
+            Arc::new(Metal::new(
+                material.field("albedo")?.as_vec3("albedo")?,
+                fuzz,
+            ))
+        }
+        "dielectric" => {
+            let refraction_index = material
+                .field("refraction_index")?
+                .as_f64("refraction_index")?;
+            match material.get("tint") {
+                Some(tint) => Arc::new(Dielectric::new_colored(
+                    refraction_index,
+                    tint.as_vec3("tint")?,
+                )),
+                None => Arc::new(Dielectric::new(refraction_index)),
+            }
+        }
+        other => return Err(invalid_data(&format!("unknown material type `{}`", other))),
+    };
+    Ok(material)
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>), // Keeps the file's key order
+}
+
+impl Json {
+    fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn field(&self, key: &str) -> io::Result<&Json> {
+        self.get(key)
+            .ok_or_else(|| invalid_data(&format!("missing field `{}`", key)))
+    }
+
+    /// Numbers too large for an `f64` (e.g. `1e400`) parse to infinity and are rejected here
+    fn as_f64(&self, name: &str) -> io::Result<f64> {
+        match self {
+            Json::Number(n) if n.is_finite() => Ok(*n),
+            _ => Err(invalid_data(&format!("`{}` must be a finite number", name))),
+        }
+    }
+
+    fn as_usize(&self, name: &str) -> io::Result<usize> {
+        match self {
+            Json::Number(n) if *n >= 0.0 && n.fract() == 0.0 => Ok(*n as usize),
+            _ => Err(invalid_data(&format!(
+                "`{}` must be a non-negative integer",
+                name
+            ))),
+        }
+    }
+
+    fn as_bool(&self, name: &str) -> io::Result<bool> {
+        match self {
+            Json::Bool(b) => Ok(*b),
+            _ => Err(invalid_data(&format!("`{}` must be true or false", name))),
+        }
+    }
+
+    fn as_str(&self, name: &str) -> io::Result<&str> {
+        match self {
+            Json::String(s) => Ok(s),
+            _ => Err(invalid_data(&format!("`{}` must be a string", name))),
+        }
+    }
+
+    fn as_array(&self, name: &str) -> io::Result<&[Json]> {
+        match self {
+            Json::Array(items) => Ok(items),
+            _ => Err(invalid_data(&format!("`{}` must be an array", name))),
+        }
+    }
+
+    fn as_object(&self, name: &str) -> io::Result<&[(String, Json)]> {
+        match self {
+            Json::Object(fields) => Ok(fields),
+            _ => Err(invalid_data(&format!("`{}` must be an object", name))),
+        }
+    }
+
+    /// `[x, y, z]`
+    fn as_vec3(&self, name: &str) -> io::Result<Vec3> {
+        match self.as_array(name)? {
+            [x, y, z] => Ok(Vec3::new(x.as_f64(name)?, y.as_f64(name)?, z.as_f64(name)?)),
+            _ => Err(invalid_data(&format!("`{}` must have 3 components", name))),
+        }
+    }
+}
+
+/// Recursive descent parser for RFC 8259 JSON
+struct JsonParser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> JsonParser<'a> {
+    fn new(source: &'a str) -> Self {
+        Self {
+            bytes: source.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    fn parse_document(&mut self) -> io::Result<Json> {
+        let value = self.parse_value()?;
+        self.skip_whitespace();
+        if self.pos != self.bytes.len() {
+            return Err(self.error("trailing characters after the document"));
+        }
+        Ok(value)
+    }
+
+    fn error(&self, message: &str) -> io::Error {
+        // Report the position as a line and column, which is what editors show
+        let consumed = &self.bytes[..self.pos.min(self.bytes.len())];
+        let line = consumed.iter().filter(|&&b| b == b'\n').count() + 1;
+        let column = consumed.iter().rev().take_while(|&&b| b != b'\n').count() + 1;
+        invalid_data(&format!("line {}, column {}: {}", line, column, message))
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> io::Result<()> {
+        self.skip_whitespace();
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{}`", byte as char)))
+        }
+    }
+
+    fn parse_value(&mut self) -> io::Result<Json> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'{') => self.parse_object(),
+            Some(b'[') => self.parse_array(),
+            Some(b'"') => Ok(Json::String(self.parse_string()?)),
+            Some(b't') => self.parse_literal("true", Json::Bool(true)),
+            Some(b'f') => self.parse_literal("false", Json::Bool(false)),
+            Some(b'n') => self.parse_literal("null", Json::Null),
+            Some(b'-' | b'0'..=b'9') => self.parse_number(),
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end of file")),
+        }
+    }
+
+    fn parse_literal(&mut self, literal: &str, value: Json) -> io::Result<Json> {
+        if self.bytes[self.pos..].starts_with(literal.as_bytes()) {
+            self.pos += literal.len();
+            Ok(value)
+        } else {
+            Err(self.error("unexpected character"))
+        }
+    }
+
+    fn parse_number(&mut self) -> io::Result<Json> {
+        let start = self.pos;
+        while matches!(
+            self.peek(),
+            Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')
+        ) {
+            self.pos += 1;
+        }
+        // The scanned bytes are all ASCII
+        let text = std::str::from_utf8(&self.bytes[start..self.pos]).unwrap();
+        text.parse::<f64>()
+            .map(Json::Number)
+            .map_err(|_| self.error(&format!("invalid number `{}`", text)))
+    }
+
+    fn parse_string(&mut self) -> io::Result<String> {
+        self.expect(b'"')?;
+        let mut bytes = Vec::new();
+        loop {
+            let Some(byte) = self.peek() else {
+                return Err(self.error("unterminated string"));
+            };
+            self.pos += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let Some(escape) = self.peek() else {
+                        return Err(self.error("unterminated string"));
+                    };
+                    self.pos += 1;
+                    let c = match escape {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.parse_unicode_escape()?,
+                        _ => return Err(self.error("invalid escape sequence")),
+                    };
+                    let mut buffer = [0; 4];
+                    bytes.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+                }
+                _ => bytes.push(byte),
+            }
+        }
+        String::from_utf8(bytes).map_err(|_| self.error("string is not valid UTF-8"))
+    }
+
+    /// `\uXXXX`, surrogate pairs aren't combined and decode to U+FFFD
+    fn parse_unicode_escape(&mut self) -> io::Result<char> {
+        let hex = self
+            .bytes
+            .get(self.pos..self.pos + 4)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+            .ok_or_else(|| self.error("invalid unicode escape"))?;
+        self.pos += 4;
+        Ok(char::from_u32(hex).unwrap_or(char::REPLACEMENT_CHARACTER))
+    }
+
+    fn parse_array(&mut self) -> io::Result<Json> {
+        self.expect(b'[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(Json::Array(items));
+        }
+        loop {
+            items.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(Json::Array(items));
+                }
+                _ => return Err(self.error("expected `,` or `]`")),
+            }
+        }
+    }
+
+    fn parse_object(&mut self) -> io::Result<Json> {
+        self.expect(b'{')?;
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(Json::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.parse_string()?;
+            self.expect(b':')?;
+            fields.push((key, self.parse_value()?));
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(Json::Object(fields));
+                }
+                _ => return Err(self.error("expected `,` or `}`")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_error(source: &str) -> io::Error {
+        match Scene::parse(source) {
+            Ok(_) => panic!("`{source}` should not parse"),
+            Err(err) => err,
+        }
+    }
+
+    #[test]
+    fn parses_camera_and_objects() {
+        let (_, world) = Scene::parse(
+            r#"{
+                "camera": { "image_width": 64, "look_from": [13, 2, 3], "shutter_close": 0.5 },
+                "objects": [
+                    {
+                        "type": "sphere", "center": [0, 1, 0], "radius": 1,
+                        "material": { "type": "metal", "albedo": [0.7, 0.6, 0.5], "fuzz": 0 }
+                    },
+                    {
+                        "type": "sphere", "center": [0, -1000, 0], "radius": 1000,
+                        "material": { "type": "dielectric", "refraction_index": 1.5 }
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(world.len(), 2);
+    }
+
+    #[test]
+    fn empty_document_uses_defaults() {
+        let (_, world) = Scene::parse("{}").unwrap();
+        assert!(world.is_empty());
+    }
+
+    #[test]
+    fn rejects_malformed_json() {
+        let err = parse_error(r#"{ "objects": [ }"#);
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().starts_with("line 1, column 16"));
+    }
+
+    #[test]
+    fn rejects_negative_fuzz() {
+        let err = parse_error(
+            r#"{ "objects": [ {
+                "type": "sphere", "center": [0, 0, 0], "radius": 1,
+                "material": { "type": "metal", "albedo": [1, 1, 1], "fuzz": -0.1 }
+            } ] }"#,
+        );
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn rejects_shutter_closing_before_opening() {
+        let err = parse_error(r#"{ "camera": { "shutter_open": 2 } }"#);
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = parse_error(r#"{ "camera": { "shutter_open": 0.5, "shutter_close": 0.2 } }"#);
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn rejects_wrong_field_types() {
+        for source in [
+            r#"{ "camera": { "image_width": -1 } }"#,
+            r#"{ "camera": { "look_at": [0, 1] } }"#,
+            r#"{ "camera": { "zoom": 2 } }"#,
+            r#"{ "objects": [ { "type": "cube" } ] }"#,
+            r#"{ "objects": [ { "type": "sphere", "center": [0, 0, 0], "radius": 1 } ] }"#,
+        ] {
+            assert_eq!(parse_error(source).kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn rejects_degenerate_cameras() {
+        for source in [
+            r#"{ "camera": { "aspect_ratio": 0, "image_width": 4 } }"#,
+            r#"{ "camera": { "aspect_ratio": -1.5 } }"#,
+            r#"{ "camera": { "aspect_ratio": 1e400 } }"#,
+            r#"{ "camera": { "vfov": -1e400 } }"#,
+            r#"{ "camera": { "image_width": 0 } }"#,
+            r#"{ "camera": { "look_from": [1, 2, 3], "look_at": [1, 2, 3] } }"#,
+            r#"{ "camera": { "look_at": [0, 0, 0] } }"#,
+        ] {
+            assert_eq!(parse_error(source).kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
@@ -0,0 +1,329 @@
+use std::sync::Arc;
+
+use crate::camera::Camera;
+use crate::camera::CameraBuilder;
+use crate::hittable::HittableList;
+use crate::material::Dielectric;
+use crate::material::DiffuseLight;
+use crate::material::Lambertian;
+use crate::material::Material;
+use crate::material::Metal;
+use crate::quad::Quad;
+use crate::quad::make_box;
+use crate::sphere::Sphere;
+use crate::texture::CheckerTexture;
+use crate::texture::ImageTexture;
+use crate::transform::RotateY;
+use crate::transform::Translate;
+use crate::utils::random_f64;
+use crate::utils::random_percentage;
+use crate::vec::Color3;
+use crate::vec::Point3;
+use crate::vec::Vec3;
+
+/// Names accepted by `by_name`, in the order they were added
+pub const NAMES: [&str; 5] = [
+    "bouncing_spheres",
+    "two_spheres",
+    "earth",
+    "cornell_box",
+    "earth_moon",
+];
+
+/// Builds the preset called `name`, see `NAMES`
+///
+/// `camera` carries the image settings (width, samples, depth...) and the preset sets up the
+/// view on top of it.
+pub fn by_name(name: &str, camera: CameraBuilder) -> Option<(Camera, HittableList)> {
+    match name {
+        "bouncing_spheres" => Some(bouncing_spheres(camera)),
+        "two_spheres" => Some(two_spheres(camera)),
+        "earth" => Some(earth(camera)),
+        "cornell_box" => Some(cornell_box(camera)),
+        "earth_moon" => Some(earth_moon(camera)),
+        _ => None,
+    }
+}
+
+/// Book cover: a field of small random spheres, some of them bouncing, around three big ones
+pub fn bouncing_spheres(camera: CameraBuilder) -> (Camera, HittableList) {
+    let camera = camera
+        .aspect_ratio(16.0 / 9.0)
+        .vfov(20.0)
+        .look_from(Point3::new(13.0, 2.0, 3.0))
+        .look_at(Point3::new(0.0, 0.0, 0.0))
+        .defocus_angle(0.6)
+        .focus_dist(10.0)
+        .enable_motion_blur(true)
+        .build();
+
+    let mut world = HittableList::new();
+
+    let m_ground = Arc::new(Lambertian::new(Color3::new(0.5, 0.5, 0.5)));
+    world.add(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        m_ground,
+    ));
+
+    for a in -11..11 {
+        for b in -11..11 {
+            let a = a as f64;
+            let b = b as f64;
+            let m = random_percentage();
+            let center = Point3::new(
+                a + 0.9 * random_percentage(),
+                0.2,
+                b + 0.9 * random_percentage(),
+            );
+
+            if (center - Point3::new(4.0, 0.2, 0.0)).length() > 0.9 {
+                if m < 0.8 {
+                    // Make it bounce at time t=1
+                    let center_t1 = center + Point3::new(0.0, random_percentage() * 0.2, 0.0);
+                    // diffuse
+                    let albedo = Color3::random() * Color3::random();
+                    let mat = Arc::new(Lambertian::new(albedo));
+                    world.add(Sphere::new_moving(center, center_t1, 0.2, mat));
+                } else if m < 0.95 {
+                    // metal
+                    let r = random_f64(0.5, 1.0);
+                    let albedo = Color3::new(r, r, r);
+                    let fuzz = random_f64(0.0, 0.5);
+                    let mat = Arc::new(Metal::new(albedo, fuzz));
+                    world.add(Sphere::new(center, 0.2, mat));
+                } else {
+                    // glass
+                    let mat = Arc::new(Dielectric::new(1.5));
+                    world.add(Sphere::new(center, 0.2, mat));
+                }
+            }
+        }
+    }
+    let material1 = Arc::new(Dielectric::new(1.5));
+    world.add(Sphere::new(Point3::new(0.0, 1.0, 0.0), 1.0, material1));
+
+    let material2 = Arc::new(Lambertian::new(Color3::new(0.4, 0.2, 0.1)));
+    world.add(Sphere::new(Point3::new(-4.0, 1.0, 0.0), 1.0, material2));
+
+    let material3 = Arc::new(Metal::new(Color3::new(0.7, 0.6, 0.5), 0.0));
+    world.add(Sphere::new(Point3::new(4.0, 1.0, 0.0), 1.0, material3));
+
+    (camera, world)
+}
+
+/// Two big checkered spheres stacked on top of each other
+pub fn two_spheres(camera: CameraBuilder) -> (Camera, HittableList) {
+    let camera = camera
+        .aspect_ratio(16.0 / 9.0)
+        .vfov(20.0)
+        .look_from(Point3::new(13.0, 2.0, 3.0))
+        .look_at(Point3::new(0.0, 0.0, 0.0))
+        .defocus_angle(0.0)
+        .build();
+
+    let mut world = HittableList::new();
+    let checker: Arc<dyn Material> = Arc::new(Lambertian::from_texture(Arc::new(
+        CheckerTexture::from_colors(0.32, Color3::new(0.2, 0.3, 0.1), Color3::new(0.9, 0.9, 0.9)),
+    )));
+    world.add(Sphere::new(
+        Point3::new(0.0, -10.0, 0.0),
+        10.0,
+        Arc::clone(&checker),
+    ));
+    world.add(Sphere::new(Point3::new(0.0, 10.0, 0.0), 10.0, checker));
+
+    (camera, world)
+}
+
+/// Globe textured with `earthmap.png` from the working directory, which renders as a
+/// checkerboard if the file is missing
+pub fn earth(camera: CameraBuilder) -> (Camera, HittableList) {
+    let camera = camera
+        .aspect_ratio(16.0 / 9.0)
+        .vfov(20.0)
+        .look_from(Point3::new(0.0, 0.0, 12.0))
+        .look_at(Point3::new(0.0, 0.0, 0.0))
+        .defocus_angle(0.0)
+        .build();
+
+    let mut world = HittableList::new();
+    let earth_surface = Arc::new(Lambertian::from_texture(Arc::new(ImageTexture::new(
+        "earthmap.png",
+    ))));
+    world.add(Sphere::new(Point3::zero(), 2.0, earth_surface));
+
+    (camera, world)
+}
+
+/// Cornell box: a closed room with a red and a green wall, a ceiling light and two boxes.
+/// Scattered rays are aimed at the light, see `Camera::set_light_shapes`.
+pub fn cornell_box(camera: CameraBuilder) -> (Camera, HittableList) {
+    let mut camera = camera
+        .aspect_ratio(1.0)
+        .vfov(40.0)
+        .look_from(Point3::new(278.0, 278.0, -800.0))
+        .look_at(Point3::new(278.0, 278.0, 0.0))
+        .defocus_angle(0.0)
+        .build();
+    camera.set_background(Color3::zero());
+
+    let red = Arc::new(Lambertian::new(Color3::new(0.65, 0.05, 0.05)));
+    let white: Arc<dyn Material> = Arc::new(Lambertian::new(Color3::new(0.73, 0.73, 0.73)));
+    let green = Arc::new(Lambertian::new(Color3::new(0.12, 0.45, 0.15)));
+    let light: Arc<dyn Material> = Arc::new(DiffuseLight::new(Color3::new(15.0, 15.0, 15.0)));
+
+    // Every wall faces the inside of the room
+    let mut world = HittableList::new();
+    world.add(Quad::new(
+        Point3::new(555.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 555.0),
+        Vec3::new(0.0, 555.0, 0.0),
+        green,
+    ));
+    world.add(Quad::new(
+        Point3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 555.0, 0.0),
+        Vec3::new(0.0, 0.0, 555.0),
+        red,
+    ));
+    world.add(Quad::new(
+        Point3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 555.0),
+        Vec3::new(555.0, 0.0, 0.0),
+        Arc::clone(&white),
+    ));
+    world.add(Quad::new(
+        Point3::new(555.0, 555.0, 555.0),
+        Vec3::new(-555.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, -555.0),
+        Arc::clone(&white),
+    ));
+    world.add(Quad::new(
+        Point3::new(0.0, 0.0, 555.0),
+        Vec3::new(0.0, 555.0, 0.0),
+        Vec3::new(555.0, 0.0, 0.0),
+        Arc::clone(&white),
+    ));
+
+    let mut lights = HittableList::new();
+    lights.add(Quad::new(
+        Point3::new(343.0, 554.0, 332.0),
+        Vec3::new(-130.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, -105.0),
+        Arc::clone(&light),
+    ));
+    world.add(Quad::new(
+        Point3::new(343.0, 554.0, 332.0),
+        Vec3::new(-130.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, -105.0),
+        light,
+    ));
+    camera.set_light_shapes(Arc::new(lights));
+
+    let tall_box = make_box(
+        Point3::new(0.0, 0.0, 0.0),
+        Point3::new(165.0, 330.0, 165.0),
+        Arc::clone(&white),
+    );
+    let tall_box = RotateY::new(Arc::new(tall_box), 15.0);
+    world.add(Translate::new(
+        Arc::new(tall_box),
+        Vec3::new(265.0, 0.0, 295.0),
+    ));
+
+    let short_box = make_box(
+        Point3::new(0.0, 0.0, 0.0),
+        Point3::new(165.0, 165.0, 165.0),
+        white,
+    );
+    let short_box = RotateY::new(Arc::new(short_box), -18.0);
+    world.add(Translate::new(
+        Arc::new(short_box),
+        Vec3::new(130.0, 0.0, 65.0),
+    ));
+
+    (camera, world)
+}
+
+/// Sun-lit `earth` with a moon passing in front of the sun, its shadow falls on the globe.
+/// The sun is a distant emissive sphere which scattered rays are aimed at, in an otherwise
+/// black sky.
+pub fn earth_moon(camera: CameraBuilder) -> (Camera, HittableList) {
+    let mut camera = camera
+        .aspect_ratio(16.0 / 9.0)
+        .vfov(30.0)
+        .look_from(Point3::new(9.0, 3.0, 9.0))
+        .look_at(Point3::new(1.0, 0.3, 0.0))
+        .defocus_angle(0.0)
+        .build();
+    camera.set_background(Color3::zero());
+
+    let mut world = HittableList::new();
+    let earth_surface = Arc::new(Lambertian::from_texture(Arc::new(ImageTexture::new(
+        "earthmap.png",
+    ))));
+    world.add(Sphere::new(Point3::zero(), 2.0, earth_surface));
+
+    // On the line from the sun to the earth's center
+    let moon_surface = Arc::new(Lambertian::new(Color3::new(0.6, 0.6, 0.6)));
+    world.add(Sphere::new(Point3::new(5.0, 1.0, 0.0), 0.54, moon_surface));
+
+    let sun: Arc<Sphere> = Arc::new(Sphere::new(
+        Point3::new(40.0, 8.0, 0.0),
+        4.0,
+        Arc::new(DiffuseLight::new(Color3::new(20.0, 19.0, 17.0))),
+    ));
+    world.add_shared(sun.clone());
+    camera.set_light_shapes(sun);
+
+    (camera, world)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::Hittable;
+    use crate::interval::Interval;
+    use crate::ray::Ray;
+
+    #[test]
+    fn every_name_builds() {
+        for name in NAMES {
+            assert!(by_name(name, Camera::builder()).is_some(), "{name}");
+        }
+        assert!(by_name("moon_earth", Camera::builder()).is_none());
+    }
+
+    #[test]
+    fn two_spheres_has_two_objects_and_the_preset_camera() {
+        let (camera, world) = two_spheres(Camera::builder().vfov(90.0));
+        assert_eq!(world.len(), 2);
+        assert_eq!(camera.vfov(), 20.0);
+        let center = camera.center();
+        assert_eq!((center.x, center.y, center.z), (13.0, 2.0, 3.0));
+    }
+
+    #[test]
+    fn earth_moon_has_a_sun_and_a_moon_shadow() {
+        let (_, world) = earth_moon(Camera::builder());
+        assert_eq!(world.len(), 3);
+
+        // The point of the earth facing the sun looks straight at the moon
+        let sun_center = Point3::new(40.0, 8.0, 0.0);
+        let shadowed = 2.0 * sun_center.unit();
+        let to_sun = Ray::new(shadowed, sun_center - shadowed);
+        let hit_record = world.hit(&to_sun, Interval::new(0.001, 1.0)).unwrap();
+        let moon_center = Point3::new(5.0, 1.0, 0.0);
+        assert!(((hit_record.p - moon_center).length() - 0.54).abs() < 1e-9);
+
+        // A point on the far side of the earth from the moon sees the sun
+        let lit = Point3::new(0.0, 2.0, 0.0);
+        let to_sun = Ray::new(lit, sun_center - lit);
+        let hit_record = world.hit(&to_sun, Interval::new(0.001, 1.0)).unwrap();
+        let emitted = hit_record
+            .material
+            .emitted(hit_record.u, hit_record.v, &hit_record.p);
+        assert!(emitted.x > 1.0);
+    }
+}
@@ -1,3 +1,4 @@
+use std::f64::consts::PI;
 use std::sync::Arc;
 
 use crate::aabb::AABB;
@@ -5,13 +6,15 @@ use crate::hittable::HitRecord;
 use crate::hittable::Hittable;
 use crate::interval::Interval;
 use crate::material::Material;
+use crate::onb::Onb;
 use crate::ray::Ray;
+use crate::utils::random_on_sphere_cap;
 use crate::vec::Point3;
 use crate::vec::Vec3;
 
 #[derive(Debug)]
 pub struct Sphere {
-    center: Ray,
+    center: Ray, // Center path over the shutter, a static sphere has a zero direction so `ray.tm` has no effect
     radius: f64,
     material: Arc<dyn Material>,
     bbox: AABB,
@@ -50,6 +53,42 @@ impl Sphere {
     }
 }
 
+impl Sphere {
+    /// Surface coordinates of a point `p` on the unit sphere centered at the origin
+    ///
+    /// `u` is the angle around the Y axis from X=-1, `v` the angle from Y=-1 to Y=+1,
+    /// both normalized to `[0, 1]`:
+    /// `theta = acos(-p.y)`, `phi = atan2(-p.z, p.x) + π`, `u = phi / 2π`, `v = theta / π`
+    pub(crate) fn get_uv(p: &Point3) -> (f64, f64) {
+        let theta = (-p.y).acos();
+        let phi = (-p.z).atan2(p.x) + PI;
+        (phi / (2.0 * PI), theta / PI)
+    }
+
+    /// Directions in which `u` and `v` of `get_uv` increase at the point `p` of the unit sphere,
+    /// both zero at the poles
+    ///
+    /// With `p = (-cos(phi) sin(theta), -cos(theta), sin(phi) sin(theta))`, `dp/dphi` is
+    /// `(p.z, 0, -p.x)` and `dp/dtheta * sin(theta)` is `(-p.x p.y, p.x^2 + p.z^2, -p.z p.y)`
+    pub(crate) fn get_uv_directions(p: &Point3) -> (Vec3, Vec3) {
+        (
+            Vec3::new(p.z, 0.0, -p.x),
+            Vec3::new(-p.x * p.y, p.x * p.x + p.z * p.z, -p.z * p.y),
+        )
+    }
+
+    /// Hit record for the root `t` of `ray`, with the sphere centered at `center` at that time
+    fn hit_record(&self, ray: &Ray, t: f64, center: Point3) -> HitRecord {
+        let hit_point = ray.at(t);
+        // This normal will always point outward
+        let normal = (hit_point - center) / self.radius; // division by radius will make it a unit vector
+        let mut hit_record = HitRecord::new(hit_point, normal, ray, Arc::clone(&self.material), t);
+        (hit_record.u, hit_record.v) = Sphere::get_uv(&normal);
+        (hit_record.tangent, hit_record.bitangent) = Sphere::get_uv_directions(&normal);
+        hit_record
+    }
+}
+
 impl Hittable for Sphere {
     /// ## Math
     /// ### Variables
@@ -77,7 +116,8 @@ impl Hittable for Sphere {
     /// ### Outcomes
     /// - If there are 0 roots, then the ray does not intersect the sphere
     /// - If there is 1 root, then the ray is a tangent to the surface of the sphere
-    /// - If there are 2 roots, then the ray passes through the sphere
+    /// - If there are 2 roots, then the ray passes through the sphere. The nearest one within
+    ///   `ray_t` is the hit, which is the far one for rays starting inside the sphere.
     fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord> {
         let current_center = self.center.at(ray.tm); // Get the current center of the shpere given ray position
 
@@ -92,24 +132,142 @@ impl Hittable for Sphere {
             return None;
         }
 
-        let root = (h - discriminant.sqrt()) / a; // Get the minimum root
+        // Try the near root first, rays starting inside the sphere only hit the far one
+        let sqrtd = discriminant.sqrt();
+        let mut root = (h - sqrtd) / a;
         if !ray_t.surrounds(root) {
-            return None;
+            root = (h + sqrtd) / a;
+            if !ray_t.surrounds(root) {
+                return None;
+            }
         }
 
-        let hit_point = ray.at(root);
-        // This normal will always point outward
-        let normal = (hit_point - current_center) / self.radius; // division by radius will make it a unit vector
-        Some(HitRecord::new(
-            hit_point,
-            normal,
-            ray,
-            Arc::clone(&self.material),
-            root,
-        ))
+        Some(self.hit_record(ray, root, current_center))
     }
 
     fn boundnig_box(&self) -> &AABB {
         &self.bbox
     }
+
+    /// Both roots, the entry and the exit, when they are within `ray_t`
+    fn hit_all(&self, ray: &Ray, ray_t: Interval) -> Vec<HitRecord> {
+        let current_center = self.center.at(ray.tm);
+        let oc = current_center - ray.origin;
+        let a = ray.dir.squared_length();
+        let h = ray.dir.dot(&oc);
+        let c = oc.squared_length() - self.radius.powi(2);
+        let discriminant = h * h - a * c;
+        if discriminant < 0.0 {
+            return Vec::new();
+        }
+
+        let sqrtd = discriminant.sqrt();
+        [(h - sqrtd) / a, (h + sqrtd) / a]
+            .into_iter()
+            .filter(|root| ray_t.surrounds(*root))
+            .map(|root| self.hit_record(ray, root, current_center))
+            .collect()
+    }
+
+    /// Same root tests as `hit`, without computing the hit point, normal or UV
+    fn occluded(&self, ray: &Ray, ray_t: Interval) -> bool {
+        let oc = self.center.at(ray.tm) - ray.origin;
+        let a = ray.dir.squared_length();
+        let h = ray.dir.dot(&oc);
+        let c = oc.squared_length() - self.radius.powi(2);
+        let discriminant = h * h - a * c;
+
+        if discriminant < 0.0 {
+            return false;
+        }
+        let sqrtd = discriminant.sqrt();
+        ray_t.surrounds((h - sqrtd) / a) || ray_t.surrounds((h + sqrtd) / a)
+    }
+
+    /// ## Math
+    /// Directions are sampled uniformly over the cone of directions from `origin` that hit the
+    /// sphere. At distance `d` from the center, its half-angle `θ_max` has
+    /// `cos θ_max = sqrt(1 - r^2 / d^2)` and it covers the solid angle `2π(1 - cos θ_max)`.
+    ///
+    /// Only static spheres are sampled, the cone is taken at time 0.
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        let ray = Ray::new(*origin, *direction);
+        if !self.occluded(&ray, Interval::new(0.001, f64::INFINITY)) {
+            return 0.0;
+        }
+
+        let distance_squared = (self.center.at(0.0) - *origin).squared_length();
+        let cos_theta_max = (1.0 - self.radius * self.radius / distance_squared)
+            .max(0.0)
+            .sqrt();
+        let solid_angle = 2.0 * PI * (1.0 - cos_theta_max);
+        1.0 / solid_angle
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        let direction = self.center.at(0.0) - *origin;
+        let cos_theta_max = (1.0 - self.radius * self.radius / direction.squared_length())
+            .max(0.0)
+            .sqrt();
+        Onb::new(&direction).transform(&random_on_sphere_cap(cos_theta_max))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::vec::Color3;
+
+    fn assert_uv(p: Point3, expected: (f64, f64)) {
+        let (u, v) = Sphere::get_uv(&p);
+        assert!(
+            (u - expected.0).abs() < 1e-12 && (v - expected.1).abs() < 1e-12,
+            "uv of {:?} is ({}, {}), expected {:?}",
+            p,
+            u,
+            v,
+            expected
+        );
+    }
+
+    #[test]
+    fn uv_at_the_poles_and_the_equator() {
+        assert_uv(Point3::new(1.0, 0.0, 0.0), (0.5, 0.5));
+        assert_uv(Point3::new(0.0, 1.0, 0.0), (0.5, 1.0));
+        assert_uv(Point3::new(0.0, -1.0, 0.0), (0.5, 0.0));
+        assert_uv(Point3::new(0.0, 0.0, 1.0), (0.25, 0.5));
+        assert_uv(Point3::new(0.0, 0.0, -1.0), (0.75, 0.5));
+    }
+
+    #[test]
+    fn hits_report_the_uv_of_the_hit_point() {
+        let material = Arc::new(Lambertian::new(Color3::new(0.5, 0.5, 0.5)));
+        let sphere = Sphere::new(Point3::new(0.0, 0.0, -3.0), 2.0, material);
+        // Straight down onto the top of the sphere
+        let ray = Ray::new(Point3::new(0.0, 5.0, -3.0), Vec3::new(0.0, -1.0, 0.0));
+        let hit_record = sphere
+            .hit(&ray, Interval::new(0.001, f64::INFINITY))
+            .unwrap();
+        assert_eq!(hit_record.t, 3.0);
+        assert!((hit_record.v - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn rays_from_the_center_hit_the_far_wall() {
+        let material = Arc::new(Lambertian::new(Color3::new(0.5, 0.5, 0.5)));
+        let sphere = Sphere::new(Point3::new(1.0, 0.0, 0.0), 2.0, material);
+        // The near root is behind the origin, only the far one is left
+        let ray = Ray::new(Point3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        let hit_record = sphere
+            .hit(&ray, Interval::new(0.001, f64::INFINITY))
+            .unwrap();
+
+        assert_eq!(hit_record.t, 2.0);
+        assert_eq!(
+            (hit_record.p.x, hit_record.p.y, hit_record.p.z),
+            (1.0, 0.0, 2.0)
+        );
+        assert!(!hit_record.is_front_face);
+    }
 }
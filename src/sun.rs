@@ -0,0 +1,71 @@
+use std::f64::consts::PI;
+
+use crate::onb::Onb;
+use crate::pdf::Pdf;
+use crate::utils::random_on_sphere_cap;
+use crate::vec::Color3;
+use crate::vec::Vec3;
+
+/// Distant directional light seen as a small disk in the sky
+#[derive(Debug, Clone, Copy)]
+pub struct SunLight {
+    direction: Vec3,   // Unit vector pointing towards the sun
+    cos_radius: f64,   // Cosine of the angular radius of the disk
+    intensity: Color3, // Radiance of the disk
+}
+
+impl SunLight {
+    /// `angular_diameter` is in degrees, the real sun is about 0.53
+    pub fn new(direction: Vec3, angular_diameter: f64, intensity: Color3) -> Self {
+        assert!(angular_diameter > 0.0 && angular_diameter < 180.0);
+        Self {
+            direction: direction.unit(),
+            cos_radius: (angular_diameter / 2.0).to_radians().cos(),
+            intensity,
+        }
+    }
+
+    pub fn direction(&self) -> Vec3 {
+        self.direction
+    }
+
+    /// Whether a ray going towards `dir` looks at the sun disk
+    pub fn contains(&self, dir: &Vec3) -> bool {
+        dir.unit().dot(&self.direction) >= self.cos_radius
+    }
+
+    /// Radiance carried by a ray missing every object in direction `dir`
+    pub fn radiance(&self, dir: &Vec3) -> Color3 {
+        if self.contains(dir) {
+            self.intensity
+        } else {
+            Color3::zero()
+        }
+    }
+
+    /// Direction uniformly distributed over the sun disk, see `random_on_sphere_cap`
+    pub fn sample_direction(&self) -> Vec3 {
+        // The cap is around +Z, rotate it onto the sun direction
+        Onb::new(&self.direction).transform(&random_on_sphere_cap(self.cos_radius))
+    }
+
+    /// Probability density of `sample_direction` returning `dir`, per unit solid angle
+    pub fn pdf(&self, dir: &Vec3) -> f64 {
+        if self.contains(dir) {
+            1.0 / (2.0 * PI * (1.0 - self.cos_radius))
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Lets scattered rays be importance sampled towards the sun, see `Camera::set_sun`
+impl Pdf for SunLight {
+    fn value(&self, dir: &Vec3) -> f64 {
+        self.pdf(dir)
+    }
+
+    fn generate(&self) -> Vec3 {
+        self.sample_direction()
+    }
+}
@@ -0,0 +1,558 @@
+use std::f64::consts::PI;
+use std::fmt::Debug;
+use std::fs;
+use std::io;
+use std::sync::Arc;
+
+use crate::aabb::AABB;
+use crate::image::load_hdr;
+use crate::image::load_rgb8;
+use crate::perlin::Perlin;
+use crate::vec::Color3;
+use crate::vec::Point3;
+use crate::vec::Vec3;
+
+pub trait Texture: Debug + Send + Sync {
+    /// Color of the texture at the surface coordinates `u`, `v` of the hit point `p`
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color3;
+}
+
+#[derive(Debug)]
+pub struct SolidColor {
+    albedo: Color3,
+}
+
+impl SolidColor {
+    pub fn new(albedo: Color3) -> Self {
+        Self { albedo }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f64, _v: f64, _p: &Point3) -> Color3 {
+        self.albedo
+    }
+}
+
+/// 3D checker pattern alternating between two textures every `scale` units along each axis
+#[derive(Debug)]
+pub struct CheckerTexture {
+    inv_scale: f64,
+    even: Arc<dyn Texture>,
+    odd: Arc<dyn Texture>,
+}
+
+impl CheckerTexture {
+    pub fn new(scale: f64, even: Arc<dyn Texture>, odd: Arc<dyn Texture>) -> Self {
+        Self {
+            inv_scale: 1.0 / scale,
+            even,
+            odd,
+        }
+    }
+
+    pub fn from_colors(scale: f64, even: Color3, odd: Color3) -> Self {
+        Self::new(
+            scale,
+            Arc::new(SolidColor::new(even)),
+            Arc::new(SolidColor::new(odd)),
+        )
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color3 {
+        let x = (self.inv_scale * p.x).floor() as i64;
+        let y = (self.inv_scale * p.y).floor() as i64;
+        let z = (self.inv_scale * p.z).floor() as i64;
+
+        if (x + y + z).rem_euclid(2) == 0 {
+            self.even.value(u, v, p)
+        } else {
+            self.odd.value(u, v, p)
+        }
+    }
+}
+
+/// Marble-like pattern: a sine wave along Z whose phase is distorted by Perlin turbulence
+#[derive(Debug)]
+pub struct NoiseTexture {
+    scale: f64,
+    noise: Perlin,
+}
+
+impl NoiseTexture {
+    /// The noise tables are drawn from the calling thread's random stream, so a scene built
+    /// after `init_rng` gets the same pattern on every run
+    pub fn new(scale: f64) -> Self {
+        Self {
+            scale,
+            noise: Perlin::new(),
+        }
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _u: f64, _v: f64, p: &Point3) -> Color3 {
+        let phase = self.scale * p.z + 10.0 * self.noise.turbulence(p, 7);
+        Color3::new(0.5, 0.5, 0.5) * (1.0 + phase.sin())
+    }
+}
+
+/// Normal map of ripples running along both `u` and `v`, for use with `NormalMapped`
+///
+/// ## Math
+/// The surface height is `h = a * (sin(2π*f*u) + sin(2π*f*v))`, tilting the tangent space
+/// normal to `(-∂h/∂u, -∂h/∂v, 1)`. The unit normal `n` is encoded as the color `(n + 1) / 2`.
+#[derive(Debug)]
+pub struct WaveNormalMap {
+    frequency: f64, // Ripples per unit of `u` and `v`
+    amplitude: f64,
+}
+
+impl WaveNormalMap {
+    pub fn new(frequency: f64, amplitude: f64) -> Self {
+        Self {
+            frequency,
+            amplitude,
+        }
+    }
+}
+
+impl Texture for WaveNormalMap {
+    fn value(&self, u: f64, v: f64, _p: &Point3) -> Color3 {
+        let k = 2.0 * PI * self.frequency;
+        let dh_du = self.amplitude * k * (k * u).cos();
+        let dh_dv = self.amplitude * k * (k * v).cos();
+        let normal = Vec3::new(-dh_du, -dh_dv, 1.0).unit();
+        0.5 * (normal + Color3::new(1.0, 1.0, 1.0))
+    }
+}
+
+/// How an `ImageTexture` is sampled between texel centers
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    /// Color of the texel the UV falls in
+    #[default]
+    Nearest,
+    /// Weighted average of the four closest texels, clamped at the borders
+    Bilinear,
+}
+
+/// Texture backed by an image file, mapped over the `[0, 1]` UV square with `v` pointing up
+#[derive(Debug)]
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color3>, // Linear colors, row-major from the top row
+    filter: Filter,
+}
+
+impl ImageTexture {
+    /// Loads the image at `path`, a Radiance `.hdr` file or any format `image::load_rgb8`
+    /// supports. If it can't be loaded a warning is printed and the texture renders as the
+    /// `missing` checkerboard.
+    pub fn new(path: &str) -> Self {
+        // Radiance HDR files are already linear and may go above 1.0
+        let texture = if path.to_ascii_lowercase().ends_with(".hdr") {
+            load_hdr(path).map(|(width, height, pixels)| Self::from_pixels(width, height, pixels))
+        } else {
+            load_rgb8(path).map(|(width, height, bytes)| Self::from_rgb8(width, height, &bytes))
+        };
+        texture.unwrap_or_else(|error| {
+            eprintln!(
+                "Warning: failed to load texture '{}': {}, using a checkerboard",
+                path, error
+            );
+            Self::missing()
+        })
+    }
+
+    /// Magenta and black checkerboard of 8 × 8 squares, stands in for textures that failed to
+    /// load so they're obvious in the render
+    pub fn missing() -> Self {
+        const SQUARES: usize = 8;
+        let pixels = (0..SQUARES * SQUARES)
+            .map(|index| {
+                if (index / SQUARES + index % SQUARES).is_multiple_of(2) {
+                    Color3::new(1.0, 0.0, 1.0)
+                } else {
+                    Color3::zero()
+                }
+            })
+            .collect();
+        Self::from_pixels(SQUARES, SQUARES, pixels)
+    }
+
+    /// Builds a texture from gamma-encoded 8-bit RGB, converting it to linear colors
+    pub fn from_rgb8(width: usize, height: usize, bytes: &[u8]) -> Self {
+        // Images are assumed to be encoded with the same gamma 2 curve `Color3::to_bytes` uses
+        let to_linear = |byte: u8| (byte as f64 / 255.0).powi(2);
+        let pixels = bytes
+            .chunks(3)
+            .map(|rgb| Color3::new(to_linear(rgb[0]), to_linear(rgb[1]), to_linear(rgb[2])))
+            .collect();
+        Self::from_pixels(width, height, pixels)
+    }
+
+    /// Builds a texture from linear colors, row-major from the top row
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<Color3>) -> Self {
+        assert_eq!(pixels.len(), width * height);
+        Self {
+            width,
+            height,
+            pixels,
+            filter: Filter::default(),
+        }
+    }
+
+    pub fn set_filter(&mut self, filter: Filter) {
+        self.filter = filter;
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Texel at column `i` and row `j`, clamped to the image borders
+    fn texel(&self, i: i64, j: i64) -> Color3 {
+        let i = i.clamp(0, self.width as i64 - 1) as usize;
+        let j = j.clamp(0, self.height as i64 - 1) as usize;
+        self.pixels[j * self.width + i]
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: &Point3) -> Color3 {
+        // Make empty textures obvious, same color as the `missing` checkerboard
+        if self.pixels.is_empty() {
+            return Color3::new(1.0, 0.0, 1.0);
+        }
+
+        // Clamp to the image and flip V since rows are stored from the top
+        let u = u.clamp(0.0, 1.0);
+        let v = 1.0 - v.clamp(0.0, 1.0);
+
+        match self.filter {
+            Filter::Nearest => {
+                let i = ((u * self.width as f64) as usize).min(self.width - 1);
+                let j = ((v * self.height as f64) as usize).min(self.height - 1);
+                self.texel(i as i64, j as i64)
+            }
+            Filter::Bilinear => {
+                // Texel centers sit at half-integer coordinates
+                let x = u * self.width as f64 - 0.5;
+                let y = v * self.height as f64 - 0.5;
+                let (i, j) = (x.floor() as i64, y.floor() as i64);
+                let (fx, fy) = (x - x.floor(), y - y.floor());
+
+                let top = Color3::lerp(&self.texel(i, j), &self.texel(i + 1, j), fx);
+                let bottom = Color3::lerp(&self.texel(i, j + 1), &self.texel(i + 1, j + 1), fx);
+                Color3::lerp(&top, &bottom, fy)
+            }
+        }
+    }
+}
+
+/// Evaluates `texture` once per texel over the `[0, 1]` UV square and stores the result, so
+/// expensive procedural textures cost a lookup per ray instead. The baked texture samples
+/// bilinearly.
+///
+/// Textures which depend on the hit point rather than the UV (`NoiseTexture`,
+/// `CheckerTexture`) are evaluated at `p = (u, v, 0)`.
+pub fn bake_texture(texture: &dyn Texture, width: usize, height: usize) -> ImageTexture {
+    assert!(width > 0 && height > 0);
+    let mut pixels = Vec::with_capacity(width * height);
+    // Sample texel centers, rows go from the top (`v = 1`) down like `ImageTexture` stores them
+    for j in 0..height {
+        let v = 1.0 - (j as f64 + 0.5) / height as f64;
+        for i in 0..width {
+            let u = (i as f64 + 0.5) / width as f64;
+            pixels.push(texture.value(u, v, &Point3::new(u, v, 0.0)));
+        }
+    }
+
+    let mut baked = ImageTexture::from_pixels(width, height, pixels);
+    baked.set_filter(Filter::Bilinear);
+    baked
+}
+
+/// 3D grid of densities (e.g. a baked smoke simulation) stretched over `bounds`, sampled with
+/// trilinear interpolation. The density is returned as gray and is 0 outside of `bounds`.
+///
+/// Meant to drive the local density of a `ConstantMedium`, see
+/// `ConstantMedium::with_density_texture`.
+#[derive(Debug)]
+pub struct VoxelTexture {
+    dims: [usize; 3],
+    densities: Vec<f64>, // x varying fastest, then y, then z
+    bounds: AABB,
+}
+
+impl VoxelTexture {
+    pub fn new(dims: [usize; 3], densities: Vec<f64>, bounds: AABB) -> Self {
+        assert!(dims.iter().all(|&n| n > 0));
+        assert_eq!(densities.len(), dims[0] * dims[1] * dims[2]);
+        Self {
+            dims,
+            densities,
+            bounds,
+        }
+    }
+
+    /// Loads a raw grid: the dimensions `nx`, `ny`, `nz` as little-endian `u32`s followed by
+    /// `nx * ny * nz` little-endian `f32` densities, x varying fastest, then y, then z
+    pub fn load(path: &str, bounds: AABB) -> io::Result<Self> {
+        Self::from_raw(&fs::read(path)?, bounds)
+    }
+
+    fn from_raw(data: &[u8], bounds: AABB) -> io::Result<Self> {
+        let invalid_data = |message| io::Error::new(io::ErrorKind::InvalidData, message);
+        if data.len() < 12 {
+            return Err(invalid_data("voxel grid header is truncated"));
+        }
+        let mut dims = [0; 3];
+        for (axis, bytes) in data[..12].chunks(4).enumerate() {
+            dims[axis] = u32::from_le_bytes(bytes.try_into().unwrap()) as usize;
+        }
+        let count = dims
+            .iter()
+            .try_fold(1usize, |count, &n| count.checked_mul(n))
+            .filter(|&count| count > 0)
+            .ok_or_else(|| invalid_data("voxel grid has an invalid size"))?;
+        if count.checked_mul(4) != Some(data.len() - 12) {
+            return Err(invalid_data("voxel grid size doesn't match its dimensions"));
+        }
+        let densities = data[12..]
+            .chunks(4)
+            .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()) as f64)
+            .collect();
+        Ok(Self::new(dims, densities, bounds))
+    }
+
+    /// Density at voxel `i`, `j`, `k`, clamped to the grid borders
+    fn voxel(&self, index: [i64; 3]) -> f64 {
+        let [i, j, k] =
+            std::array::from_fn(|axis| index[axis].clamp(0, self.dims[axis] as i64 - 1) as usize);
+        self.densities[(k * self.dims[1] + j) * self.dims[0] + i]
+    }
+
+    /// Trilinearly interpolated density at `p`, 0 outside of the bounds
+    ///
+    /// ## Math
+    /// Voxel `i` along an axis of `n` voxels covering `[min, max]` is centered at
+    /// `min + (i + 0.5) * (max - min) / n`, so `p` lies at the fractional voxel index
+    /// `x = (p - min) / (max - min) * n - 0.5`. The eight voxels around it are weighted by
+    /// `1 - |x - i|` along each axis.
+    pub fn density(&self, p: &Point3) -> f64 {
+        let p = [p.x, p.y, p.z];
+        let mut base = [0; 3];
+        let mut fraction = [0.0; 3];
+        for axis in 0..3 {
+            let interval = self.bounds.axis_interval(axis);
+            if !interval.contains_closed(p[axis]) {
+                return 0.0;
+            }
+            let x = (p[axis] - interval.min) / interval.size() * self.dims[axis] as f64 - 0.5;
+            base[axis] = x.floor() as i64;
+            fraction[axis] = x - x.floor();
+        }
+
+        let mut density = 0.0;
+        for corner in 0..8 {
+            let mut weight = 1.0;
+            let mut index = base;
+            for axis in 0..3 {
+                if corner >> axis & 1 == 1 {
+                    index[axis] += 1;
+                    weight *= fraction[axis];
+                } else {
+                    weight *= 1.0 - fraction[axis];
+                }
+            }
+            density += weight * self.voxel(index);
+        }
+        density
+    }
+}
+
+impl Texture for VoxelTexture {
+    fn value(&self, _u: f64, _v: f64, p: &Point3) -> Color3 {
+        let density = self.density(p);
+        Color3::new(density, density, density)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Camera;
+    use crate::hittable::Hittable;
+    use crate::material::Lambertian;
+    use crate::png::write_png;
+    use crate::sphere::Sphere;
+    use crate::utils::init_rng;
+
+    fn render_noise_scene(seed: u64) -> crate::image::RgbImage {
+        init_rng(seed);
+        let material = Arc::new(Lambertian::from_texture(Arc::new(NoiseTexture::new(4.0))));
+        let world: Arc<dyn Hittable> =
+            Arc::new(Sphere::new(Point3::new(0.0, 0.0, -2.0), 1.0, material));
+
+        let mut camera = Camera::builder()
+            .image_width(32)
+            .samples_per_pixel(4)
+            .max_depth(4)
+            .build();
+        camera.set_progress(false);
+        camera.set_threads(Some(2));
+        Arc::new(camera).render_to_buffer(world)
+    }
+
+    #[test]
+    fn checker_alternates_across_integer_boundaries() {
+        let (white, black) = (Color3::new(1.0, 1.0, 1.0), Color3::zero());
+        let checker = CheckerTexture::from_colors(1.0, white, black);
+        let color = |x: f64, y: f64, z: f64| {
+            let c = checker.value(0.0, 0.0, &Point3::new(x, y, z));
+            (c.x, c.y, c.z)
+        };
+
+        assert_eq!(color(0.5, 0.5, 0.5), (1.0, 1.0, 1.0));
+        // Crossing any one boundary flips the color, crossing two flips it back
+        assert_eq!(color(1.5, 0.5, 0.5), (0.0, 0.0, 0.0));
+        assert_eq!(color(0.5, -0.5, 0.5), (0.0, 0.0, 0.0));
+        assert_eq!(color(0.5, 0.5, 1.5), (0.0, 0.0, 0.0));
+        assert_eq!(color(1.5, 1.5, 0.5), (1.0, 1.0, 1.0));
+        assert_eq!(color(0.999, 0.5, 0.5), (1.0, 1.0, 1.0));
+        assert_eq!(color(1.001, 0.5, 0.5), (0.0, 0.0, 0.0));
+
+        // A scale of 2 makes the squares twice as wide
+        let checker = CheckerTexture::from_colors(2.0, white, black);
+        assert_eq!(checker.value(0.0, 0.0, &Point3::new(1.5, 0.5, 0.5)).x, 1.0);
+    }
+
+    #[test]
+    fn missing_image_renders_as_a_checkerboard() {
+        let texture = ImageTexture::new("does/not/exist.png");
+        let color = |u: f64, v: f64| {
+            let c = texture.value(u, v, &Point3::zero());
+            (c.x, c.y, c.z)
+        };
+        // Neighboring squares are 1/8 apart, the top left one is magenta
+        assert_eq!(color(0.05, 0.95), (1.0, 0.0, 1.0));
+        assert_eq!(color(0.2, 0.95), (0.0, 0.0, 0.0));
+        assert_eq!(color(0.2, 0.8), (1.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn checkerboard_png_corners() {
+        // White and gray on the top row, swapped on the bottom one
+        let (w, g) = ([255, 255, 255], [51, 51, 51]);
+        let pixels = [w, g, g, w].concat();
+        let mut png = Vec::new();
+        write_png(&mut png, 2, 2, &pixels, None).unwrap();
+        let path = std::env::temp_dir().join(format!("checker_{}.png", std::process::id()));
+        fs::write(&path, png).unwrap();
+        let texture = ImageTexture::new(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!((texture.width(), texture.height()), (2, 2));
+        let gray = 0.2 * 0.2; // Decoded from gamma 2
+        let brightness = |u: f64, v: f64| texture.value(u, v, &Point3::zero()).x;
+        assert_eq!(brightness(0.0, 1.0), 1.0); // Top left
+        assert_eq!(brightness(1.0, 1.0), gray);
+        assert_eq!(brightness(0.0, 0.0), gray);
+        assert_eq!(brightness(1.0, 0.0), 1.0); // Bottom right
+        // Out of range UVs are clamped to the border
+        assert_eq!(brightness(-0.5, 1.5), 1.0);
+    }
+
+    #[test]
+    fn bilinear_averages_the_texels_around_the_center() {
+        let pixels = [1.0, 2.0, 3.0, 6.0]
+            .into_iter()
+            .map(|red| Color3::new(red, 0.0, 0.0))
+            .collect();
+        let mut texture = ImageTexture::from_pixels(2, 2, pixels);
+        let center = |texture: &ImageTexture| texture.value(0.5, 0.5, &Point3::zero()).x;
+
+        // The center is on the corner of all four texels, nearest picks the bottom right one
+        assert_eq!(center(&texture), 6.0);
+        texture.set_filter(Filter::Bilinear);
+        assert_eq!(center(&texture), 3.0);
+        // Texel centers are exact, and the borders are clamped
+        assert_eq!(texture.value(0.25, 0.75, &Point3::zero()).x, 1.0);
+        assert_eq!(texture.value(0.0, 1.0, &Point3::zero()).x, 1.0);
+    }
+
+    /// Color encoding the UV it was looked up at
+    #[derive(Debug)]
+    struct UvTexture;
+
+    impl Texture for UvTexture {
+        fn value(&self, u: f64, v: f64, _p: &Point3) -> Color3 {
+            Color3::new(u, v, 0.0)
+        }
+    }
+
+    #[test]
+    fn baked_texture_matches_the_original() {
+        let baked = bake_texture(&UvTexture, 16, 8);
+        assert_eq!((baked.width(), baked.height()), (16, 8));
+        // Bilinear filtering reproduces a linear texture exactly away from the borders
+        for (u, v) in [(0.5, 0.5), (0.1, 0.9), (0.7, 0.25)] {
+            let color = baked.value(u, v, &Point3::zero());
+            assert!((color.x - u).abs() < 1e-9 && (color.y - v).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn noise_texture_renders_are_reproducible() {
+        assert_eq!(render_noise_scene(7), render_noise_scene(7));
+    }
+
+    #[test]
+    fn voxel_grid_is_interpolated_between_voxel_centers() {
+        // Voxel centers at x = 0.5 and x = 1.5
+        let bounds = AABB::from_points(Point3::zero(), Point3::new(2.0, 1.0, 1.0));
+        let texture = VoxelTexture::new([2, 1, 1], vec![0.0, 1.0], bounds);
+        let density = |x: f64| texture.density(&Point3::new(x, 0.5, 0.5));
+
+        assert_eq!(density(0.5), 0.0);
+        assert_eq!(density(1.0), 0.5);
+        assert_eq!(density(1.25), 0.75);
+        // Clamped at the borders, nothing outside of the bounds
+        assert_eq!(density(0.1), 0.0);
+        assert_eq!(density(1.9), 1.0);
+        assert_eq!(density(2.1), 0.0);
+        assert_eq!(texture.density(&Point3::new(1.9, 1.5, 0.5)), 0.0);
+    }
+
+    #[test]
+    fn voxel_grid_loads_from_raw_floats() {
+        let mut data = Vec::new();
+        for n in [2u32, 2, 2] {
+            data.extend_from_slice(&n.to_le_bytes());
+        }
+        for density in 0..8 {
+            data.extend_from_slice(&(density as f32).to_le_bytes());
+        }
+        let bounds = AABB::from_points(Point3::zero(), Point3::new(1.0, 1.0, 1.0));
+        let texture = VoxelTexture::from_raw(&data, bounds.clone()).unwrap();
+
+        // Corner voxel centers hold their own value, x fastest, then y, then z
+        assert_eq!(texture.density(&Point3::new(0.25, 0.25, 0.25)), 0.0);
+        assert_eq!(texture.density(&Point3::new(0.75, 0.25, 0.25)), 1.0);
+        assert_eq!(texture.density(&Point3::new(0.25, 0.75, 0.25)), 2.0);
+        assert_eq!(texture.density(&Point3::new(0.25, 0.25, 0.75)), 4.0);
+        assert_eq!(texture.density(&Point3::new(0.5, 0.5, 0.5)), 3.5);
+
+        assert!(VoxelTexture::from_raw(&data[..data.len() - 1], bounds.clone()).is_err());
+        assert!(VoxelTexture::from_raw(&data[..8], bounds).is_err());
+    }
+}
@@ -0,0 +1,681 @@
+use std::sync::Arc;
+
+use crate::aabb::AABB;
+use crate::hittable::HitRecord;
+use crate::hittable::Hittable;
+use crate::interval::Interval;
+use crate::ray::Ray;
+use crate::vec::Point3;
+use crate::vec::Vec3;
+
+/// Turns the solid angle density of `object_direction` seen from an object's space into the
+/// density of `direction`, the same direction in world space
+///
+/// ## Math
+/// ### Variables
+/// `M` is the linear map from world to object directions, `ω` a unit world direction
+/// ### Calculation
+/// Normalizing `M*ω` maps a small solid angle `dω` around `ω` to
+/// `dω' = |det M| / |M*ω|^3 * dω`, and densities scale by the same factor. For a direction
+/// `d` of any length `|M*ω| = |M*d| / |d|`.
+/// ### Outcomes
+/// - Rotations and translations leave the density unchanged
+/// - A uniform scale by `s` seen from the scaled origin leaves it unchanged as well, the factor
+///   being `s^-3 * s^3`
+pub(crate) fn world_pdf(
+    object_pdf: f64,
+    direction: &Vec3,
+    object_direction: &Vec3,
+    det: f64,
+) -> f64 {
+    let ratio = direction.length() / object_direction.length();
+    object_pdf * det.abs() * ratio * ratio * ratio
+}
+
+/// Scales an object by a (possibly non-uniform) factor along each axis
+pub struct Scale {
+    object: Arc<dyn Hittable>,
+    scale: Vec3,
+    bbox: AABB,
+}
+
+impl Scale {
+    pub fn new(object: Arc<dyn Hittable>, scale: Vec3) -> Self {
+        assert!(scale.x != 0.0 && scale.y != 0.0 && scale.z != 0.0);
+        let bbox = object.boundnig_box();
+        let bbox = AABB::from_points(bbox.min() * scale, bbox.max() * scale);
+        Self {
+            object,
+            scale,
+            bbox,
+        }
+    }
+
+    fn inv_scale(&self) -> Vec3 {
+        Vec3::new(1.0 / self.scale.x, 1.0 / self.scale.y, 1.0 / self.scale.z)
+    }
+
+    fn object_ray(&self, ray: &Ray) -> Ray {
+        let inv_scale = self.inv_scale();
+        Ray::new_time(ray.origin * inv_scale, ray.dir * inv_scale, ray.tm)
+    }
+
+    fn to_world(&self, mut hit_record: HitRecord) -> HitRecord {
+        hit_record.p = hit_record.p * self.scale;
+        hit_record.normal = (hit_record.normal * self.inv_scale()).unit();
+        // Surface directions follow the points
+        hit_record.tangent = hit_record.tangent * self.scale;
+        hit_record.bitangent = hit_record.bitangent * self.scale;
+        hit_record
+    }
+}
+
+impl Hittable for Scale {
+    /// The ray is scaled into object space by `1/scale`. Since the transform is linear
+    /// the ray parameter `t` is the same in both spaces.
+    ///
+    /// Points are scaled back by `scale`, but normals have to be transformed by the
+    /// inverse-transpose of the scale, which for a diagonal matrix is `1/scale`,
+    /// and renormalized afterwards.
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        self.object
+            .hit(&self.object_ray(ray), ray_t)
+            .map(|hit_record| self.to_world(hit_record))
+    }
+
+    fn boundnig_box(&self) -> &AABB {
+        &self.bbox
+    }
+
+    fn occluded(&self, ray: &Ray, ray_t: Interval) -> bool {
+        self.object.occluded(&self.object_ray(ray), ray_t)
+    }
+
+    fn hit_all(&self, ray: &Ray, ray_t: Interval) -> Vec<HitRecord> {
+        self.object
+            .hit_all(&self.object_ray(ray), ray_t)
+            .into_iter()
+            .map(|hit_record| self.to_world(hit_record))
+            .collect()
+    }
+
+    /// Scaling isn't rigid, the object's density is corrected with `world_pdf`
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        let inv_scale = self.inv_scale();
+        let object_direction = *direction * inv_scale;
+        let object_pdf = self
+            .object
+            .pdf_value(&(*origin * inv_scale), &object_direction);
+        let det = inv_scale.x * inv_scale.y * inv_scale.z;
+        world_pdf(object_pdf, direction, &object_direction, det)
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        self.object.random(&(*origin * self.inv_scale())) * self.scale
+    }
+}
+
+/// Moves an object by `offset`
+pub struct Translate {
+    object: Arc<dyn Hittable>,
+    offset: Vec3,
+    bbox: AABB,
+}
+
+impl Translate {
+    pub fn new(object: Arc<dyn Hittable>, offset: Vec3) -> Self {
+        let bbox = object.boundnig_box();
+        let bbox = AABB::from_points(bbox.min() + offset, bbox.max() + offset);
+        Self {
+            object,
+            offset,
+            bbox,
+        }
+    }
+}
+
+impl Hittable for Translate {
+    /// Moving the object by `offset` is the same as moving the ray by `-offset`
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let offset_ray = Ray::new_time(ray.origin - self.offset, ray.dir, ray.tm);
+
+        let mut hit_record = self.object.hit(&offset_ray, ray_t)?;
+        hit_record.p += self.offset;
+        Some(hit_record)
+    }
+
+    fn boundnig_box(&self) -> &AABB {
+        &self.bbox
+    }
+
+    fn occluded(&self, ray: &Ray, ray_t: Interval) -> bool {
+        let offset_ray = Ray::new_time(ray.origin - self.offset, ray.dir, ray.tm);
+        self.object.occluded(&offset_ray, ray_t)
+    }
+
+    fn hit_all(&self, ray: &Ray, ray_t: Interval) -> Vec<HitRecord> {
+        let offset_ray = Ray::new_time(ray.origin - self.offset, ray.dir, ray.tm);
+
+        let mut hits = self.object.hit_all(&offset_ray, ray_t);
+        for hit_record in &mut hits {
+            hit_record.p += self.offset;
+        }
+        hits
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        self.object.pdf_value(&(*origin - self.offset), direction)
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        self.object.random(&(*origin - self.offset))
+    }
+}
+
+/// Moves an object by `translation_at_t1` over the shutter interval, it is at its original
+/// position at `tm = 0` and fully translated at `tm = 1`
+pub struct MotionBlur {
+    object: Arc<dyn Hittable>,
+    translation_at_t1: Vec3,
+    bbox: AABB,
+}
+
+impl MotionBlur {
+    pub fn new(object: Arc<dyn Hittable>, translation_at_t1: Vec3) -> Self {
+        // Enclose the object at both ends of its travel
+        let bbox_t0 = object.boundnig_box();
+        let bbox_t1 = AABB::from_points(
+            bbox_t0.min() + translation_at_t1,
+            bbox_t0.max() + translation_at_t1,
+        );
+        let bbox = AABB::from_boxes(bbox_t0, &bbox_t1);
+        Self {
+            object,
+            translation_at_t1,
+            bbox,
+        }
+    }
+
+    /// Where the ray is in the object's space, at its `tm` the object is translated by
+    /// `translation_at_t1 * tm`
+    fn object_ray(&self, ray: &Ray) -> (Ray, Vec3) {
+        let offset = self.translation_at_t1 * ray.tm;
+        (Ray::new_time(ray.origin - offset, ray.dir, ray.tm), offset)
+    }
+}
+
+impl Hittable for MotionBlur {
+    /// Same as `Translate` with the offset picked by the ray's time
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let (object_ray, offset) = self.object_ray(ray);
+
+        let mut hit_record = self.object.hit(&object_ray, ray_t)?;
+        hit_record.p += offset;
+        Some(hit_record)
+    }
+
+    fn boundnig_box(&self) -> &AABB {
+        &self.bbox
+    }
+
+    fn occluded(&self, ray: &Ray, ray_t: Interval) -> bool {
+        let (object_ray, _) = self.object_ray(ray);
+        self.object.occluded(&object_ray, ray_t)
+    }
+
+    fn hit_all(&self, ray: &Ray, ray_t: Interval) -> Vec<HitRecord> {
+        let (object_ray, offset) = self.object_ray(ray);
+
+        let mut hits = self.object.hit_all(&object_ray, ray_t);
+        for hit_record in &mut hits {
+            hit_record.p += offset;
+        }
+        hits
+    }
+
+    /// Like moving spheres the object is sampled where it is at time 0, i.e. untranslated
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        self.object.pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        self.object.random(origin)
+    }
+}
+
+/// Rotates an object around the Y axis
+pub struct RotateY {
+    object: Arc<dyn Hittable>,
+    sin_theta: f64,
+    cos_theta: f64,
+    bbox: AABB,
+}
+
+impl RotateY {
+    /// `angle` is in degrees, counter-clockwise when looking down from +Y
+    pub fn new(object: Arc<dyn Hittable>, angle: f64) -> Self {
+        let radians = angle.to_radians();
+        let sin_theta = radians.sin();
+        let cos_theta = radians.cos();
+
+        // Box enclosing all 8 rotated corners of the object's box
+        let bbox = object.boundnig_box();
+        let mut min = Point3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+        let mut max = Point3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for corner in 0..8 {
+            let x = if corner & 1 == 0 {
+                bbox.min().x
+            } else {
+                bbox.max().x
+            };
+            let y = if corner & 2 == 0 {
+                bbox.min().y
+            } else {
+                bbox.max().y
+            };
+            let z = if corner & 4 == 0 {
+                bbox.min().z
+            } else {
+                bbox.max().z
+            };
+
+            let rotated = RotateY::rotate(sin_theta, cos_theta, Point3::new(x, y, z));
+            min = min.min(&rotated);
+            max = max.max(&rotated);
+        }
+
+        Self {
+            object,
+            sin_theta,
+            cos_theta,
+            bbox: AABB::from_points(min, max),
+        }
+    }
+
+    /// Rotates `v` around Y by the angle whose sine and cosine are given
+    fn rotate(sin_theta: f64, cos_theta: f64, v: Vec3) -> Vec3 {
+        Vec3::new(
+            cos_theta * v.x + sin_theta * v.z,
+            v.y,
+            -sin_theta * v.x + cos_theta * v.z,
+        )
+    }
+
+    fn to_object(&self, v: Vec3) -> Vec3 {
+        // Rotating by -theta flips the sign of the sine
+        RotateY::rotate(-self.sin_theta, self.cos_theta, v)
+    }
+
+    fn to_world(&self, v: Vec3) -> Vec3 {
+        RotateY::rotate(self.sin_theta, self.cos_theta, v)
+    }
+
+    fn object_ray(&self, ray: &Ray) -> Ray {
+        Ray::new_time(self.to_object(ray.origin), self.to_object(ray.dir), ray.tm)
+    }
+
+    fn hit_to_world(&self, mut hit_record: HitRecord) -> HitRecord {
+        hit_record.p = self.to_world(hit_record.p);
+        hit_record.normal = self.to_world(hit_record.normal);
+        hit_record.tangent = self.to_world(hit_record.tangent);
+        hit_record.bitangent = self.to_world(hit_record.bitangent);
+        hit_record
+    }
+}
+
+impl Hittable for RotateY {
+    /// The ray is rotated into object space by `-theta`, and the resulting hit point and
+    /// normal are rotated back by `theta`
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        self.object
+            .hit(&self.object_ray(ray), ray_t)
+            .map(|hit_record| self.hit_to_world(hit_record))
+    }
+
+    fn boundnig_box(&self) -> &AABB {
+        &self.bbox
+    }
+
+    fn occluded(&self, ray: &Ray, ray_t: Interval) -> bool {
+        self.object.occluded(&self.object_ray(ray), ray_t)
+    }
+
+    fn hit_all(&self, ray: &Ray, ray_t: Interval) -> Vec<HitRecord> {
+        self.object
+            .hit_all(&self.object_ray(ray), ray_t)
+            .into_iter()
+            .map(|hit_record| self.hit_to_world(hit_record))
+            .collect()
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        self.object
+            .pdf_value(&self.to_object(*origin), &self.to_object(*direction))
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        self.to_world(self.object.random(&self.to_object(*origin)))
+    }
+}
+
+/// Affine transform `p' = M*p + t`, built by chaining translations, rotations and scales
+#[derive(Debug, Clone, Copy)]
+pub struct Transform {
+    m: [[f64; 3]; 3],
+    t: Vec3,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::identity()
+    }
+}
+
+impl Transform {
+    pub fn identity() -> Self {
+        Self {
+            m: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            t: Vec3::zero(),
+        }
+    }
+
+    pub fn translation(offset: Vec3) -> Self {
+        Self {
+            t: offset,
+            ..Transform::identity()
+        }
+    }
+
+    pub fn scale(scale: Vec3) -> Self {
+        Self {
+            m: [
+                [scale.x, 0.0, 0.0],
+                [0.0, scale.y, 0.0],
+                [0.0, 0.0, scale.z],
+            ],
+            t: Vec3::zero(),
+        }
+    }
+
+    /// `angle` is in degrees, counter-clockwise when looking down from +X
+    pub fn rotation_x(angle: f64) -> Self {
+        let (s, c) = angle.to_radians().sin_cos();
+        Self {
+            m: [[1.0, 0.0, 0.0], [0.0, c, -s], [0.0, s, c]],
+            t: Vec3::zero(),
+        }
+    }
+
+    /// `angle` is in degrees, counter-clockwise when looking down from +Y, same as `RotateY`
+    pub fn rotation_y(angle: f64) -> Self {
+        let (s, c) = angle.to_radians().sin_cos();
+        Self {
+            m: [[c, 0.0, s], [0.0, 1.0, 0.0], [-s, 0.0, c]],
+            t: Vec3::zero(),
+        }
+    }
+
+    /// `angle` is in degrees, counter-clockwise when looking down from +Z
+    pub fn rotation_z(angle: f64) -> Self {
+        let (s, c) = angle.to_radians().sin_cos();
+        Self {
+            m: [[c, -s, 0.0], [s, c, 0.0], [0.0, 0.0, 1.0]],
+            t: Vec3::zero(),
+        }
+    }
+
+    /// Transform applying `self` first and `next` after it, `next.M * self.M`
+    pub fn then(&self, next: &Transform) -> Transform {
+        let mut m = [[0.0; 3]; 3];
+        for (row, m_row) in m.iter_mut().enumerate() {
+            for (col, value) in m_row.iter_mut().enumerate() {
+                *value = (0..3).map(|k| next.m[row][k] * self.m[k][col]).sum();
+            }
+        }
+        Transform {
+            m,
+            t: next.apply_vector(self.t) + next.t,
+        }
+    }
+
+    /// Determinant of the linear part, how much the transform scales volumes
+    pub fn determinant(&self) -> f64 {
+        let m = &self.m;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    /// ## Math
+    /// `M⁻¹` is the adjugate divided by the determinant, and `p = M⁻¹*(p' - t)` so the
+    /// inverse translation is `-M⁻¹*t`
+    pub fn inverse(&self) -> Transform {
+        let m = &self.m;
+        let cofactor = |r0: usize, r1: usize, c0: usize, c1: usize| {
+            m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0]
+        };
+        let det = self.determinant();
+        assert!(det != 0.0, "Transform is not invertible");
+        let inv_det = 1.0 / det;
+
+        let inv = [
+            [
+                cofactor(1, 2, 1, 2) * inv_det,
+                -cofactor(0, 2, 1, 2) * inv_det,
+                cofactor(0, 1, 1, 2) * inv_det,
+            ],
+            [
+                -cofactor(1, 2, 0, 2) * inv_det,
+                cofactor(0, 2, 0, 2) * inv_det,
+                -cofactor(0, 1, 0, 2) * inv_det,
+            ],
+            [
+                cofactor(1, 2, 0, 1) * inv_det,
+                -cofactor(0, 2, 0, 1) * inv_det,
+                cofactor(0, 1, 0, 1) * inv_det,
+            ],
+        ];
+        let linear = Transform {
+            m: inv,
+            t: Vec3::zero(),
+        };
+        Transform {
+            m: inv,
+            t: -linear.apply_vector(self.t),
+        }
+    }
+
+    pub fn apply_point(&self, p: Point3) -> Point3 {
+        self.apply_vector(p) + self.t
+    }
+
+    /// Directions ignore the translation
+    pub fn apply_vector(&self, v: Vec3) -> Vec3 {
+        Vec3::new(
+            self.m[0][0] * v.x + self.m[0][1] * v.y + self.m[0][2] * v.z,
+            self.m[1][0] * v.x + self.m[1][1] * v.y + self.m[1][2] * v.z,
+            self.m[2][0] * v.x + self.m[2][1] * v.y + self.m[2][2] * v.z,
+        )
+    }
+
+    /// Multiplies `n` by the transpose of the linear part. Called on the inverse transform
+    /// this maps normals with the inverse-transpose, keeping them perpendicular to the surface.
+    /// The result isn't normalized.
+    pub fn apply_transpose(&self, n: Vec3) -> Vec3 {
+        Vec3::new(
+            self.m[0][0] * n.x + self.m[1][0] * n.y + self.m[2][0] * n.z,
+            self.m[0][1] * n.x + self.m[1][1] * n.y + self.m[2][1] * n.z,
+            self.m[0][2] * n.x + self.m[1][2] * n.y + self.m[2][2] * n.z,
+        )
+    }
+
+    /// Box enclosing all 8 transformed corners of `bbox`
+    pub fn apply_box(&self, bbox: &AABB) -> AABB {
+        let (min, max) = (bbox.min(), bbox.max());
+        let mut result = AABB::empty();
+        for corner in 0..8 {
+            let x = if corner & 1 == 0 { min.x } else { max.x };
+            let y = if corner & 2 == 0 { min.y } else { max.y };
+            let z = if corner & 4 == 0 { min.z } else { max.z };
+            let p = self.apply_point(Point3::new(x, y, z));
+            result = AABB::from_boxes(&result, &AABB::from_points(p, p));
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use super::*;
+    use crate::group::Group;
+    use crate::hittable::HittableList;
+    use crate::material::DiffuseLight;
+    use crate::quad::Quad;
+    use crate::quad::make_box;
+    use crate::sphere::Sphere;
+    use crate::utils::seed_thread_rng;
+    use crate::vec::Color3;
+
+    fn unit_light() -> Arc<dyn Hittable> {
+        let light = Arc::new(DiffuseLight::new(Color3::new(4.0, 4.0, 4.0)));
+        Arc::new(Sphere::new(Point3::zero(), 1.0, light))
+    }
+
+    /// `∫ pdf dω` over every direction from `origin`, estimated with uniform directions
+    fn pdf_integral(object: &dyn Hittable, origin: Point3) -> f64 {
+        const SAMPLES: usize = 200_000;
+        let sum: f64 = (0..SAMPLES)
+            .map(|_| object.pdf_value(&origin, &Vec3::random_unit()))
+            .sum();
+        4.0 * PI * sum / SAMPLES as f64
+    }
+
+    /// Whether every direction `random` picks from `origin` reaches the object
+    fn samples_hit(object: &dyn Hittable, origin: Point3) -> bool {
+        (0..1000).all(|_| {
+            let ray = Ray::new(origin, object.random(&origin));
+            object.occluded(&ray, Interval::new(0.001, f64::INFINITY))
+        })
+    }
+
+    #[test]
+    fn translated_sphere_is_hit_at_its_new_place() {
+        let translated = Translate::new(unit_light(), Vec3::new(3.0, 0.0, -4.0));
+        let ray = Ray::new(Point3::new(3.0, 0.0, 2.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_record = translated
+            .hit(&ray, Interval::new(0.001, f64::INFINITY))
+            .unwrap();
+        assert_eq!(hit_record.t, 5.0);
+        assert_eq!(
+            (hit_record.p.x, hit_record.p.y, hit_record.p.z),
+            (3.0, 0.0, -3.0)
+        );
+        assert_eq!(hit_record.normal.z, 1.0);
+
+        // Where the sphere used to be there's nothing left
+        let ray = Ray::new(Point3::new(0.0, 0.0, 2.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(
+            translated
+                .hit(&ray, Interval::new(0.001, f64::INFINITY))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn quarter_turn_moves_the_box_corners() {
+        let material = Arc::new(DiffuseLight::new(Color3::new(1.0, 1.0, 1.0)));
+        let cuboid = make_box(Point3::zero(), Point3::new(1.0, 2.0, 3.0), material);
+        let rotated = RotateY::new(Arc::new(cuboid), 90.0);
+
+        // Counter-clockwise seen from above, +Z turns into +X and +X into -Z
+        let bbox = rotated.boundnig_box();
+        let (min, max) = (bbox.min(), bbox.max());
+        // The faces are padded to a minimum thickness
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-3;
+        assert!(close(min.x, 0.0) && close(max.x, 3.0));
+        assert!(close(min.y, 0.0) && close(max.y, 2.0));
+        assert!(close(min.z, -1.0) && close(max.z, 0.0));
+    }
+
+    #[test]
+    fn moving_quad_spans_its_travel() {
+        let material = Arc::new(DiffuseLight::new(Color3::new(1.0, 1.0, 1.0)));
+        // Unit square facing +Z, sliding 4 units along +X
+        let quad = Quad::new(
+            Point3::zero(),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            material,
+        );
+        let moving = MotionBlur::new(Arc::new(quad), Vec3::new(4.0, 0.0, 0.0));
+
+        let bbox = moving.boundnig_box();
+        assert_eq!((bbox.min().x, bbox.max().x), (0.0, 5.0));
+        assert_eq!((bbox.min().y, bbox.max().y), (0.0, 1.0));
+
+        // Halfway through the shutter the square spans `[2, 3]` along X
+        let ray_at = |x: f64| {
+            let ray = Ray::new_time(Point3::new(x, 0.5, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.5);
+            moving.hit(&ray, Interval::new(0.001, f64::INFINITY))
+        };
+        let hit_record = ray_at(2.5).unwrap();
+        assert_eq!(
+            (hit_record.p.x, hit_record.p.y, hit_record.p.z),
+            (2.5, 0.5, 0.0)
+        );
+        assert_eq!((hit_record.u, hit_record.v), (0.5, 0.5));
+        assert!(ray_at(0.5).is_none());
+        assert!(ray_at(4.5).is_none());
+    }
+
+    #[test]
+    fn translated_light_is_sampled_where_it_was_moved() {
+        let offset = Vec3::new(1.0, 2.0, -5.0);
+        let translated = Translate::new(unit_light(), offset);
+        let light = Arc::new(DiffuseLight::new(Color3::new(4.0, 4.0, 4.0)));
+        let in_place = Sphere::new(offset, 1.0, light);
+
+        let origin = Point3::new(0.0, 0.5, 0.0);
+        for direction in [offset - origin, offset - origin + Vec3::new(0.3, 0.0, 0.0)] {
+            let pdf = translated.pdf_value(&origin, &direction);
+            assert!(pdf > 0.0);
+            assert!((pdf - in_place.pdf_value(&origin, &direction)).abs() < 1e-12);
+        }
+        assert_eq!(translated.pdf_value(&origin, &-offset), 0.0);
+        assert!(samples_hit(&translated, origin));
+
+        // Motion blur samples the object where it starts
+        let moving = MotionBlur::new(Arc::new(translated), Vec3::new(0.0, 3.0, 0.0));
+        assert!(moving.pdf_value(&origin, &(offset - origin)) > 0.0);
+        assert!(samples_hit(&moving, origin));
+    }
+
+    #[test]
+    fn transformed_light_densities_integrate_to_one() {
+        seed_thread_rng(11);
+        let origin = Point3::new(0.5, 0.0, 3.0);
+
+        let rotated = RotateY::new(
+            Arc::new(Translate::new(unit_light(), Vec3::new(-2.0, 0.0, 0.0))),
+            40.0,
+        );
+        let scaled = Scale::new(unit_light(), Vec3::new(1.5, 0.5, 0.8));
+        let mut children = HittableList::new();
+        children.add_shared(unit_light());
+        let group = Group::new(
+            children,
+            Transform::scale(Vec3::new(0.6, 1.2, 0.9))
+                .then(&Transform::rotation_x(30.0))
+                .then(&Transform::translation(Vec3::new(0.0, 1.0, -1.0))),
+        );
+
+        let objects: [&dyn Hittable; 3] = [&rotated, &scaled, &group];
+        for object in objects {
+            let integral = pdf_integral(object, origin);
+            assert!((integral - 1.0).abs() < 0.05, "{integral}");
+            assert!(samples_hit(object, origin));
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use std::sync::Arc;
+
+use crate::aabb::AABB;
+use crate::hittable::HitRecord;
+use crate::hittable::Hittable;
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec::Point3;
+use crate::vec::Vec3;
+
+#[derive(Debug)]
+pub struct Triangle {
+    v0: Point3,
+    edge1: Vec3, // v1 - v0
+    edge2: Vec3, // v2 - v0
+    normal: Vec3,
+    material: Arc<dyn Material>,
+    bbox: AABB,
+}
+
+impl Triangle {
+    /// The front face is the one the vertices are seen counter-clockwise from
+    pub fn new(v0: Point3, v1: Point3, v2: Point3, material: Arc<dyn Material>) -> Self {
+        let edge1 = v1 - v0;
+        let edge2 = v2 - v0;
+        let normal = edge1.cross(edge2).unit();
+        let bbox = AABB::from_boxes(&AABB::from_points(v0, v1), &AABB::from_points(v2, v2))
+            .pad_to_minimum();
+        Self {
+            v0,
+            edge1,
+            edge2,
+            normal,
+            material,
+            bbox,
+        }
+    }
+}
+
+impl Hittable for Triangle {
+    /// ## Math
+    /// Möller–Trumbore intersection.
+    /// ### Variables
+    /// `V0` is the first vertex, `e1 = V1 - V0`, `e2 = V2 - V0`
+    /// Ray: `P(t) = O + t*d`
+    /// ### Calculation
+    /// A point on the triangle is `V0 + u*e1 + v*e2` with barycentric coordinates `u`, `v`.
+    /// Setting it equal to the ray gives the linear system
+    /// `[-d e1 e2] * [t u v]ᵀ = O - V0`
+    ///
+    /// Solving it with Cramer's rule, using `p = d × e2`, `s = O - V0` and `q = s × e1`:
+    /// `det = e1 ⋅ p`, `u = (s ⋅ p) / det`, `v = (d ⋅ q) / det`, `t = (e2 ⋅ q) / det`
+    ///
+    /// ### Outcomes
+    /// - If `det` is 0 the ray is parallel to the triangle
+    /// - The point is inside the triangle if `u >= 0`, `v >= 0` and `u + v <= 1`
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let p = ray.dir.cross(self.edge2);
+        let det = self.edge1.dot(&p);
+        if det.abs() < 1e-12 {
+            return None;
+        }
+        let inv_det = 1.0 / det;
+
+        let s = ray.origin - self.v0;
+        let u = s.dot(&p) * inv_det;
+        if !Interval::new(0.0, 1.0).contains_closed(u) {
+            return None;
+        }
+
+        let q = s.cross(self.edge1);
+        let v = ray.dir.dot(&q) * inv_det;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let t = self.edge2.dot(&q) * inv_det;
+        if !ray_t.surrounds(t) {
+            return None;
+        }
+
+        let hit_point = self.v0 + u * self.edge1 + v * self.edge2;
+        Some(HitRecord::new(
+            hit_point,
+            self.normal,
+            ray,
+            Arc::clone(&self.material),
+            t,
+        ))
+    }
+
+    fn boundnig_box(&self) -> &AABB {
+        &self.bbox
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::vec::Color3;
+
+    fn triangle() -> Triangle {
+        let material = Arc::new(Lambertian::new(Color3::new(0.5, 0.5, 0.5)));
+        Triangle::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+            material,
+        )
+    }
+
+    fn towards(triangle: &Triangle, x: f64, y: f64, z: f64) -> Option<HitRecord> {
+        let ray = Ray::new(Point3::new(x, y, z), Vec3::new(0.0, 0.0, -z.signum()));
+        triangle.hit(&ray, Interval::new(0.001, f64::INFINITY))
+    }
+
+    #[test]
+    fn triangle_is_hit_through_its_centroid() {
+        let triangle = triangle();
+        let hit_record = towards(&triangle, 1.0 / 3.0, 1.0 / 3.0, 2.0).unwrap();
+        assert!((hit_record.t - 2.0).abs() < 1e-12);
+        assert!((hit_record.p - Point3::new(1.0 / 3.0, 1.0 / 3.0, 0.0)).length() < 1e-12);
+        assert_eq!(
+            (
+                hit_record.normal.x,
+                hit_record.normal.y,
+                hit_record.normal.z
+            ),
+            (0.0, 0.0, 1.0)
+        );
+        assert!(hit_record.is_front_face);
+
+        // Seen from behind, the vertices go clockwise
+        let hit_record = towards(&triangle, 1.0 / 3.0, 1.0 / 3.0, -2.0).unwrap();
+        assert!(!hit_record.is_front_face);
+    }
+
+    #[test]
+    fn triangle_is_missed_just_outside_an_edge() {
+        let triangle = triangle();
+        // Just past the hypotenuse, and just past the edge along the x axis
+        assert!(towards(&triangle, 0.501, 0.501, 2.0).is_none());
+        assert!(towards(&triangle, 0.5, -0.001, 2.0).is_none());
+        assert!(towards(&triangle, 0.499, 0.499, 2.0).is_some());
+    }
+}
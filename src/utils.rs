@@ -1,12 +1,221 @@
+use std::cell::RefCell;
+use std::sync::Mutex;
+
 use rand::Rng;
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+
+use crate::aabb::AABB;
+use crate::vec::Point3;
+use crate::vec::Vec3;
+
+thread_local! {
+    static RNG: RefCell<SmallRng> = RefCell::new(SmallRng::from_rng(&mut rand::rng()));
+}
+
+// Seed given to `init_rng`, render workers derive their per-tile seeds from it
+static RNG_SEED: Mutex<Option<u64>> = Mutex::new(None);
+
+/// Seeds the calling thread's random stream and makes `Camera::render` reseed its workers
+/// with `hash_u64(seed ^ hash_u64(tile_id))` before every tile. Calling it before `render`
+/// gives byte-identical images across runs, whatever the thread count, since each tile's
+/// samples only depend on `seed` and the tile.
+pub fn init_rng(seed: u64) {
+    *RNG_SEED.lock().unwrap() = Some(seed);
+    seed_thread_rng(seed);
+}
+
+/// Seed set by `init_rng`, `None` while rendering nondeterministically
+pub fn rng_seed() -> Option<u64> {
+    *RNG_SEED.lock().unwrap()
+}
+
+/// Restarts the calling thread's random stream from `seed` without touching the global seed
+pub fn seed_thread_rng(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = SmallRng::seed_from_u64(seed));
+}
 
 /// Returns a value between 0.0 and 1.0
 pub fn random_percentage() -> f64 {
-    let mut rng = rand::rng();
-    rng.random()
+    RNG.with(|rng| rng.borrow_mut().random())
 }
 
 pub fn random_f64(low: f64, high: f64) -> f64 {
-    let mut rng = rand::rng();
-    rng.random_range(low..high)
+    RNG.with(|rng| rng.borrow_mut().random_range(low..high))
+}
+
+/// Returns a value between `low` and `high`, both included
+pub fn random_u64(low: u64, high: u64) -> u64 {
+    RNG.with(|rng| rng.borrow_mut().random_range(low..=high))
+}
+
+/// Uniformly distributed point inside the triangle `a`, `b`, `c`
+///
+/// Folding the unit square along its diagonal maps `(r1, r2)` onto barycentric coordinates
+/// without wasting samples: if `r1 + r2 > 1` both are mirrored to `1 - r`.
+pub fn random_in_triangle(a: Point3, b: Point3, c: Point3) -> Point3 {
+    let (mut r1, mut r2) = (random_percentage(), random_percentage());
+    if r1 + r2 > 1.0 {
+        (r1, r2) = (1.0 - r1, 1.0 - r2);
+    }
+    a + r1 * (b - a) + r2 * (c - a)
+}
+
+/// Uniformly distributed point inside `aabb`
+pub fn random_in_box(aabb: &AABB) -> Point3 {
+    let (min, max) = (aabb.min(), aabb.max());
+    Point3::new(
+        min.x + random_percentage() * (max.x - min.x),
+        min.y + random_percentage() * (max.y - min.y),
+        min.z + random_percentage() * (max.z - min.z),
+    )
+}
+
+/// Unit direction uniformly distributed over the cap of directions within `acos(cos_max)` of +Z
+///
+/// The cap's solid angle is `2π(1 - cos_max)`, so `cos θ = 1 - r1*(1 - cos_max)`, `φ = 2π*r2`
+pub fn random_on_sphere_cap(cos_max: f64) -> Vec3 {
+    let cos_theta = 1.0 - random_percentage() * (1.0 - cos_max);
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = 2.0 * std::f64::consts::PI * random_percentage();
+    Vec3::new(phi.cos() * sin_theta, phi.sin() * sin_theta, cos_theta)
+}
+
+/// SplitMix64 finalizer, scrambles `seed` into a well-distributed 64-bit value
+pub fn hash_u64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Returns a value between 0.0 and 1.0 (exclusive) derived only from `seed`
+pub fn hash_to_f64(seed: u64) -> f64 {
+    // Keep the top 53 bits, which is all the precision an f64 mantissa can hold
+    (hash_u64(seed) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Returns a value between 0.0 and 1.0 that only depends on the pixel, the sample index
+/// within that pixel and the sample dimension (e.g. 0/1 for pixel jitter, 2/3 for the lens)
+pub fn hash_sample(pixel: u64, sample: u64, dimension: u64) -> f64 {
+    hash_to_f64(hash_u64(hash_u64(pixel) ^ sample) ^ dimension)
+}
+
+/// Counter-based SplitMix64 random stream, cheap to create per pixel or per tile
+#[derive(Debug, Clone)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let value = hash_u64(self.state);
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        value
+    }
+
+    /// Returns a value between 0.0 and 1.0 (exclusive)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Formats seconds since the Unix epoch as an ISO 8601 UTC timestamp, e.g.
+/// `2024-03-01T12:34:56Z`
+///
+/// Days are turned into a civil date by counting 400-year eras from 0000-03-01, so leap days
+/// fall at the end of each year (Howard Hinnant's `civil_from_days`).
+pub fn format_utc(unix_seconds: u64) -> String {
+    let days = unix_seconds / 86_400;
+    let seconds_of_day = unix_seconds % 86_400;
+
+    let z = days + 719_468; // Days since 0000-03-01
+    let era = z / 146_097;
+    let day_of_era = z % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153; // 0 is March
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        seconds_of_day / 3600,
+        seconds_of_day / 60 % 60,
+        seconds_of_day % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::camera::Camera;
+    use crate::image::OutputFormat;
+    use crate::material::Lambertian;
+    use crate::sphere::Sphere;
+    use crate::vec::Color3;
+
+    fn render_ppm() -> Vec<u8> {
+        let material = Arc::new(Lambertian::new(Color3::new(0.5, 0.5, 0.5)));
+        let world = Arc::new(Sphere::new(Point3::new(0.0, 0.0, -2.0), 1.0, material));
+        let mut camera = Camera::builder()
+            .image_width(16)
+            .samples_per_pixel(4)
+            .max_depth(4)
+            .build();
+        camera.set_progress(false);
+        camera.set_threads(Some(1));
+
+        let mut ppm = Vec::new();
+        let image = Arc::new(camera).render_to_buffer(world);
+        image.write_to(&mut ppm, OutputFormat::Ppm).unwrap();
+        ppm
+    }
+
+    #[test]
+    fn same_seed_renders_the_same_ppm() {
+        // The seed is global, every test setting it uses the same one so they can't race
+        init_rng(7);
+        let first = render_ppm();
+        init_rng(7);
+        assert_eq!(first, render_ppm());
+        assert_eq!(rng_seed(), Some(7));
+    }
+
+    #[test]
+    fn random_points_fall_within_their_shapes() {
+        seed_thread_rng(17);
+
+        let (a, b, c) = (
+            Point3::new(0.0, 0.0, 1.0),
+            Point3::new(2.0, 0.0, 1.0),
+            Point3::new(0.0, 1.0, 1.0),
+        );
+        let aabb = AABB::from_points(Point3::new(-1.0, 2.0, 0.0), Point3::new(1.0, 3.0, 0.5));
+        for _ in 0..10_000 {
+            // Inside the triangle `x / 2 + y <= 1` on the plane `z = 1`
+            let p = random_in_triangle(a, b, c);
+            assert!(p.x >= 0.0 && p.y >= 0.0 && p.x / 2.0 + p.y <= 1.0 + 1e-12);
+            assert_eq!(p.z, 1.0);
+
+            let p = random_in_box(&aabb);
+            assert!((0..3).all(|axis| aabb.axis_interval(axis).contains_closed(p[axis])));
+
+            let direction = random_on_sphere_cap(0.9);
+            assert!((direction.length() - 1.0).abs() < 1e-12);
+            assert!(direction.z >= 0.9 - 1e-12);
+        }
+    }
 }
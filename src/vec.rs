@@ -1,10 +1,16 @@
 use lazy_static::lazy_static;
 use std::fmt::Display;
 use std::ops::Add;
+use std::ops::AddAssign;
 use std::ops::Div;
+use std::ops::DivAssign;
 use std::ops::Index;
+use std::ops::IndexMut;
 use std::ops::Mul;
+use std::ops::MulAssign;
+use std::ops::Neg;
 use std::ops::Sub;
+use std::ops::SubAssign;
 
 use crate::interval::Interval;
 use crate::utils::random_f64;
@@ -17,7 +23,7 @@ lazy_static! {
     static ref INTENSITY: Interval = Interval::new(0.0, 0.999);
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default)]
 pub struct Vec3 {
     pub x: f64,
     pub y: f64,
@@ -73,6 +79,19 @@ impl Vec3 {
         Vec3::zero() - on_unit_sphere
     }
 
+    /// Unit direction on the hemisphere around +Z with a density of `cos θ / π`, see `Onb`
+    /// to orient it around a normal
+    ///
+    /// Uniform points on the unit disk projected up onto the hemisphere (Malley's method):
+    /// `r = sqrt(r2)`, `φ = 2π*r1`, `z = sqrt(1 - r2)`
+    pub fn random_cosine_direction() -> Self {
+        let r1 = random_percentage();
+        let r2 = random_percentage();
+        let phi = 2.0 * std::f64::consts::PI * r1;
+        let r = r2.sqrt();
+        Vec3::new(phi.cos() * r, phi.sin() * r, (1.0 - r2).sqrt())
+    }
+
     pub fn random_in_unit_disk() -> Self {
         loop {
             let p = Vec3::new(random_f64(-1.0, 1.0), random_f64(-1.0, 1.0), 0.0);
@@ -87,7 +106,7 @@ impl Vec3 {
     }
 
     pub fn refract(uv: &Vec3, normal: &Vec3, etai_over_etat: f64) -> Self {
-        let cos_theta = uv.negate().dot(&normal).min(1.0);
+        let cos_theta = (-*uv).dot(normal).min(1.0);
         let r_out_perp = etai_over_etat * (*uv + cos_theta * *normal);
         let r_out_parallel = -(1.0 - r_out_perp.squared_length()).abs().sqrt() * *normal;
         r_out_perp + r_out_parallel
@@ -122,35 +141,123 @@ impl Vec3 {
         self.x.abs() < s && self.y.abs() < s && self.z.abs() < s
     }
 
+    pub fn is_finite(&self) -> bool {
+        self.x.is_finite() && self.y.is_finite() && self.z.is_finite()
+    }
+
+    pub fn is_nan(&self) -> bool {
+        self.x.is_nan() || self.y.is_nan() || self.z.is_nan()
+    }
+
+    /// Returns `default` if any component is NaN or infinite
+    pub fn sanitized(&self, default: Vec3) -> Self {
+        if self.is_finite() { *self } else { default }
+    }
+
+    /// Linear interpolation, `a` at `t = 0` and `b` at `t = 1`
+    pub fn lerp(a: &Vec3, b: &Vec3, t: f64) -> Self {
+        (1.0 - t) * *a + t * *b
+    }
+
+    /// Clamps every component to `[min, max]`
+    pub fn clamp(&self, min: f64, max: f64) -> Self {
+        Self::new(
+            self.x.clamp(min, max),
+            self.y.clamp(min, max),
+            self.z.clamp(min, max),
+        )
+    }
+
+    /// Componentwise minimum
+    pub fn min(&self, rhs: &Self) -> Self {
+        Self::new(self.x.min(rhs.x), self.y.min(rhs.y), self.z.min(rhs.z))
+    }
+
+    /// Componentwise maximum
+    pub fn max(&self, rhs: &Self) -> Self {
+        Self::new(self.x.max(rhs.x), self.y.max(rhs.y), self.z.max(rhs.z))
+    }
+
+    pub fn abs(&self) -> Self {
+        Self::new(self.x.abs(), self.y.abs(), self.z.abs())
+    }
+
+    /// Componentwise square root, negative components give NaN
+    pub fn sqrt(&self) -> Self {
+        Self::new(self.x.sqrt(), self.y.sqrt(), self.z.sqrt())
+    }
+
+    #[deprecated(note = "use the unary `-` operator instead")]
     pub fn negate(&self) -> Self {
-        Self {
-            x: -self.x,
-            y: -self.y,
-            z: -self.z,
-        }
+        -*self
     }
 }
 
 impl Color3 {
-    pub fn write(&self, output: &mut String) {
-        fn linear_to_gamma(linear_component: f64) -> f64 {
-            if linear_component > 0.0 {
-                return linear_component.sqrt();
+    /// Relative luminance of a linear color (Rec. 709 weights)
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.x + 0.7152 * self.y + 0.0722 * self.z
+    }
+
+    /// Gamma corrects and clamps the color into 8-bit RGB
+    pub fn to_bytes(&self) -> [u8; 3] {
+        self.to_display_bytes(ToneMap::Clamp, 2.0)
+    }
+
+    /// Linear radiance to 8-bit RGB: tone map, gamma correction, then clamp and quantize
+    ///
+    /// Components are raised to `1/gamma`, a `gamma` of 0 or less writes linear values.
+    pub fn to_display_bytes(&self, tone_map: ToneMap, gamma: f64) -> [u8; 3] {
+        let linear_to_gamma = |linear_component: f64| {
+            if linear_component <= 0.0 {
+                0.0
+            } else if gamma <= 0.0 {
+                linear_component
+            } else {
+                linear_component.powf(1.0 / gamma)
             }
-            0.0
-        }
+        };
 
-        let r = linear_to_gamma(self.x);
-        let g = linear_to_gamma(self.y);
-        let b = linear_to_gamma(self.z);
+        let mapped = tone_map.apply(*self);
+        let r = linear_to_gamma(mapped.x);
+        let g = linear_to_gamma(mapped.y);
+        let b = linear_to_gamma(mapped.z);
 
         let rbyte = INTENSITY.clamp(r) * 256.0;
         let gbyte = INTENSITY.clamp(g) * 256.0;
         let bbyte = INTENSITY.clamp(b) * 256.0;
-        output.push_str(&format!(
-            "{} {} {}\n",
-            rbyte as usize, gbyte as usize, bbyte as usize
-        ));
+        [rbyte as u8, gbyte as u8, bbyte as u8]
+    }
+
+    pub fn write(&self, output: &mut String) {
+        let [r, g, b] = self.to_bytes();
+        output.push_str(&format!("{} {} {}\n", r, g, b));
+    }
+}
+
+/// Compresses linear radiance into the displayable `[0, 1]` range before gamma correction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ToneMap {
+    #[default]
+    Clamp, // Leaves the color as is, anything above 1.0 clips to white
+    Reinhard,
+    AcesFilmic,
+}
+
+impl ToneMap {
+    /// ## Math
+    /// - `Reinhard`: `c / (1 + c)` per channel
+    /// - `AcesFilmic`: Narkowicz's fit of the ACES curve,
+    ///   `c*(2.51c + 0.03) / (c*(2.43c + 0.59) + 0.14)` per channel, clamped to `[0, 1]`
+    pub fn apply(&self, color: Color3) -> Color3 {
+        let map = |c: f64| match self {
+            ToneMap::Clamp => c,
+            ToneMap::Reinhard => c / (1.0 + c),
+            ToneMap::AcesFilmic => {
+                ((c * (2.51 * c + 0.03)) / (c * (2.43 * c + 0.59) + 0.14)).clamp(0.0, 1.0)
+            }
+        };
+        Color3::new(map(color.x), map(color.y), map(color.z))
     }
 }
 
@@ -226,22 +333,172 @@ impl Div<f64> for Vec3 {
     }
 }
 
+impl AddAssign for Vec3 {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for Vec3 {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl MulAssign<f64> for Vec3 {
+    fn mul_assign(&mut self, rhs: f64) {
+        *self = *self * rhs;
+    }
+}
+
+impl DivAssign<f64> for Vec3 {
+    fn div_assign(&mut self, rhs: f64) {
+        *self = *self / rhs;
+    }
+}
+
+impl Neg for Vec3 {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Self {
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        }
+    }
+}
+
 impl Index<usize> for Vec3 {
     type Output = f64;
 
+    /// `0`, `1` and `2` map to `x`, `y` and `z`, any other index panics
     fn index(&self, index: usize) -> &Self::Output {
-        assert!(index < 3);
         match index {
             0 => &self.x,
             1 => &self.y,
             2 => &self.z,
-            _ => unreachable!("You're living in a higher dimention"),
+            _ => panic!("Vec3 index out of range: {index} (expected 0, 1 or 2)"),
         }
     }
 }
 
+impl IndexMut<usize> for Vec3 {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        match index {
+            0 => &mut self.x,
+            1 => &mut self.y,
+            2 => &mut self.z,
+            _ => panic!("Vec3 index out of range: {index} (expected 0, 1 or 2)"),
+        }
+    }
+}
+
+/// Components rounded to 3 decimals, or to the precision given in the format string
+/// (e.g. `{:.1}`)
 impl Display for Vec3 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(&format!("{} {} {}", self.x, self.y, self.z))
+        let precision = f.precision().unwrap_or(3);
+        f.write_str(&format!(
+            "{:.*} {:.*} {:.*}",
+            precision, self.x, precision, self.y, precision, self.z
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn xyz(v: Vec3) -> (f64, f64, f64) {
+        (v.x, v.y, v.z)
+    }
+
+    #[test]
+    fn op_assign_matches_the_binary_operators() {
+        let v = Vec3::new(1.0, -2.0, 3.0);
+        let w = Vec3::new(0.5, 4.0, -1.0);
+
+        // Identities leave the vector untouched
+        let mut u = v;
+        u += Vec3::zero();
+        u -= Vec3::zero();
+        u *= 1.0;
+        u /= 1.0;
+        assert_eq!(xyz(u), xyz(v));
+
+        let mut u = v;
+        u += w;
+        assert_eq!(xyz(u), (1.5, 2.0, 2.0));
+        u -= w;
+        assert_eq!(xyz(u), xyz(v));
+        u *= 2.0;
+        assert_eq!(xyz(u), xyz(2.0 * v));
+        u /= 4.0;
+        assert_eq!(xyz(u), (0.5, -1.0, 1.5));
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn neg_flips_every_component() {
+        let v = Vec3::new(1.0, -2.0, 3.0);
+        assert_eq!(xyz(-v), (-1.0, 2.0, -3.0));
+        assert_eq!(xyz(-v), xyz(v.negate()));
+        assert_eq!(xyz(-(-v)), xyz(v));
+        assert_eq!(xyz(-Vec3::zero() + v), xyz(v));
+    }
+
+    #[test]
+    fn indices_map_to_components() {
+        let mut v = Vec3::new(1.0, 2.0, 3.0);
+        assert_eq!((v[0], v[1], v[2]), (v.x, v.y, v.z));
+
+        v[1] = -5.0;
+        v[2] += 1.0;
+        assert_eq!(xyz(v), (1.0, -5.0, 4.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Vec3 index out of range: 3 (expected 0, 1 or 2)")]
+    fn index_past_z_panics() {
+        let _ = Vec3::zero()[3];
+    }
+
+    #[test]
+    fn tone_maps_of_four() {
+        let four = Color3::new(4.0, 4.0, 4.0);
+        assert_eq!(ToneMap::Clamp.apply(four).x, 4.0);
+        assert!((ToneMap::Reinhard.apply(four).x - 0.8).abs() < 1e-12);
+        assert!((ToneMap::AcesFilmic.apply(four).x - 40.28 / 41.38).abs() < 1e-12);
+
+        // Clamp clips to white, the other two keep it below
+        assert_eq!(four.to_display_bytes(ToneMap::Clamp, 2.0), [255, 255, 255]);
+        assert_eq!(
+            four.to_display_bytes(ToneMap::Reinhard, 2.0),
+            [228, 228, 228]
+        );
+        assert_eq!(
+            four.to_display_bytes(ToneMap::AcesFilmic, 2.0),
+            [252, 252, 252]
+        );
+    }
+
+    #[test]
+    fn componentwise_helpers() {
+        let v = Vec3::new(-2.0, 0.5, 3.0);
+        let w = Vec3::new(1.0, -1.0, 4.0);
+
+        assert_eq!(xyz(Vec3::lerp(&v, &w, 0.0)), xyz(v));
+        assert_eq!(xyz(Vec3::lerp(&v, &w, 1.0)), xyz(w));
+        assert_eq!(xyz(Vec3::lerp(&v, &w, 0.5)), (-0.5, -0.25, 3.5));
+
+        assert_eq!(xyz(v.clamp(-1.0, 1.0)), (-1.0, 0.5, 1.0));
+        assert_eq!(xyz(v.min(&w)), (-2.0, -1.0, 3.0));
+        assert_eq!(xyz(v.max(&w)), (1.0, 0.5, 4.0));
+        assert_eq!(xyz(v.abs()), (2.0, 0.5, 3.0));
+
+        let root = Vec3::new(4.0, 0.25, 9.0).sqrt();
+        assert_eq!(xyz(root), (2.0, 0.5, 3.0));
+        assert!(v.sqrt().x.is_nan());
     }
 }